@@ -2,11 +2,16 @@ mod prelude;
 mod tui;
 mod logging;
 
-use clap::{Parser, ValueEnum}; // Import ValueEnum
+use clap::{Parser, Subcommand, ValueEnum}; // Import ValueEnum
 use tracing::{Level, debug, error, info}; // Import tracing macros and Level
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
-use common::{core::Engine, prelude::*};
+use common::{
+    core::{Engine, SessionId},
+    llm::UsageGrouping,
+    memory::MemoryId,
+    prelude::*,
+};
 use tui::run_tui; // Import the TUI runner function // Import core engine and prelude
 
 // Define the client modes
@@ -34,6 +39,69 @@ struct CliArgs {
     /// Client mode to run (tui, gui, web)
     #[arg(long, value_enum, default_value_t = ClientMode::Tui)]
     client: ClientMode,
+
+    /// Resume an existing conversation session instead of starting a new one
+    #[arg(short, long)]
+    session: Option<SessionId>,
+
+    /// Answer with local providers only and keep the session out of long-term memory
+    #[arg(long, default_value_t = false)]
+    private: bool,
+
+    /// Persona answering the session, as named in the config
+    #[arg(long)]
+    persona: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage stored conversation sessions
+    #[command(subcommand)]
+    Sessions(SessionsCommand),
+    /// Manage long-term memories
+    #[command(subcommand)]
+    Memories(MemoriesCommand),
+    /// Show the tokens, latency and cost of provider calls
+    Usage {
+        /// Total the calls per day or per session
+        #[arg(long, value_enum, default_value_t = UsageBy::Day)]
+        by: UsageBy,
+        /// Only count calls made in the last number of days
+        #[arg(long)]
+        days: Option<u32>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+#[value(rename_all = "lowercase")]
+enum UsageBy {
+    Day,
+    Session,
+}
+
+#[derive(Subcommand, Debug)]
+enum SessionsCommand {
+    /// List stored conversation sessions
+    List,
+    /// Delete a conversation session and its history
+    Drop {
+        /// Id of the session to delete
+        id: SessionId,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum MemoriesCommand {
+    /// List stored memories
+    List,
+    /// Delete a memory so it is no longer recalled
+    Forget {
+        /// Id of the memory to delete
+        id: MemoryId,
+    },
 }
 
 #[async_std::main]
@@ -59,12 +127,47 @@ async fn main() -> Result<()> {
         }
     };
 
+    if let Some(command) = args.command {
+        return run_command(&engine, command).await;
+    }
+
+    // Only answering prompts needs the providers
+    if let Err(e) = engine.probe().await {
+        error!("Providers are not usable: {}", e);
+        return Err(e);
+    }
+
+    // --- Resume the requested session or start a new one ---
+    let session_id = match args.session {
+        Some(session_id) => engine.resume_session(session_id).await?.id,
+        None => engine.create_session().await?,
+    };
+    info!("Using conversation session {}", session_id);
+    if args.private {
+        engine.set_session_private(session_id, true).await?;
+    }
+    if let Some(persona) = args.persona {
+        engine
+            .set_session_persona(session_id, Some(persona))
+            .await?;
+    }
+
     // --- Handle initial prompt if provided ---
     if let Some(initial_prompt) = args.prompt {
         info!("Processing initial prompt: '{}'", initial_prompt);
-        match engine.process_prompt(&initial_prompt).await {
+        match engine.process_prompt(session_id, &initial_prompt).await {
             Ok(response) => {
-                println!("Assistant: {}", response); // Print response directly
+                println!("Assistant: {}", response.content); // Print response directly
+                for memory in &response.memories {
+                    eprintln!("Memory ({:.2}): {}", memory.score, memory.record.content);
+                }
+                if let Some(context) = &response.usage.context {
+                    eprintln!(
+                        "Context: ~{} of {} tokens ({} message(s) left out)",
+                        context.estimated_tokens, context.context_window, context.trimmed_messages
+                    );
+                }
+                eprintln!("Session: {}", session_id);
             }
             Err(e) => {
                 error!("Error processing initial prompt: {}", e);
@@ -78,7 +181,7 @@ async fn main() -> Result<()> {
         match args.client {
             ClientMode::Tui => {
                 info!("Entering TUI mode...");
-                if let Err(e) = run_tui(&engine).await {
+                if let Err(e) = run_tui(&engine, session_id).await {
                     error!("TUI application error: {}", e);
                     // Optionally return the error to exit the CLI with an error code
                     // return Err(e);
@@ -100,6 +203,83 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Runs a non-interactive subcommand
+async fn run_command(engine: &Engine, command: Command) -> Result<()> {
+    match command {
+        Command::Sessions(SessionsCommand::List) => {
+            let sessions = engine.list_sessions().await?;
+            if sessions.is_empty() {
+                println!("No conversation sessions found.");
+            }
+            for session in sessions {
+                println!(
+                    "{}  {}  {:>3} messages  {}",
+                    session.id,
+                    session.updated_at.format("%Y-%m-%d %H:%M"),
+                    session.message_count,
+                    session.title
+                );
+            }
+        }
+        Command::Sessions(SessionsCommand::Drop { id }) => {
+            engine.drop_session(id).await?;
+            println!("Dropped session {}", id);
+        }
+        Command::Memories(MemoriesCommand::List) => {
+            let memories = engine.list_memories().await?;
+            if memories.is_empty() {
+                println!("No memories stored.");
+            }
+            for memory in memories {
+                println!(
+                    "{}  {}  {}",
+                    memory.id,
+                    memory.created_at.format("%Y-%m-%d %H:%M"),
+                    memory.content
+                );
+            }
+        }
+        Command::Memories(MemoriesCommand::Forget { id }) => {
+            engine.forget_memory(id).await?;
+            println!("Forgot memory {}", id);
+        }
+        Command::Usage { by, days } => {
+            let grouping = match by {
+                UsageBy::Day => UsageGrouping::Day,
+                UsageBy::Session => UsageGrouping::Session,
+            };
+            let summaries = engine.usage_summary(grouping, days)?;
+            if summaries.is_empty() {
+                println!("No provider calls recorded.");
+            }
+            let mut any_estimated = false;
+            for summary in summaries {
+                let cost = summary
+                    .cost
+                    .map_or_else(|| String::from("-"), |cost| format!("{:.4}", cost));
+                // Token counts of some calls are estimated rather than reported
+                let approx = if summary.estimated_calls > 0 { "~" } else { "" };
+                any_estimated |= summary.estimated_calls > 0;
+                println!(
+                    "{:<36}  {:<18}  {:>5} calls  {:>9} in  {:>9} out  {:>6} ms/call  {:>8}",
+                    summary.group,
+                    format!("{:?}/{}", summary.provider, summary.model),
+                    summary.calls,
+                    format!("{}{}", approx, summary.input_tokens),
+                    format!("{}{}", approx, summary.output_tokens),
+                    summary.latency_ms / summary.calls.max(1) as u64,
+                    cost
+                );
+            }
+            if any_estimated {
+                println!("~ Includes estimated token counts");
+            }
+        }
+    }
+
+    Ok(())
+}
+
 // TODO: Define run_tui function (LYN-17)
 // async fn run_tui(engine: &Engine) -> Result<()> {
 //     // Setup terminal, run TUI loop, restore terminal
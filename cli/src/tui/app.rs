@@ -1,6 +1,9 @@
 //! TUI Application State
 
+use std::collections::VecDeque;
+
 use common::config::AppConfig;
+use common::core::{ApprovalRequest, CancellationToken, Role, ToolApprovals, TranscriptEntry};
 use common::prelude::*;
 use tui_framework_experiment::button::Button;
 
@@ -26,10 +29,23 @@ pub struct AppState {
     pub input: String,
     /// History of messages (user prompts and assistant responses) as plain strings.
     pub messages: Vec<String>, // Store plain Strings
+    /// Running summary standing in for the compacted start of the conversation.
+    pub earlier: Option<String>,
+    /// Whether the summary of the earlier conversation is shown in full.
+    pub earlier_expanded: bool,
     /// Accumulates the current streaming response before parsing.
     pub current_response: String,
+    /// Accumulates the model's thought process for the current response.
+    pub current_reasoning: String,
     /// Current status message or indicator.
     pub status: String,
+    /// Stops the answers to the prompts sent, in the order the engine answers them; the
+    /// first belongs to the response being streamed.
+    pub cancels: VecDeque<CancellationToken>,
+    /// Answers the engine's requests to approve tool calls.
+    pub approvals: Option<ToolApprovals>,
+    /// Tool call waiting for the user to approve or deny it.
+    pub pending_approval: Option<ApprovalRequest>,
     /// Vertical scroll offset for the messages area.
     pub scroll_offset: u16,
     /// Whether the message view should automatically scroll to the bottom.
@@ -63,8 +79,14 @@ impl AppState {
         Self {
             input: String::new(),
             messages: Vec::new(),
+            earlier: None,
+            earlier_expanded: false,
             current_response: String::new(), // Initialize empty
+            current_reasoning: String::new(),
             status: "Ready. Type your prompt and press Enter.".to_string(),
+            cancels: VecDeque::new(),
+            approvals: None,
+            pending_approval: None,
             scroll_offset: 0,
             is_auto_scrolling: true, // Default to auto-scrolling
             mode: AppMode::Chat,
@@ -83,12 +105,83 @@ impl AppState {
         state
     }
 
+    /// Fills the message view with the transcript of a resumed session. Compacted messages
+    /// are left to the summary block.
+    pub fn load_transcript(&mut self, transcript: &[TranscriptEntry]) {
+        self.messages = transcript
+            .iter()
+            .filter(|entry| !entry.compacted)
+            .map(|entry| match entry.role {
+                Role::User => format!("> {}", entry.content),
+                Role::Assistant if entry.interrupted => {
+                    format!("Assistant: {} [interrupted]", entry.content)
+                }
+                Role::Assistant => format!("Assistant: {}", entry.content),
+            })
+            .collect();
+        self.scroll_offset = u16::MAX;
+    }
+
+    /// Shows or hides the full summary of the earlier conversation.
+    pub fn toggle_earlier(&mut self) {
+        self.earlier_expanded = !self.earlier_expanded;
+    }
+
+    /// The collapsible "earlier in this conversation" block shown above the messages, if the
+    /// conversation was compacted.
+    pub fn earlier_block(&self) -> Option<String> {
+        let summary = self.earlier.as_ref()?;
+        Some(if self.earlier_expanded {
+            format!(
+                "▾ Earlier in this conversation (Ctrl+E to hide)\n{}\n",
+                summary
+            )
+        } else {
+            "▸ Earlier in this conversation (Ctrl+E to show)".to_string()
+        })
+    }
+
+    /// Stops the response being streamed; the engine reports the partial answer and goes on
+    /// with any prompt queued behind it.
+    pub fn cancel_response(&mut self) {
+        if let Some(cancel) = self.cancels.front() {
+            cancel.cancel();
+            self.status = "Stopping...".to_string();
+        }
+        // The engine stops waiting for the answer once cancelled
+        self.pending_approval = None;
+    }
+
+    /// Approves or denies the tool call waiting for an answer, if any.
+    pub fn answer_approval(&mut self, approved: bool) {
+        let Some(request) = self.pending_approval.take() else {
+            return;
+        };
+        let answered = self
+            .approvals
+            .as_ref()
+            .map(|approvals| approvals.answer(request.id, approved));
+        match answered {
+            Some(Ok(())) => {
+                let outcome = if approved { "Approved" } else { "Denied" };
+                self.messages
+                    .push(format!("⚙ {} tool `{}`", outcome, request.tool));
+                self.status = "Processing... (Esc to stop)".to_string();
+            }
+            Some(Err(e)) => {
+                error!("Failed to answer tool approval: {}", e);
+                self.status = format!("Error: {}", e);
+            }
+            None => error!("Tool approval requested but no approvals handle was set"),
+        }
+    }
+
     /// Toggles the settings dialog
     pub fn toggle_settings(&mut self) {
         match self.mode {
             AppMode::Chat => {
                 self.mode = AppMode::Settings;
-                self.status = "Settings: Use arrow keys to navigate, Enter to edit, Tab to switch sections, Esc to exit".to_string();
+                self.status = "Settings: Use arrow keys to navigate, ←/→ to change options, Enter to edit, Tab to switch sections, Esc to exit".to_string();
                 // Update button state to pressed
                 self.settings_button.press();
                 // Set expand button to normal state (expanded by default)
@@ -2,4 +2,4 @@
 
 pub mod task;
 
-pub use task::spawn_engine_task;
+pub use task::{PromptRequest, spawn_engine_task};
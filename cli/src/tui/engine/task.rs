@@ -2,41 +2,56 @@
 
 use async_channel::{Receiver, Sender};
 use async_std::task;
-use common::core::Engine;
+use common::core::{CancellationToken, Engine, SessionId};
 use common::prelude::*;
 use futures_util::StreamExt;
 
 use crate::tui::events::StreamEvent;
 
-/// Spawns a task that processes prompts using the engine
+/// A prompt sent to the engine task, with the token that stops its answer
+#[derive(Debug, Clone)]
+pub struct PromptRequest {
+    pub text: String,
+    pub cancel: CancellationToken,
+    /// Persona picked in the settings, applied to the session before answering
+    pub persona: Option<String>,
+}
+
+/// Spawns a task that processes prompts of a session using the engine
 pub fn spawn_engine_task(
     engine: Engine,
-    prompt_rx: Receiver<String>,
+    session_id: SessionId,
+    prompt_rx: Receiver<PromptRequest>,
     event_tx: Sender<StreamEvent>,
 ) -> task::JoinHandle<()> {
     task::spawn(async move {
         info!("Engine task started.");
         // Clone the event sender for the task
         let event_tx_clone = event_tx.clone();
+        let mut persona = None;
         while let Ok(prompt) = prompt_rx.recv().await {
-            debug!("Engine task received prompt: '{}'", prompt);
-            match engine.process_prompt_stream(prompt).await {
+            debug!("Engine task received prompt: '{}'", prompt.text);
+            if prompt.persona.is_some() && prompt.persona != persona {
+                if let Err(e) = engine
+                    .set_session_persona(session_id, prompt.persona.clone())
+                    .await
+                {
+                    warn!("Failed to switch persona: {}", e);
+                }
+                persona = prompt.persona;
+            }
+            match engine
+                .process_prompt_stream(session_id, prompt.text, prompt.cancel)
+                .await
+            {
                 Ok(mut stream) => {
-                    while let Some(result) = stream.next().await {
-                        let event = match result {
-                            Ok(chunk) => StreamEvent::Chunk(chunk),
-                            Err(e) => StreamEvent::Error(e.to_string()),
-                        };
-                        if event_tx_clone.send(event).await.is_err() {
+                    while let Some(event) = stream.next().await {
+                        if event_tx_clone.send(StreamEvent::from(event)).await.is_err() {
                             warn!("Engine task failed to send stream event: TUI receiver dropped.");
                             // Break inner loop, outer loop will check recv again
                             break;
                         }
                     }
-                    // Send End event after stream finishes (if receiver still exists)
-                    if event_tx_clone.send(StreamEvent::End).await.is_err() {
-                        warn!("Engine task failed to send End event: TUI receiver dropped.");
-                    }
                 }
                 Err(e) => {
                     // Send Error event if stream creation failed
@@ -53,3 +68,59 @@ pub fn spawn_engine_task(
         info!("Engine task finished.");
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::{
+        config::AppConfig,
+        core::SessionStore,
+        llm::{MockProvider, MockReply},
+        memory::InMemoryStore,
+    };
+
+    use super::*;
+
+    #[async_std::test]
+    async fn test_engine_task_streams_events() {
+        let provider = Arc::new(MockProvider::scripted([
+            MockReply::chunks(["Hello", " there"]),
+            MockReply::text("User greeted Lyn"),
+        ]));
+        let engine = Engine::from_parts(
+            Arc::new(AppConfig::default()),
+            provider.clone(),
+            provider,
+            SessionStore::in_memory(),
+            Arc::new(InMemoryStore::new()),
+        );
+        let session_id = engine.create_session().await.unwrap();
+
+        let (prompt_tx, prompt_rx) = async_channel::unbounded();
+        let (event_tx, event_rx) = async_channel::unbounded();
+        let handle = spawn_engine_task(engine, session_id, prompt_rx, event_tx);
+        prompt_tx
+            .send(PromptRequest {
+                text: "Hi".to_string(),
+                cancel: CancellationToken::new(),
+                persona: None,
+            })
+            .await
+            .unwrap();
+
+        let mut chunks = String::new();
+        loop {
+            match event_rx.recv().await.unwrap() {
+                StreamEvent::Chunk(chunk) => chunks.push_str(&chunk),
+                StreamEvent::End => break,
+                StreamEvent::Error(e) => panic!("engine task failed: {}", e),
+                _ => {}
+            }
+        }
+        assert_eq!(chunks, "Hello there");
+
+        drop(prompt_tx);
+        handle.await;
+    }
+}
@@ -7,7 +7,10 @@ use common::prelude::*;
 use crossterm::event::{self, Event, KeyCode, KeyModifiers, MouseEvent, MouseEventKind, MouseButton};
 use ratatui::{Terminal, backend::Backend};
 
+use common::core::CancellationToken;
+
 use crate::tui::app::{AppMode, AppState};
+use crate::tui::engine::PromptRequest;
 use crate::tui::events::stream::StreamEvent;
 use crate::tui::ui::render::draw_ui;
 
@@ -15,7 +18,7 @@ use crate::tui::ui::render::draw_ui;
 pub async fn handle_key_event(
     key_event: crossterm::event::KeyEvent,
     app_state: &mut AppState,
-    prompt_tx: &Sender<String>,
+    prompt_tx: &Sender<PromptRequest>,
 ) -> Result<bool> {
     // Debug log to see what modifiers are being detected - now goes to stderr
     debug!(
@@ -40,6 +43,14 @@ pub async fn handle_key_event(
                 match c.to_ascii_lowercase() {
                     'c' => return Ok(true), // Ctrl+C
                     'd' => return Ok(true), // Ctrl+D
+                    'x' => {
+                        app_state.cancel_response(); // Ctrl+X
+                        return Ok(false);
+                    }
+                    'e' => {
+                        app_state.toggle_earlier(); // Ctrl+E
+                        return Ok(false);
+                    }
                     '.' => {
                         app_state.toggle_settings();
                         return Ok(false);
@@ -59,6 +70,11 @@ pub async fn handle_key_event(
         return Ok(false);
     }
 
+    // A tool call waiting for approval takes every key until it is answered
+    if app_state.pending_approval.is_some() {
+        return Ok(handle_approval_key(key_event, app_state));
+    }
+
     // Handle keys based on current mode
     match app_state.mode {
         AppMode::Chat => handle_chat_mode_key(key_event, app_state, prompt_tx).await,
@@ -68,11 +84,21 @@ pub async fn handle_key_event(
     }
 }
 
+/// Handles key events while a tool call waits for approval
+fn handle_approval_key(key_event: crossterm::event::KeyEvent, app_state: &mut AppState) -> bool {
+    match key_event.code {
+        KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => app_state.answer_approval(true),
+        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => app_state.answer_approval(false),
+        _ => {}
+    }
+    false // Continue loop
+}
+
 /// Handles key events in chat mode
 async fn handle_chat_mode_key(
     key_event: crossterm::event::KeyEvent,
     app_state: &mut AppState,
-    prompt_tx: &Sender<String>,
+    prompt_tx: &Sender<PromptRequest>,
 ) -> Result<bool> {
     match key_event.code {
         KeyCode::Enter => {
@@ -81,11 +107,18 @@ async fn handle_chat_mode_key(
                 // Format user input and push as plain string
                 let formatted_prompt = format!("> {}", prompt_text);
                 app_state.messages.push(formatted_prompt); // Push String directly
-                app_state.status = "Processing...".to_string();
+                app_state.status = "Processing... (Esc to stop)".to_string();
                 app_state.is_auto_scrolling = true; // Re-enable auto-scroll on new prompt
                 app_state.current_response.clear(); // Clear accumulator for new response
                 // Send the raw prompt text to the engine task
-                if prompt_tx.send(prompt_text).await.is_err() {
+                let cancel = CancellationToken::new();
+                app_state.cancels.push_back(cancel.clone());
+                let request = PromptRequest {
+                    text: prompt_text,
+                    cancel,
+                    persona: app_state.settings.persona().map(str::to_string),
+                };
+                if prompt_tx.send(request).await.is_err() {
                     error!("Failed to send prompt to engine task: channel closed.");
                     // Update status instead of breaking
                     app_state.status =
@@ -93,6 +126,9 @@ async fn handle_chat_mode_key(
                 }
             }
         }
+        KeyCode::Esc => {
+            app_state.cancel_response();
+        }
         KeyCode::Char(c) => {
             app_state.input.push(c);
         }
@@ -154,6 +190,18 @@ fn handle_settings_mode_key(
         KeyCode::Down => {
             app_state.settings.select_next();
         }
+        KeyCode::Left | KeyCode::Right => {
+            // Change the option of a dropdown, e.g. to pick a model
+            if let Some(setting) = app_state.settings.selected_setting()
+                && let crate::tui::settings::SettingValue::Dropdown { .. } = &setting.value
+            {
+                app_state
+                    .settings
+                    .step_dropdown(key_event.code == KeyCode::Right)
+                    .ok();
+                app_state.settings_modified = true;
+            }
+        }
         KeyCode::Char(' ') => {
             // Toggle dropdown expansion
             if let Some(setting) = app_state.settings.selected_setting() {
@@ -269,11 +317,11 @@ pub fn handle_stream_event(stream_event: StreamEvent, app_state: &mut AppState)
         StreamEvent::Chunk(chunk) => {
             // Append chunk to accumulator
             app_state.current_response.push_str(&chunk);
-            // If it's the first chunk, add a new message entry
+            // If it's the first chunk (or the first after a tool call), add a new message entry
             if app_state
                 .messages
                 .last()
-                .is_none_or(|m| m.starts_with("> "))
+                .is_none_or(|m| !m.starts_with("Assistant: "))
             {
                 app_state
                     .messages
@@ -288,18 +336,95 @@ pub fn handle_stream_event(stream_event: StreamEvent, app_state: &mut AppState)
                 app_state.scroll_offset = u16::MAX;
             }
         }
+        StreamEvent::Memories(memories) => {
+            for memory in memories {
+                app_state.messages.push(format!("📎 Remembered: {}", memory));
+            }
+            if app_state.is_auto_scrolling {
+                app_state.scroll_offset = u16::MAX;
+            }
+        }
+        StreamEvent::Compacted(summary) => {
+            // Messages already on screen stay; they are left to the block on the next resume
+            app_state.earlier = Some(summary);
+        }
+        StreamEvent::Reasoning(chunk) => {
+            // Keep the thought process out of the answer, only hint that it is happening
+            app_state.current_reasoning.push_str(&chunk);
+            app_state.status = "Thinking...".to_string();
+        }
+        StreamEvent::ToolCall(name) => {
+            // Text after the tool call belongs to a new assistant message
+            app_state.current_response.clear();
+            app_state.messages.push(format!("⚙ Running tool `{}`...", name));
+            app_state.status = format!("Running tool `{}`...", name);
+            if app_state.is_auto_scrolling {
+                app_state.scroll_offset = u16::MAX;
+            }
+        }
+        StreamEvent::ApprovalRequired(request) => {
+            app_state.status = format!("Allow tool `{}` to run? (y/n)", request.tool);
+            app_state.pending_approval = Some(request);
+        }
+        StreamEvent::ToolResult { name, is_error } => {
+            let outcome = if is_error { "failed" } else { "finished" };
+            app_state
+                .messages
+                .push(format!("⚙ Tool `{}` {}", name, outcome));
+            app_state.status = "Streaming...".to_string();
+            if app_state.is_auto_scrolling {
+                app_state.scroll_offset = u16::MAX;
+            }
+        }
+        StreamEvent::Usage(usage) => {
+            debug!(
+                "Response used {} request(s) and {} tool call(s) in {} ms",
+                usage.requests, usage.tool_calls, usage.elapsed_ms
+            );
+            if let Some(context) = usage.context
+                && context.trimmed_messages > 0
+            {
+                app_state.messages.push(format!(
+                    "⚙ Left out the {} oldest message(s) to fit the model's context window",
+                    context.trimmed_messages
+                ));
+            }
+        }
         StreamEvent::End => {
+            // Each answer ends with exactly one of End, Interrupted or Error
+            app_state.cancels.pop_front();
             app_state.current_response.clear(); // Clear accumulator
+            app_state.current_reasoning.clear();
             app_state.status = "Ready. Type your prompt and press Enter.".to_string();
             // Auto-scroll logic (repeat as after chunk): Use u16::MAX
             if app_state.is_auto_scrolling {
                 app_state.scroll_offset = u16::MAX;
             }
         }
+        StreamEvent::Interrupted(partial) => {
+            // Keep what was said so far, marked so it is not mistaken for a full answer
+            match app_state.messages.last_mut() {
+                Some(last) if !partial.is_empty() && last.starts_with("Assistant: ") => {
+                    last.push_str(" [interrupted]");
+                }
+                _ => app_state
+                    .messages
+                    .push("Assistant: [interrupted]".to_string()),
+            }
+            app_state.cancels.pop_front();
+            app_state.current_response.clear();
+            app_state.current_reasoning.clear();
+            app_state.status = "Stopped. Ready.".to_string();
+            if app_state.is_auto_scrolling {
+                app_state.scroll_offset = u16::MAX;
+            }
+        }
         StreamEvent::Error(e) => {
+            app_state.cancels.pop_front();
             // Format error message and push as plain string
             app_state.messages.push(format!("Error: {}", e)); // Push String directly
             app_state.current_response.clear(); // Clear accumulator on error too
+            app_state.current_reasoning.clear();
             app_state.status = "Error occurred. Ready.".to_string();
             // Auto-scroll logic for error message
             if app_state.is_auto_scrolling {
@@ -313,7 +438,7 @@ pub fn handle_stream_event(stream_event: StreamEvent, app_state: &mut AppState)
 pub async fn handle_events<B: Backend>(
     terminal: &mut Terminal<B>,
     app_state: &mut AppState,
-    prompt_tx: Sender<String>,
+    prompt_tx: Sender<PromptRequest>,
     event_rx: Receiver<StreamEvent>,
 ) -> Result<()> {
     loop {
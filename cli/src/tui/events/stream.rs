@@ -1,12 +1,55 @@
 //! Stream event types and handling
 
+use common::core::{ApprovalRequest, EngineEvent, Usage};
+
 /// Events that can occur during streaming responses from the engine
 #[derive(Debug)]
 pub enum StreamEvent {
+    /// Stored memories the response is based on
+    Memories(Vec<String>),
+    /// Older messages were folded into the conversation's running summary
+    Compacted(String),
     /// A piece of the response stream
     Chunk(String),
+    /// A piece of the model's thought process
+    Reasoning(String),
+    /// The model started a tool call
+    ToolCall(String),
+    /// A tool call waits for the user to approve or deny it
+    ApprovalRequired(ApprovalRequest),
+    /// A tool call finished
+    ToolResult { name: String, is_error: bool },
+    /// Resources consumed by the response
+    Usage(Usage),
     /// Stream finished successfully
     End,
+    /// The response was stopped; carries the partial answer
+    Interrupted(String),
     /// An error occurred during streaming
     Error(String),
 }
+
+impl From<EngineEvent> for StreamEvent {
+    fn from(event: EngineEvent) -> Self {
+        match event {
+            EngineEvent::MemoriesRecalled(memories) => StreamEvent::Memories(
+                memories
+                    .into_iter()
+                    .map(|memory| memory.record.content)
+                    .collect(),
+            ),
+            EngineEvent::Compacted { summary, .. } => StreamEvent::Compacted(summary),
+            EngineEvent::TextDelta(chunk) => StreamEvent::Chunk(chunk),
+            EngineEvent::Reasoning(chunk) => StreamEvent::Reasoning(chunk),
+            EngineEvent::ToolCallStarted { name, .. } => StreamEvent::ToolCall(name),
+            EngineEvent::ApprovalRequired(request) => StreamEvent::ApprovalRequired(request),
+            EngineEvent::ToolResult { name, is_error, .. } => {
+                StreamEvent::ToolResult { name, is_error }
+            }
+            EngineEvent::Usage(usage) => StreamEvent::Usage(usage),
+            EngineEvent::Done { .. } => StreamEvent::End,
+            EngineEvent::Interrupted { content } => StreamEvent::Interrupted(content),
+            EngineEvent::Error(e) => StreamEvent::Error(e),
+        }
+    }
+}
@@ -8,17 +8,23 @@ pub mod terminal;
 pub mod ui;
 
 use async_channel::{Receiver, Sender, unbounded};
-use common::{core::Engine, prelude::*};
+use common::{
+    core::{Engine, SessionId},
+    prelude::*,
+};
 
 use app::AppState;
-use engine::spawn_engine_task;
+use engine::{PromptRequest, spawn_engine_task};
 use events::{handle_events, StreamEvent};
 use terminal::{restore_terminal, setup_terminal};
 
-/// Runs the TUI application
-pub async fn run_tui(engine: &Engine) -> Result<()> {
+/// Runs the TUI application for the given conversation session
+pub async fn run_tui(engine: &Engine, session_id: SessionId) -> Result<()> {
     info!("Initializing TUI...");
 
+    // Load the session before touching the terminal so errors are printed normally
+    let session = engine.resume_session(session_id).await?;
+
     // --- Terminal Setup ---
     let mut terminal = setup_terminal()?;
 
@@ -26,16 +32,27 @@ pub async fn run_tui(engine: &Engine) -> Result<()> {
     // Get the config from the engine to initialize settings
     let config = engine.get_config();
     let mut app_state = AppState::with_config(&config);
+    app_state.approvals = Some(engine.approvals());
+    app_state.load_transcript(&session.transcript());
+    app_state.earlier = session.summary.clone();
+    if let Some(persona) = &session.persona {
+        app_state.settings.select_persona(persona);
+    }
+    // Offer the provider's models to pick from; the settings stay editable without them
+    match engine.list_models().await {
+        Ok(models) => app_state.settings.set_available_models(&models),
+        Err(e) => warn!("Failed to list models: {}", e),
+    }
     let engine_clone = engine.clone(); // Clone engine for async task
 
     // --- Create channels for communication ---
     // TUI -> Engine Task (Send prompts)
-    let (prompt_tx, prompt_rx): (Sender<String>, Receiver<String>) = unbounded();
+    let (prompt_tx, prompt_rx): (Sender<PromptRequest>, Receiver<PromptRequest>) = unbounded();
     // Engine Task -> TUI (Send stream events)
     let (event_tx, event_rx): (Sender<StreamEvent>, Receiver<StreamEvent>) = unbounded();
 
     // --- Spawn the engine processing task ---
-    let engine_task = spawn_engine_task(engine_clone, prompt_rx, event_tx);
+    let engine_task = spawn_engine_task(engine_clone, session_id, prompt_rx, event_tx);
 
     // --- Main Event Loop ---
     let run_result = handle_events(&mut terminal, &mut app_state, prompt_tx, event_rx).await;
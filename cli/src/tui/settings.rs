@@ -4,7 +4,8 @@ use std::collections::HashMap;
 
 use common::{
     config::{self, AppConfig},
-    llm::{LLMProviders, GeminiProviderConfig, OllamaProviderConfig},
+    core::{DEFAULT_PERSONA, Persona},
+    llm::{LLMProviders, GeminiProviderConfig, ModelInfo, OllamaProviderConfig},
     prelude::*,
 };
 
@@ -33,6 +34,20 @@ pub enum SettingValue {
     },
 }
 
+impl SettingValue {
+    /// The value as text, for settings that hold a string or a selected option
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            SettingValue::String(value) => Some(value),
+            SettingValue::Enum { current, .. } => Some(current),
+            SettingValue::Dropdown { selected, options, .. } => {
+                options.get(*selected).map(String::as_str)
+            }
+            SettingValue::Bool(_) => None,
+        }
+    }
+}
+
 /// Represents a single setting in the settings dialog
 #[derive(Debug, Clone)]
 pub struct Setting {
@@ -88,13 +103,37 @@ impl Settings {
                 selected: match config.provider {
                     LLMProviders::Ollama => 0,
                     LLMProviders::Gemini => 1,
+                    LLMProviders::OpenAICompatible => 2,
+                    LLMProviders::Mock => 3,
                 },
-                options: vec!["Ollama".to_string(), "Gemini".to_string()],
+                options: vec![
+                    "Ollama".to_string(),
+                    "Gemini".to_string(),
+                    "OpenAI Compatible".to_string(),
+                    "Mock".to_string(),
+                ],
                 expanded: false,
             },
             description: "The LLM provider to use for generating responses".to_string(),
         });
 
+        // Persona answering the current session
+        let personas = Persona::names(config);
+        let default_persona = config.default_persona.as_deref().unwrap_or(DEFAULT_PERSONA);
+        general_settings.push(Setting {
+            key: "persona".to_string(),
+            name: "Persona".to_string(),
+            value: SettingValue::Dropdown {
+                selected: personas
+                    .iter()
+                    .position(|name| name == default_persona)
+                    .unwrap_or_default(),
+                options: personas,
+                expanded: false,
+            },
+            description: "The persona answering this session".to_string(),
+        });
+
         // Create Ollama provider config
         let mut ollama_settings = Vec::new();
 
@@ -184,6 +223,44 @@ impl Settings {
             is_selected: config.provider == LLMProviders::Gemini,
         });
 
+        // Create OpenAI-compatible provider config
+        let openai_compatible = &config.provider_configs.openai_compatible;
+        let openai_compatible_settings = vec![
+            Setting {
+                key: "openai_compatible.base_url".to_string(),
+                name: "Base URL".to_string(),
+                value: SettingValue::String(openai_compatible.base_url.to_string()),
+                description: "The API root of the server, including the version (e.g. /v1)"
+                    .to_string(),
+            },
+            Setting {
+                key: "openai_compatible.api_key".to_string(),
+                name: "API Key".to_string(),
+                value: SettingValue::String(openai_compatible.api_key.clone().unwrap_or_default()),
+                description: "The API key for the server, if it requires one".to_string(),
+            },
+            Setting {
+                key: "openai_compatible.model".to_string(),
+                name: "Model".to_string(),
+                value: SettingValue::String(openai_compatible.model.clone()),
+                description: "The model to use with the server".to_string(),
+            },
+            Setting {
+                key: "openai_compatible.embedding_model".to_string(),
+                name: "Embedding Model".to_string(),
+                value: SettingValue::String(
+                    openai_compatible.embedding_model.clone().unwrap_or_default(),
+                ),
+                description: "The model to use for embeddings with the server".to_string(),
+            },
+        ];
+
+        providers.push(ProviderConfig {
+            name: "OpenAI Compatible".to_string(),
+            settings: openai_compatible_settings,
+            is_selected: config.provider == LLMProviders::OpenAICompatible,
+        });
+
         Self {
             settings: general_settings,
             providers,
@@ -204,6 +281,8 @@ impl Settings {
                         match options[*selected].as_str() {
                             "Ollama" => config.provider = LLMProviders::Ollama,
                             "Gemini" => config.provider = LLMProviders::Gemini,
+                            "OpenAI Compatible" => config.provider = LLMProviders::OpenAICompatible,
+                            "Mock" => config.provider = LLMProviders::Mock,
                             _ => return Err(Error::Config(config::ConfigError::ValidationError(
                                 format!("Invalid provider: {}", options[*selected])
                             ))),
@@ -237,13 +316,13 @@ impl Settings {
                                 }
                             }
                             "ollama.model" => {
-                                if let SettingValue::String(value) = &setting.value {
-                                    config.provider_configs.ollama.model = value.clone();
+                                if let Some(value) = setting.value.as_text() {
+                                    config.provider_configs.ollama.model = value.to_string();
                                 }
                             }
                             "ollama.embedding_model" => {
-                                if let SettingValue::String(value) = &setting.value {
-                                    config.provider_configs.ollama.embedding_model = value.clone();
+                                if let Some(value) = setting.value.as_text() {
+                                    config.provider_configs.ollama.embedding_model = value.to_string();
                                 }
                             }
                             _ => {}
@@ -264,13 +343,13 @@ impl Settings {
                                 }
                             }
                             "gemini.model" => {
-                                if let SettingValue::String(value) = &setting.value {
-                                    model = value.clone();
+                                if let Some(value) = setting.value.as_text() {
+                                    model = value.to_string();
                                 }
                             }
                             "gemini.embedding_model" => {
-                                if let SettingValue::String(value) = &setting.value {
-                                    embedding_model = value.clone();
+                                if let Some(value) = setting.value.as_text() {
+                                    embedding_model = value.to_string();
                                 }
                             }
                             _ => {}
@@ -304,6 +383,36 @@ impl Settings {
                         }
                     }
                 }
+                "OpenAI Compatible" => {
+                    let openai_compatible = &mut config.provider_configs.openai_compatible;
+                    for setting in &provider.settings {
+                        let Some(value) = setting.value.as_text() else {
+                            continue;
+                        };
+                        match setting.key.as_str() {
+                            "openai_compatible.base_url" => {
+                                openai_compatible.base_url = value.parse().map_err(|_| {
+                                    Error::Config(config::ConfigError::ValidationError(format!(
+                                        "Invalid base URL: {}",
+                                        value
+                                    )))
+                                })?;
+                            }
+                            "openai_compatible.api_key" => {
+                                openai_compatible.api_key =
+                                    Some(value.to_string()).filter(|key| !key.is_empty());
+                            }
+                            "openai_compatible.model" => {
+                                openai_compatible.model = value.to_string();
+                            }
+                            "openai_compatible.embedding_model" => {
+                                openai_compatible.embedding_model =
+                                    Some(value.to_string()).filter(|model| !model.is_empty());
+                            }
+                            _ => {}
+                        }
+                    }
+                }
                 _ => {}
             }
         }
@@ -397,6 +506,93 @@ impl Settings {
         Ok(())
     }
 
+    /// The persona picked for the session
+    pub fn persona(&self) -> Option<&str> {
+        self.settings
+            .iter()
+            .find(|setting| setting.key == "persona")
+            .and_then(|setting| setting.value.as_text())
+    }
+
+    /// Shows the persona a resumed session already uses
+    pub fn select_persona(&mut self, persona: &str) {
+        let setting = self.settings.iter_mut().find(|setting| setting.key == "persona");
+        if let Some(Setting {
+            value: SettingValue::Dropdown { selected, options, .. },
+            ..
+        }) = setting
+            && let Some(index) = options.iter().position(|option| option == persona)
+        {
+            *selected = index;
+        }
+    }
+
+    /// Moves a dropdown setting to its next or previous option
+    pub fn step_dropdown(&mut self, forward: bool) -> Result<()> {
+        let Some(setting) = self.selected_setting_mut() else {
+            return Ok(());
+        };
+        let SettingValue::Dropdown { selected, options, .. } = &mut setting.value else {
+            return Ok(());
+        };
+        if options.is_empty() {
+            return Ok(());
+        }
+
+        *selected = if forward {
+            (*selected + 1) % options.len()
+        } else {
+            (*selected + options.len() - 1) % options.len()
+        };
+
+        if setting.key == "provider" {
+            let selected_value = *selected;
+            self.update_selected_provider(selected_value);
+        }
+        Ok(())
+    }
+
+    /// Replaces the free-text model fields of the selected provider with dropdowns of the
+    /// models it reports, keeping the configured model selected
+    pub fn set_available_models(&mut self, models: &[ModelInfo]) {
+        let Some(provider) = self.selected_provider_mut() else {
+            return;
+        };
+
+        for setting in &mut provider.settings {
+            let embedding = if setting.key.ends_with(".embedding_model") {
+                true
+            } else if setting.key.ends_with(".model") {
+                false
+            } else {
+                continue;
+            };
+
+            let mut options = models
+                .iter()
+                .filter(|model| model.supports_embeddings == embedding)
+                .map(|model| model.name.clone())
+                .collect::<Vec<_>>();
+            if options.is_empty() {
+                continue;
+            }
+
+            let current = setting.value.as_text().unwrap_or_default().to_string();
+            let selected = match options.iter().position(|option| *option == current) {
+                Some(index) => index,
+                None => {
+                    options.insert(0, current);
+                    0
+                }
+            };
+            setting.value = SettingValue::Dropdown {
+                selected,
+                options,
+                expanded: false,
+            };
+        }
+    }
+
     /// Updates the selected provider based on dropdown selection
     pub fn update_selected_provider(&mut self, selected_index: usize) {
         for (i, provider) in self.providers.iter_mut().enumerate() {
@@ -8,6 +8,8 @@ use ratatui::{
     widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
 };
 
+use common::{core::ApprovalRequest, tools::RiskLevel};
+
 use crate::tui::app::{AppMode, AppState};
 use crate::tui::settings::SettingValue;
 
@@ -54,6 +56,83 @@ pub fn draw_ui(f: &mut Frame, app_state: &AppState) {
             }
         }
     }
+
+    // Drawn over every mode, as it takes every key until it is answered
+    if let Some(request) = &app_state.pending_approval {
+        draw_approval_dialog(f, request, size);
+    }
+}
+
+/// Draws the dialog asking to approve a tool call over the middle of the screen, showing what
+/// the call would change when the tool can tell, or its arguments otherwise
+fn draw_approval_dialog(f: &mut Frame, request: &ApprovalRequest, area: Rect) {
+    let details: Vec<Line> = match &request.preview {
+        Some(preview) => preview.lines().map(diff_line).collect(),
+        None => vec![Line::from(Span::styled(
+            request.arguments.to_string(),
+            Style::default().fg(Color::Yellow),
+        ))],
+    };
+
+    let width = area.width.min(if request.preview.is_some() { 90 } else { 60 });
+    // Borders, the request, the details, a blank line and the keys
+    let height = area.height.min((details.len() as u16 + 5).max(8));
+    let dialog_area = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let risk = match request.risk {
+        RiskLevel::Low => "low",
+        RiskLevel::Medium => "medium",
+        RiskLevel::High => "high",
+    };
+    let mut text = vec![Line::from(vec![
+        Span::raw("The assistant wants to run "),
+        Span::styled(
+            format!("`{}`", request.tool),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(" ({} risk) with:", risk)),
+    ])];
+    text.extend(details);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Approve tool call")
+        .border_style(Style::default().fg(Color::Yellow));
+    let inner = block.inner(dialog_area);
+    // The keys stay on the last line, however long the details are
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(2)])
+        .split(inner);
+    let keys = Paragraph::new(vec![
+        Line::from(""),
+        Line::from("y/Enter: Allow | n/Esc: Deny"),
+    ]);
+
+    f.render_widget(Clear, dialog_area);
+    f.render_widget(block, dialog_area);
+    f.render_widget(Paragraph::new(text).wrap(Wrap { trim: false }), chunks[0]);
+    f.render_widget(keys, chunks[1]);
+}
+
+/// Colours a line of a unified diff
+fn diff_line(line: &str) -> Line<'_> {
+    let style = if line.starts_with("+++") || line.starts_with("---") {
+        Style::default().add_modifier(Modifier::BOLD)
+    } else if line.starts_with('+') {
+        Style::default().fg(Color::Green)
+    } else if line.starts_with('-') {
+        Style::default().fg(Color::Red)
+    } else if line.starts_with("@@") {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    };
+    Line::from(Span::styled(line, style))
 }
 
 /// Draws the main chat UI components
@@ -72,8 +151,14 @@ pub fn draw_chat_ui(f: &mut Frame, app_state: &AppState, area: Rect) {
         .split(area);
 
     // --- Messages Area ---
-    // Join the Vec<String> messages into a single String with newlines
-    let messages_text = app_state.messages.join("\n");
+    // Join the Vec<String> messages into a single String with newlines, below the summary of
+    // any compacted messages
+    let messages_text = app_state
+        .earlier_block()
+        .into_iter()
+        .chain(app_state.messages.iter().cloned())
+        .collect::<Vec<_>>()
+        .join("\n");
     let messages_paragraph = Paragraph::new(messages_text)
         .block(Block::default().borders(Borders::ALL).title("Conversation"))
         .wrap(Wrap { trim: true }); // Enable text wrapping
@@ -354,6 +439,8 @@ Mouse Support
 Chat Mode
 ---------
 Enter      Send message
+Esc/Ctrl+X Stop the current answer
+Ctrl+E     Show/hide earlier in this conversation
 ↑/↓        Scroll chat history
 
 Settings Mode
@@ -362,7 +449,12 @@ Settings Mode
 Enter      Edit selected setting
 Tab        Switch sections
 Space      Toggle dropdown
-Esc        Exit settings",
+Esc        Exit settings
+
+Tool Approval
+-------------
+y/Enter    Allow the tool call
+n/Esc      Deny the tool call",
         if cfg!(target_os = "macos") {
             "Cmd + ."
         } else {
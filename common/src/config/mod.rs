@@ -2,11 +2,15 @@
 
 mod error;
 
-use std::{fs, path::PathBuf};
+use std::{collections::HashMap, fs, path::PathBuf};
 
 use crate::{
-    llm::{LLMConfig, LLMProviders, VectorDbConfig},
+    core::{ContextConfig, Persona},
+    llm::{LLMConfig, LLMProviders, RoutingConfig, UsageConfig, VectorDbConfig},
+    memory::RetrievalConfig,
     prelude::*,
+    templates::PromptTemplates,
+    tools::ToolsConfig,
 };
 use serde::{Deserialize, Serialize};
 
@@ -27,12 +31,39 @@ pub struct AppConfig {
     #[serde(default)]
     pub embedding_provider_configs: LLMConfig,
 
+    #[serde(default)]
+    pub routing: RoutingConfig,
+
     #[serde(default)]
     pub vector_db: VectorDbConfig,
+
+    #[serde(default)]
+    pub retrieval: RetrievalConfig,
+
+    #[serde(default)]
+    pub context: ContextConfig,
+
+    #[serde(default)]
+    pub tools: ToolsConfig,
+
+    #[serde(default)]
+    pub usage: UsageConfig,
+
+    /// Persona of sessions that have not picked one; the built-in persona when unset
+    #[serde(default)]
+    pub default_persona: Option<String>,
+
+    #[serde(default)]
+    pub personas: HashMap<String, Persona>,
+
+    /// Prompt templates, read from the `templates` directory rather than the config file
+    #[serde(skip)]
+    pub templates: PromptTemplates,
 }
 
 const CONFIG_DIR_NAME: &str = "lyn";
 const CONFIG_FILE_NAME: &str = "config.toml";
+const TEMPLATES_DIR_NAME: &str = "templates";
 
 /// Loads the application configuration.
 pub fn load_config() -> Result<AppConfig> {
@@ -46,7 +77,8 @@ pub fn load_config() -> Result<AppConfig> {
         .build()
         .map_err(ConfigError::from)?;
 
-    let app_config: AppConfig = settings.try_deserialize().map_err(ConfigError::from)?;
+    let mut app_config: AppConfig = settings.try_deserialize().map_err(ConfigError::from)?;
+    app_config.templates = PromptTemplates::load(&get_config_dir()?.join(TEMPLATES_DIR_NAME))?;
 
     Ok(app_config)
 }
@@ -71,7 +103,24 @@ pub fn save_config(config: &AppConfig) -> Result<()> {
     Ok(())
 }
 
-fn get_config_path() -> Result<PathBuf> {
+/// Returns the directory used for persistent application data (sessions, memories, ...),
+/// creating it if necessary.
+pub fn get_data_dir() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir()
+        .ok_or(ConfigError::DirectoryNotFound)?
+        .join(CONFIG_DIR_NAME);
+
+    if !data_dir.exists() {
+        fs::create_dir_all(&data_dir).map_err(ConfigError::ReadError)?;
+        info!("Created data directory: {}", data_dir.display());
+    }
+
+    Ok(data_dir)
+}
+
+/// Returns the directory holding the configuration file and prompt templates, creating it if
+/// necessary.
+pub fn get_config_dir() -> Result<PathBuf> {
     let config_dir = dirs::config_dir()
         .ok_or(ConfigError::DirectoryNotFound)?
         .join(CONFIG_DIR_NAME);
@@ -81,5 +130,9 @@ fn get_config_path() -> Result<PathBuf> {
         info!("Created configuration directory: {}", config_dir.display());
     }
 
-    Ok(config_dir.join(CONFIG_FILE_NAME))
+    Ok(config_dir)
+}
+
+fn get_config_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(CONFIG_FILE_NAME))
 }
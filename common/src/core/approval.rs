@@ -0,0 +1,85 @@
+//! Tool calls waiting for the user to approve or deny them.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_std::channel::{Receiver, Sender, bounded};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    core::CancellationToken,
+    prelude::*,
+    tools::{RiskLevel, ToolError},
+};
+
+/// A tool call the user is asked to approve before it runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    /// Identifies the request when answering it
+    pub id: Uuid,
+    pub tool: String,
+    pub arguments: Value,
+    pub risk: RiskLevel,
+    /// What the call would change, such as the diff of a file write, when the tool can tell
+    #[serde(default)]
+    pub preview: Option<String>,
+}
+
+/// Tool calls waiting for an answer. Clones share the same requests, so one can be kept by
+/// the client to answer the requests the engine makes.
+#[derive(Debug, Clone, Default)]
+pub struct ToolApprovals {
+    pending: Arc<Mutex<HashMap<Uuid, Sender<bool>>>>,
+}
+
+impl ToolApprovals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a request, returning its id and the receiver of its answer.
+    pub(crate) fn request(&self) -> (Uuid, Receiver<bool>) {
+        let id = Uuid::new_v4();
+        let (sender, receiver) = bounded(1);
+        self.pending().insert(id, sender);
+        (id, receiver)
+    }
+
+    /// Approves or denies a waiting tool call.
+    pub fn answer(&self, id: Uuid, approved: bool) -> Result<()> {
+        let sender = self
+            .pending()
+            .remove(&id)
+            .ok_or_else(|| ToolError::UnknownApproval(id.to_string()))?;
+        // The prompt may have been cancelled meanwhile; then nobody waits for the answer
+        let _ = sender.try_send(approved);
+        Ok(())
+    }
+
+    /// Waits for the answer to a request, returning `None` when `cancel` stops the prompt
+    /// first. A request dropped without an answer counts as denied.
+    pub(crate) async fn wait(
+        &self,
+        id: Uuid,
+        answer: Receiver<bool>,
+        cancel: &CancellationToken,
+    ) -> Option<bool> {
+        let approved = cancel
+            .run(answer.recv())
+            .await
+            .map(|answer| answer.unwrap_or(false));
+        self.pending().remove(&id);
+        approved
+    }
+
+    fn pending(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, Sender<bool>>> {
+        // A panic while holding the lock cannot leave the map inconsistent
+        self.pending
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,427 @@
+//! Fitting prompts, history and recalled memories into the model's context window.
+
+use std::{collections::HashMap, sync::Arc};
+
+use async_std::sync::RwLock;
+use rig::completion::{CompletionRequest, Message, ToolDefinition};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{Persona, Session},
+    llm::{LLMError, LLMProvider, TokenEstimator},
+    memory::ScoredMemory,
+    prelude::*,
+    templates::PromptTemplates,
+};
+
+/// Introduces the running summary of a compacted conversation in the preamble.
+const SUMMARY_HEADING: &str = "Summary of the earlier conversation:";
+
+/// How much of the model's context window a request may use.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ContextConfig {
+    /// Context window used instead of the one the provider reports
+    #[serde(default)]
+    pub window: Option<u64>,
+
+    /// Context window assumed when the provider does not report one
+    #[serde(default = "default_fallback_window")]
+    pub fallback_window: u64,
+
+    /// Tokens kept free for the answer
+    #[serde(default = "default_reserved_output")]
+    pub reserved_output: usize,
+
+    /// Largest share of the available context given to recalled memories, the only limit on
+    /// how many are sent
+    #[serde(default = "default_memory_share")]
+    pub memory_share: f64,
+
+    /// Characters per token used instead of the estimate for the model
+    #[serde(default)]
+    pub chars_per_token: Option<f64>,
+
+    /// Estimated tokens of history past which the oldest exchanges are folded into a running
+    /// summary; 0 never compacts
+    #[serde(default = "default_compact_after")]
+    pub compact_after: usize,
+
+    /// Most recent history messages never folded into the summary
+    #[serde(default = "default_keep_recent_messages")]
+    pub keep_recent_messages: usize,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            window: None,
+            fallback_window: default_fallback_window(),
+            reserved_output: default_reserved_output(),
+            memory_share: default_memory_share(),
+            chars_per_token: None,
+            compact_after: default_compact_after(),
+            keep_recent_messages: default_keep_recent_messages(),
+        }
+    }
+}
+
+fn default_fallback_window() -> u64 {
+    4096
+}
+
+fn default_reserved_output() -> usize {
+    1024
+}
+
+fn default_memory_share() -> f64 {
+    0.25
+}
+
+fn default_compact_after() -> usize {
+    2048
+}
+
+fn default_keep_recent_messages() -> usize {
+    6
+}
+
+/// What fitting a request into the context window left out.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetReport {
+    /// Estimated tokens of the request
+    pub estimated_tokens: usize,
+    /// Context window the request was fitted into
+    pub context_window: u64,
+    /// Oldest history messages left out
+    pub trimmed_messages: usize,
+    /// Least relevant recalled memories left out
+    pub dropped_memories: usize,
+}
+
+/// The parts of a request left once it fits the context window.
+#[derive(Debug, Clone)]
+pub(crate) struct FittedRequest {
+    pub(crate) preamble: String,
+    pub(crate) history: Vec<Message>,
+    pub(crate) report: BudgetReport,
+}
+
+/// Context windows of the chat models, looked up on the first prompt sent to each rather than
+/// on every prompt.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ContextWindows {
+    // By model name; `None` when the provider does not report the window
+    windows: Arc<RwLock<HashMap<String, Option<u64>>>>,
+}
+
+impl ContextWindows {
+    /// The context window of the provider's chat model. Lookups failing to reach the provider
+    /// are not remembered, so the next prompt tries again.
+    pub(crate) async fn get(&self, provider: &dyn LLMProvider) -> Option<u64> {
+        if let Some(window) = self.windows.read().await.get(provider.model()) {
+            return *window;
+        }
+        match provider.context_window().await {
+            Ok(window) => {
+                let model = provider.model().to_string();
+                self.windows.write().await.insert(model, window);
+                window
+            }
+            Err(e) => {
+                debug!("Cannot look up the context window: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// The token budget of requests sent to one model.
+#[derive(Debug, Clone)]
+pub struct ContextBudget {
+    window: u64,
+    reserved_output: usize,
+    memory_share: f64,
+    estimator: TokenEstimator,
+}
+
+impl ContextBudget {
+    pub fn new(config: &ContextConfig, window: u64, estimator: TokenEstimator) -> Self {
+        Self {
+            window,
+            reserved_output: config.reserved_output,
+            memory_share: config.memory_share.clamp(0.0, 1.0),
+            estimator: config
+                .chars_per_token
+                .map_or(estimator, TokenEstimator::new),
+        }
+    }
+
+    /// The budget of the provider's chat model, using the context window it reports unless
+    /// the config sets one.
+    pub(crate) async fn for_provider(
+        config: &ContextConfig,
+        provider: &dyn LLMProvider,
+        windows: &ContextWindows,
+    ) -> Self {
+        let window = match config.window {
+            Some(window) => window,
+            None => windows
+                .get(provider)
+                .await
+                .unwrap_or(config.fallback_window),
+        };
+        Self::new(config, window, provider.token_estimator())
+    }
+
+    pub fn estimator(&self) -> &TokenEstimator {
+        &self.estimator
+    }
+
+    /// Tokens a request may use, leaving room for the answer.
+    pub fn available(&self) -> usize {
+        usize::try_from(self.window)
+            .unwrap_or(usize::MAX)
+            .saturating_sub(self.reserved_output)
+    }
+
+    /// Leaves out the least relevant memories and the oldest exchanges of the session's
+    /// active history until the request fits, and renders the preamble. The running summary
+    /// of a compacted session closes the preamble.
+    ///
+    /// Memories are limited to their share of the context first; history then gets what
+    /// remains. The prompt, preamble and tool definitions are always sent, even when they
+    /// alone exceed the budget.
+    pub(crate) fn fit(
+        &self,
+        persona: &Persona,
+        templates: &PromptTemplates,
+        session: &Session,
+        user_prompt: &str,
+        tools: &[ToolDefinition],
+        memories: &mut Vec<ScoredMemory>,
+    ) -> Result<FittedRequest> {
+        let summary = session.summary.as_deref();
+        let mut history = session.active_history();
+        let mut report = BudgetReport {
+            context_window: self.window,
+            ..Default::default()
+        };
+        let tools_tokens = serde_json::to_string(tools)
+            .map_or(0, |definitions| self.estimator.estimate(&definitions));
+        let fixed = self
+            .estimator
+            .estimate(&with_summary(persona.render_preamble("", tools), summary))
+            + self.estimator.estimate_message(&Message::user(user_prompt))
+            + tools_tokens;
+        let mut available = self.available().saturating_sub(fixed);
+
+        let memory_budget = (available as f64 * self.memory_share) as usize;
+        let mut recalled = templates.render_memories(user_prompt, memories);
+        while !memories.is_empty() && self.estimator.estimate(&recalled) > memory_budget {
+            memories.pop();
+            report.dropped_memories += 1;
+            recalled = templates.render_memories(user_prompt, memories);
+        }
+        available = available.saturating_sub(self.estimator.estimate(&recalled));
+
+        let mut history_tokens = self.history_tokens(&history);
+        while !history.is_empty() && history_tokens > available {
+            // Exchanges are dropped whole so the history keeps alternating
+            let dropped = history.len().min(2);
+            history.drain(..dropped);
+            report.trimmed_messages += dropped;
+            history_tokens = self.history_tokens(&history);
+        }
+
+        let preamble = with_summary(persona.render_preamble(&recalled, tools), summary);
+        report.estimated_tokens = self.estimator.estimate(&preamble)
+            + self.estimator.estimate_message(&Message::user(user_prompt))
+            + tools_tokens
+            + history_tokens;
+        if report.trimmed_messages > 0 || report.dropped_memories > 0 {
+            info!(
+                "Left out {} message(s) and {} memory(ies) to fit {} tokens",
+                report.trimmed_messages,
+                report.dropped_memories,
+                self.available()
+            );
+        }
+        if report.estimated_tokens > self.available() {
+            warn!(
+                "Request of ~{} tokens exceeds the context window of {}",
+                report.estimated_tokens, self.window
+            );
+        }
+        Ok(FittedRequest {
+            preamble,
+            history,
+            report,
+        })
+    }
+
+    /// Leaves out the oldest exchanges among the first `trimmable` history messages, those
+    /// coming from the session, until the request of a tool round fits, returning how many it
+    /// left out. Each round adds the model's tool calls and their results to the history;
+    /// these are always kept.
+    ///
+    /// Fails when the request does not fit even so, rather than letting the model lose the
+    /// start of it.
+    pub(crate) fn fit_round(
+        &self,
+        request: &mut CompletionRequest,
+        trimmable: usize,
+        report: &mut BudgetReport,
+    ) -> Result<usize> {
+        let mut tokens = self.estimator.estimate_request(request);
+        let mut trimmed = 0;
+        while tokens > self.available() && trimmed < trimmable {
+            let dropped = (trimmable - trimmed).min(2);
+            request.chat_history.drain(..dropped);
+            trimmed += dropped;
+            tokens = self.estimator.estimate_request(request);
+        }
+        report.trimmed_messages += trimmed;
+        report.estimated_tokens = report.estimated_tokens.max(tokens);
+        if tokens > self.available() {
+            return Err(LLMError::ContextOverflow {
+                tokens,
+                window: self.window,
+            }
+            .into());
+        }
+        Ok(trimmed)
+    }
+
+    fn history_tokens(&self, history: &[Message]) -> usize {
+        history
+            .iter()
+            .map(|message| self.estimator.estimate_message(message))
+            .sum()
+    }
+}
+
+fn with_summary(preamble: String, summary: Option<&str>) -> String {
+    match summary {
+        Some(summary) => f!("{}\n\n{}\n{}", preamble, SUMMARY_HEADING, summary),
+        None => preamble,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        memory::MemoryRecord,
+        templates::{PREAMBLE_VARIABLES, PromptTemplate},
+    };
+
+    #[test]
+    fn test_fit_trims_oldest_history_and_memories() {
+        let config = ContextConfig {
+            reserved_output: 0,
+            ..Default::default()
+        };
+        let persona = Persona {
+            preamble: PromptTemplate::parse("persona", "Be brief.", PREAMBLE_VARIABLES).unwrap(),
+            ..Default::default()
+        };
+        let budget = ContextBudget::new(&config, 400, TokenEstimator::default());
+
+        let mut session = Session::new();
+        for i in 0..10 {
+            session.push_turn(
+                Message::user(f!("question {} {}", i, "x".repeat(100))),
+                Message::assistant(f!("answer {} {}", i, "y".repeat(100))),
+            );
+        }
+        let mut memories = (0..5)
+            .map(|_| ScoredMemory {
+                record: MemoryRecord::new("z".repeat(200), Uuid::new_v4(), 0),
+                score: 0.9,
+            })
+            .collect::<Vec<_>>();
+
+        let FittedRequest {
+            preamble,
+            history,
+            report,
+        } = budget
+            .fit(
+                &persona,
+                &PromptTemplates::default(),
+                &session,
+                "Hi",
+                &[],
+                &mut memories,
+            )
+            .unwrap();
+
+        assert!(report.estimated_tokens <= 400);
+        assert_eq!(report.trimmed_messages, 20 - history.len());
+        assert_eq!(report.dropped_memories, 5 - memories.len());
+        assert!(report.dropped_memories > 0 && !memories.is_empty());
+        assert!(message_starts_with(&history[0], "question"));
+        assert!(preamble.starts_with("Be brief.") && preamble.contains(&"z".repeat(200)));
+
+        session.summary = Some(String::from("The user asked ten questions."));
+        session.compacted = 16;
+        let fitted = budget
+            .fit(
+                &persona,
+                &PromptTemplates::default(),
+                &session,
+                "Hi",
+                &[],
+                &mut Vec::new(),
+            )
+            .unwrap();
+        assert_eq!(
+            (fitted.history.len(), fitted.report.trimmed_messages),
+            (4, 0)
+        );
+        assert!(fitted.preamble.ends_with("The user asked ten questions."));
+    }
+
+    #[test]
+    fn test_fit_round_keeps_tool_rounds() {
+        let config = ContextConfig {
+            reserved_output: 0,
+            ..Default::default()
+        };
+        let budget = ContextBudget::new(&config, 100, TokenEstimator::default());
+        let round = |result: &str| CompletionRequest {
+            prompt: Message::user(result),
+            preamble: None,
+            chat_history: vec![
+                Message::user("a".repeat(120)),
+                Message::assistant("b".repeat(120)),
+                Message::user("What is in notes.txt?"),
+                Message::assistant("Reading it"),
+            ],
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            additional_params: None,
+        };
+
+        let mut report = BudgetReport::default();
+        let mut request = round(&"c".repeat(200));
+        assert_eq!(budget.fit_round(&mut request, 2, &mut report).unwrap(), 2);
+        assert_eq!(request.chat_history.len(), 2);
+        assert_eq!(report.trimmed_messages, 2);
+
+        // Tool results alone exceeding the window are reported rather than sent
+        let mut request = round(&"c".repeat(500));
+        assert!(matches!(
+            budget.fit_round(&mut request, 2, &mut report),
+            Err(Error::LLM(LLMError::ContextOverflow { window: 100, .. }))
+        ));
+    }
+
+    fn message_starts_with(message: &Message, prefix: &str) -> bool {
+        crate::core::message_text(message).is_some_and(|text| text.starts_with(prefix))
+    }
+}
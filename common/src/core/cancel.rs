@@ -0,0 +1,54 @@
+//! Cooperative cancellation of prompts being answered.
+
+use std::{future::Future, pin::pin};
+
+use async_std::channel::{Receiver, Sender, bounded};
+use futures::future::{Either, select};
+
+/// Asks a running prompt to stop. Clones share the same signal, so one can be kept by the
+/// client while another is handed to the engine.
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    // Nothing is ever sent; closing the channel is the signal
+    sender: Sender<()>,
+    receiver: Receiver<()>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        let (sender, receiver) = bounded(1);
+        Self { sender, receiver }
+    }
+
+    /// Signals cancellation to every clone of the token.
+    pub fn cancel(&self) {
+        self.sender.close();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    /// Completes once the token is cancelled.
+    pub async fn cancelled(&self) {
+        let _ = self.receiver.recv().await;
+    }
+
+    /// Runs a future until it completes or the token is cancelled, returning `None` in the
+    /// latter case.
+    pub async fn run<F: Future>(&self, future: F) -> Option<F::Output> {
+        if self.is_cancelled() {
+            return None;
+        }
+        match select(pin!(future), pin!(self.cancelled())).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,149 @@
+//! Folding the oldest exchanges of long conversations into a running summary.
+
+use crate::{
+    core::{CancellationToken, ContextConfig, Session, SessionStore},
+    llm::{LLMProvider, TokenEstimator},
+    memory::summarize_conversation,
+    prelude::*,
+    templates::PromptTemplates,
+};
+
+/// Folds the oldest exchanges of a session into its summary once the history sent to the
+/// model outgrows `compact_after` tokens, returning whether it did.
+///
+/// The most recent messages stay verbatim. The session is updated in place and persisted;
+/// its history itself is kept whole. Cancelling `cancel` stops the summary being written and
+/// leaves the session as it was.
+pub(crate) async fn compact(
+    sessions: &SessionStore,
+    session: &mut Session,
+    provider: &dyn LLMProvider,
+    templates: &PromptTemplates,
+    config: &ContextConfig,
+    estimator: &TokenEstimator,
+    cancel: &CancellationToken,
+) -> Result<bool> {
+    if config.compact_after == 0 {
+        return Ok(false);
+    }
+    let tokens = session
+        .active_history()
+        .iter()
+        .map(|message| estimator.estimate_message(message))
+        .sum::<usize>();
+    if tokens <= config.compact_after {
+        return Ok(false);
+    }
+
+    // History holds whole exchanges, so folding an even number of messages keeps it
+    // alternating
+    let keep = config.keep_recent_messages.next_multiple_of(2);
+    let end = session.history.len().saturating_sub(keep);
+    if end <= session.compacted {
+        return Ok(false);
+    }
+
+    let summary = summarize_conversation(
+        provider,
+        &templates.compaction,
+        session.summary.as_deref(),
+        &session.history[session.compacted..end],
+    );
+    let Some(summary) = cancel.run(summary).await else {
+        return Ok(false);
+    };
+    let summary = summary?;
+    sessions
+        .set_summary(session.id, summary.clone(), end)
+        .await?;
+    info!(
+        "Compacted {} message(s) of session {} into its summary",
+        end - session.compacted,
+        session.id
+    );
+    session.summary = Some(summary);
+    session.compacted = end;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use rig::completion::Message;
+
+    use super::*;
+    use crate::llm::{MockProvider, MockReply};
+
+    #[async_std::test]
+    async fn test_compact_folds_oldest_exchanges() {
+        let sessions = SessionStore::in_memory();
+        let id = sessions.create().await.unwrap();
+        for i in 0..5 {
+            sessions
+                .record_turn(
+                    id,
+                    Message::user(f!("question {}", i)),
+                    Message::assistant(f!("answer {}", i)),
+                )
+                .await
+                .unwrap();
+        }
+        let config = ContextConfig {
+            compact_after: 10,
+            keep_recent_messages: 3,
+            ..Default::default()
+        };
+        let provider = MockProvider::scripted([MockReply::text("Five questions were asked")]);
+
+        let mut session = sessions.get(id).await.unwrap();
+        let compacted = compact(
+            &sessions,
+            &mut session,
+            &provider,
+            &PromptTemplates::default(),
+            &config,
+            &TokenEstimator::default(),
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(compacted);
+        assert_eq!(session.compacted, 6);
+        assert_eq!(session.active_history()[0], Message::user("question 3"));
+        assert!(provider.prompts().await[0].contains("User: question 2\nAssistant: answer 2"));
+
+        let stored = sessions.get(id).await.unwrap();
+        assert_eq!(stored.summary.as_deref(), Some("Five questions were asked"));
+        assert!(stored.transcript()[5].compacted && !stored.transcript()[6].compacted);
+
+        // A cancelled prompt does not wait for the summary
+        for i in 5..8 {
+            sessions
+                .record_turn(
+                    id,
+                    Message::user(f!("question {}", i)),
+                    Message::assistant(f!("answer {}", i)),
+                )
+                .await
+                .unwrap();
+        }
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let mut session = sessions.get(id).await.unwrap();
+        let compacted = compact(
+            &sessions,
+            &mut session,
+            &provider,
+            &PromptTemplates::default(),
+            &config,
+            &TokenEstimator::default(),
+            &cancel,
+        )
+        .await
+        .unwrap();
+
+        assert!(!compacted);
+        assert_eq!(session.compacted, 6);
+        assert_eq!(provider.prompts().await.len(), 1);
+    }
+}
@@ -0,0 +1,224 @@
+//! Dispatching of model tool calls to the tool registry.
+
+use rig::{
+    OneOrMany,
+    completion::{CompletionRequest, Message, ToolDefinition},
+    message::{AssistantContent, ToolCall, ToolResultContent, UserContent},
+};
+use serde_json::json;
+
+use crate::{
+    core::Persona,
+    prelude::*,
+    tools::{ToolPolicy, ToolRegistry, ToolsConfig},
+};
+
+/// Told to the model when a tool call is refused by the approval policy.
+pub(crate) const DENIED_BY_POLICY: &str = "The user does not allow this tool to be run";
+/// Told to the model when the user denies a tool call.
+pub(crate) const DENIED_BY_USER: &str = "The user denied this tool call";
+/// Told to the model when a tool call needs approval nobody can give.
+const NEEDS_APPROVAL: &str = "This tool needs the user's approval, which cannot be asked for here";
+
+/// Builds a completion request for one round of the tool loop, with the persona's rendered
+/// preamble.
+pub(crate) fn build_request(
+    persona: &Persona,
+    preamble: String,
+    prompt: Message,
+    chat_history: Vec<Message>,
+    tools: Vec<ToolDefinition>,
+) -> CompletionRequest {
+    CompletionRequest {
+        prompt,
+        preamble: Some(preamble),
+        chat_history,
+        documents: Vec::new(),
+        tools,
+        temperature: persona.temperature,
+        max_tokens: persona.max_tokens,
+        additional_params: None,
+    }
+}
+
+/// Splits assistant content into its text and the tool calls it requests.
+pub(crate) fn split_tool_calls(content: &OneOrMany<AssistantContent>) -> (String, Vec<ToolCall>) {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+
+    for item in content.iter() {
+        match item {
+            AssistantContent::Text(t) => text.push_str(&t.text),
+            AssistantContent::ToolCall(call) => tool_calls.push(call.clone()),
+        }
+    }
+
+    (text, tool_calls)
+}
+
+/// Rebuilds the assistant message that requested the given tool calls.
+pub(crate) fn tool_call_message(text: String, tool_calls: &[ToolCall]) -> Result<Message> {
+    let text = (!text.is_empty()).then(|| AssistantContent::text(text));
+    let content = text
+        .into_iter()
+        .chain(tool_calls.iter().cloned().map(AssistantContent::ToolCall));
+
+    OneOrMany::many(content)
+        .map(|content| Message::Assistant { content })
+        .map_err(|_| Error::ToolCallParseFailed("Response contained no tool calls".to_string()))
+}
+
+/// Outcome of running a single tool call.
+#[derive(Debug, Clone)]
+pub(crate) struct ToolOutcome {
+    pub id: String,
+    pub name: String,
+    pub output: String,
+    pub is_error: bool,
+}
+
+/// Runs a tool call against the registry.
+///
+/// Tool failures are reported back to the model as results instead of aborting the turn,
+/// so it gets a chance to correct its arguments.
+pub(crate) async fn run_tool_call(registry: &ToolRegistry, call: &ToolCall) -> ToolOutcome {
+    let name = &call.function.name;
+    info!("Model requested tool '{}'", name);
+
+    let (output, is_error) = match registry
+        .call(name, call.function.arguments.to_string())
+        .await
+    {
+        Ok(output) => (output, false),
+        Err(e) => {
+            warn!("Tool '{}' failed: {}", name, e);
+            (json!({ "error": e.to_string() }).to_string(), true)
+        }
+    };
+
+    ToolOutcome {
+        id: tool_call_id(call),
+        name: name.clone(),
+        output,
+        is_error,
+    }
+}
+
+/// Outcome of a tool call that was not run, telling the model why.
+pub(crate) fn refused_tool_call(call: &ToolCall, reason: &str) -> ToolOutcome {
+    info!("Tool call '{}' refused: {}", call.function.name, reason);
+    ToolOutcome {
+        id: tool_call_id(call),
+        name: call.function.name.clone(),
+        output: json!({ "error": reason }).to_string(),
+        is_error: true,
+    }
+}
+
+/// Builds the user message carrying tool results back to the model.
+pub(crate) fn tool_results_message(outcomes: &[ToolOutcome]) -> Result<Message> {
+    let results = outcomes.iter().map(|outcome| {
+        UserContent::tool_result(
+            outcome.id.clone(),
+            OneOrMany::one(ToolResultContent::text(outcome.output.clone())),
+        )
+    });
+
+    OneOrMany::many(results)
+        .map(|content| Message::User { content })
+        .map_err(|_| Error::ToolCallParseFailed("No tool calls to dispatch".to_string()))
+}
+
+/// Runs each tool call the approval policy allows against the registry and returns a user
+/// message carrying the results.
+///
+/// Nobody can be asked for approval here, so calls needing it are refused.
+pub(crate) async fn dispatch_tool_calls(
+    registry: &ToolRegistry,
+    config: &ToolsConfig,
+    tool_calls: &[ToolCall],
+) -> Result<Message> {
+    let mut outcomes = Vec::with_capacity(tool_calls.len());
+    for call in tool_calls {
+        let outcome = match registry.policy(&call.function.name, &call.function.arguments, config) {
+            ToolPolicy::Allow => run_tool_call(registry, call).await,
+            ToolPolicy::Ask => refused_tool_call(call, NEEDS_APPROVAL),
+            ToolPolicy::Deny => refused_tool_call(call, DENIED_BY_POLICY),
+        };
+        outcomes.push(outcome);
+    }
+
+    tool_results_message(&outcomes)
+}
+
+/// Providers such as Ollama and Gemini identify calls by function name rather than by id.
+pub(crate) fn tool_call_id(call: &ToolCall) -> String {
+    if call.id.is_empty() {
+        call.function.name.clone()
+    } else {
+        call.id.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{Calculator, ToolCategory};
+
+    fn calculator_call(expression: &str) -> ToolCall {
+        match AssistantContent::tool_call("", "calculator", json!({ "expression": expression })) {
+            AssistantContent::ToolCall(call) => call,
+            AssistantContent::Text(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_split_tool_calls() {
+        let content = OneOrMany::many(vec![
+            AssistantContent::text("Let me calculate that."),
+            AssistantContent::ToolCall(calculator_call("2 + 2")),
+        ])
+        .unwrap();
+
+        let (text, calls) = split_tool_calls(&content);
+        assert_eq!(text, "Let me calculate that.");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].function.name, "calculator");
+    }
+
+    #[async_std::test]
+    async fn test_dispatch_tool_calls() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Calculator, ToolCategory::Utilities);
+
+        let calls = vec![
+            calculator_call("6 * 7"),
+            match AssistantContent::tool_call("call-1", "missing", json!({})) {
+                AssistantContent::ToolCall(call) => call,
+                AssistantContent::Text(_) => unreachable!(),
+            },
+        ];
+
+        let Message::User { content } = dispatch_tool_calls(&registry, &Default::default(), &calls)
+            .await
+            .unwrap()
+        else {
+            panic!("Tool results must be sent as a user message");
+        };
+
+        let results = content
+            .iter()
+            .map(|item| match item {
+                UserContent::ToolResult(result) => match result.content.first() {
+                    ToolResultContent::Text(text) => (result.id.clone(), text.text),
+                    ToolResultContent::Image(_) => unreachable!(),
+                },
+                _ => panic!("Expected only tool results"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(results[0], ("calculator".to_string(), "42.0".to_string()));
+        assert_eq!(results[1].0, "call-1");
+        assert!(results[1].1.contains("error"));
+    }
+}
@@ -0,0 +1,200 @@
+//! Typed events emitted while the engine answers a prompt.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    core::{ApprovalRequest, BudgetReport},
+    llm::TokenUsage,
+    memory::ScoredMemory,
+};
+
+const REASONING_START: &str = "<think>";
+const REASONING_END: &str = "</think>";
+
+/// An event of a streamed engine response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum EngineEvent {
+    /// Stored memories given to the model as context
+    MemoriesRecalled(Vec<ScoredMemory>),
+    /// The oldest messages of the session were folded into its running summary
+    Compacted {
+        summary: String,
+        /// Leading history messages the summary now covers
+        messages: usize,
+    },
+    /// A piece of the answer text
+    TextDelta(String),
+    /// A piece of the model's thought process
+    Reasoning(String),
+    /// The model asked for a tool to be run
+    ToolCallStarted {
+        id: String,
+        name: String,
+        arguments: Value,
+    },
+    /// A tool call waits for the user to approve or deny it
+    ApprovalRequired(ApprovalRequest),
+    /// A requested tool finished running
+    ToolResult {
+        id: String,
+        name: String,
+        output: String,
+        is_error: bool,
+    },
+    /// Resources consumed to produce the response
+    Usage(Usage),
+    /// The response is complete; carries the full answer text
+    Done { content: String },
+    /// The response was cancelled; carries the partial answer text, which is kept in the
+    /// session
+    Interrupted { content: String },
+    /// Processing failed; no further events follow
+    Error(String),
+}
+
+/// Resources consumed while answering a prompt.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Number of completion requests sent to the provider
+    pub requests: usize,
+    /// Number of tool calls dispatched
+    pub tool_calls: usize,
+    /// Tokens of the prompts, as reported by the provider or estimated when it reports none
+    pub input_tokens: Option<u64>,
+    /// Tokens of the responses, as reported by the provider or estimated when it reports none
+    pub output_tokens: Option<u64>,
+    /// Some of the token counts were estimated; streamed responses always are
+    pub estimated_tokens: bool,
+    /// Wall-clock time spent answering
+    pub elapsed_ms: u64,
+    /// How the request was fitted into the model's context window
+    pub context: Option<BudgetReport>,
+}
+
+impl Usage {
+    /// Adds the tokens of one request, those the provider reported or else the estimate.
+    pub(crate) fn add_tokens(&mut self, reported: Option<TokenUsage>, estimate: TokenUsage) {
+        let tokens = reported.unwrap_or(estimate);
+        self.estimated_tokens |= reported.is_none();
+        *self.input_tokens.get_or_insert(0) += tokens.input_tokens;
+        *self.output_tokens.get_or_insert(0) += tokens.output_tokens;
+    }
+}
+
+/// Separates `<think>...</think>` sections emitted by reasoning models from the answer text.
+///
+/// Tags may be split across chunks, so text that could be the start of a tag is held back
+/// until the next chunk arrives.
+#[derive(Debug, Default)]
+pub(crate) struct ReasoningSplitter {
+    in_reasoning: bool,
+    pending: String,
+}
+
+impl ReasoningSplitter {
+    /// Feeds a chunk of model output, returning the events that can be emitted so far.
+    pub(crate) fn push(&mut self, chunk: &str) -> Vec<EngineEvent> {
+        self.pending.push_str(chunk);
+        let mut events = Vec::new();
+
+        loop {
+            let tag = self.current_tag();
+            match self.pending.find(tag) {
+                Some(pos) => {
+                    let text = self.pending[..pos].to_string();
+                    self.pending.drain(..pos + tag.len());
+                    events.extend(self.event(text));
+                    self.in_reasoning = !self.in_reasoning;
+                }
+                None => {
+                    let keep = (1..tag.len())
+                        .rev()
+                        .find(|&len| self.pending.ends_with(&tag[..len]))
+                        .unwrap_or(0);
+                    let text = self.pending[..self.pending.len() - keep].to_string();
+                    self.pending.drain(..self.pending.len() - keep);
+                    events.extend(self.event(text));
+                    return events;
+                }
+            }
+        }
+    }
+
+    /// Flushes any text held back at the end of the stream.
+    pub(crate) fn finish(&mut self) -> Vec<EngineEvent> {
+        let text = std::mem::take(&mut self.pending);
+        self.event(text).into_iter().collect()
+    }
+
+    fn current_tag(&self) -> &'static str {
+        if self.in_reasoning {
+            REASONING_END
+        } else {
+            REASONING_START
+        }
+    }
+
+    fn event(&self, text: String) -> Option<EngineEvent> {
+        match (text.is_empty(), self.in_reasoning) {
+            (true, _) => None,
+            (false, true) => Some(EngineEvent::Reasoning(text)),
+            (false, false) => Some(EngineEvent::TextDelta(text)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(chunks: &[&str]) -> Vec<EngineEvent> {
+        let mut splitter = ReasoningSplitter::default();
+        let mut events = chunks
+            .iter()
+            .flat_map(|chunk| splitter.push(chunk))
+            .collect::<Vec<_>>();
+        events.extend(splitter.finish());
+        events
+    }
+
+    #[test]
+    fn test_plain_text_passes_through() {
+        assert_eq!(
+            split(&["Hello", " world"]),
+            vec![
+                EngineEvent::TextDelta("Hello".to_string()),
+                EngineEvent::TextDelta(" world".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reasoning_split_across_chunks() {
+        assert_eq!(
+            split(&[
+                "<th",
+                "ink>Let me",
+                " think</thi",
+                "nk>The answer <",
+                "is 4"
+            ]),
+            vec![
+                EngineEvent::Reasoning("Let me".to_string()),
+                EngineEvent::Reasoning(" think".to_string()),
+                EngineEvent::TextDelta("The answer ".to_string()),
+                EngineEvent::TextDelta("<is 4".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let event = EngineEvent::TextDelta("Hi".to_string());
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({ "type": "text_delta", "data": "Hi" })
+        );
+    }
+}
@@ -0,0 +1,1080 @@
+//! Core application logic.
+
+mod approval;
+mod budget;
+mod cancel;
+mod compaction;
+mod dispatch;
+mod event;
+mod persona;
+mod session;
+
+use std::{pin::Pin, sync::Arc, time::Instant};
+
+use serde::Serialize;
+
+use futures::{Stream, StreamExt};
+use rig::{
+    completion::{Message, ToolDefinition},
+    message::{ToolCall, ToolFunction},
+    streaming::StreamingChoice,
+};
+use uuid::Uuid;
+
+use crate::{
+    config::{self, AppConfig},
+    llm::{
+        Completion, LLMError, LLMProvider, MeteredProvider, ModelInfo, ProviderRole,
+        ProviderRouter, TokenUsage, UsageGrouping, UsageLedger, UsageSummary, create_llm_provider,
+    },
+    memory::{
+        MemoryId, MemoryManager, MemoryRecord, MemoryStore, Retriever, ScoredMemory, open_store,
+    },
+    prelude::*,
+    tools::{
+        Calculator, DateTime, FileSandbox, RiskLevel, Shell, ToolCategory, ToolError, ToolIndex,
+        ToolPolicy, ToolRegistry, ToolSelection, register_file_tools,
+    },
+};
+pub use approval::{ApprovalRequest, ToolApprovals};
+use budget::{ContextWindows, FittedRequest};
+pub use budget::{BudgetReport, ContextBudget, ContextConfig};
+pub use cancel::CancellationToken;
+use compaction::compact;
+use dispatch::{
+    DENIED_BY_POLICY, DENIED_BY_USER, build_request, dispatch_tool_calls, refused_tool_call,
+    run_tool_call, split_tool_calls, tool_call_id, tool_call_message, tool_results_message,
+};
+use event::ReasoningSplitter;
+pub use event::{EngineEvent, Usage};
+pub use persona::{DEFAULT_PERSONA, Persona};
+pub use session::{
+    Role, Session, SessionId, SessionInfo, SessionStore, TranscriptEntry, message_text,
+};
+
+// Removed old constants and ToolCallRequest struct
+
+/// Stream of events produced while answering a prompt.
+pub type EngineStream = Pin<Box<dyn Stream<Item = EngineEvent> + Send>>;
+
+/// The answer to a prompt together with the memories it drew on.
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptResponse {
+    pub content: String,
+    pub memories: Vec<ScoredMemory>,
+    pub usage: Usage,
+}
+
+#[derive(Clone)]
+pub struct Engine {
+    config: Arc<AppConfig>,
+    // Answers prompts and calls tools
+    llm_client: Arc<dyn LLMProvider>,
+    // Embeds tool docs and prompts to retrieve the relevant tools
+    embedding_client: Arc<dyn LLMProvider>,
+    // Conversation sessions and their chat history
+    sessions: SessionStore,
+    // Tools offered to the model and dispatched when it calls them
+    tool_registry: Arc<ToolRegistry>,
+    // Embedded tool docs, when only the tools relevant to a prompt are offered
+    tool_index: Arc<ToolIndex>,
+    // Long-term memory of summarized exchanges
+    memory: Arc<MemoryManager>,
+    // Picks the provider of each session, honoring privacy and persona preferences
+    router: Option<ProviderRouter>,
+    // Tokens, latency and cost of every provider call
+    usage: Arc<UsageLedger>,
+    // Tool calls of streamed prompts waiting for the user's approval
+    approvals: ToolApprovals,
+    // Context window of each chat model, looked up on its first prompt
+    context_windows: ContextWindows,
+}
+
+impl Engine {
+    /// Creates a new instance of the Engine.
+    ///
+    /// Loads configuration, initializes the chat and embedding providers, memory client,
+    /// and registers the tools offered to the model. Providers are not contacted until
+    /// [`Engine::probe`] or the first prompt.
+    pub async fn new() -> Result<Self> {
+        info!("Initializing Lyn Engine...");
+
+        // --- Load Configuration ---
+        let config = Arc::new(config::load_config()?);
+        info!("Configuration loaded successfully.");
+        debug!("Loaded config: {:?}", config);
+
+        let router = ProviderRouter::new(Arc::clone(&config))?;
+
+        // The chat provider of the same kind already holds the embedding settings
+        let embedding_client = match router.provider(&config.embedding_provider) {
+            Some(client) if config.embedding_provider == config.provider => client,
+            _ => {
+                if config.routing.private
+                    && config
+                        .embedding_provider
+                        .is_cloud(&config.embedding_provider_configs)
+                {
+                    return Err(Error::LLM(LLMError::Configuration(String::from(
+                        "Private routing needs a local embedding provider",
+                    ))));
+                }
+                create_llm_provider(Arc::clone(&config), ProviderRole::Embedding)?
+            }
+        };
+
+        let sessions = SessionStore::new()?;
+        let memory_store = open_store(&config.vector_db)?;
+        let usage = Arc::new(UsageLedger::new(config.usage.clone())?);
+
+        // Memory summaries and embeddings are metered without a session; prompts are
+        // attributed to theirs when routed
+        let embedding_client = Arc::new(MeteredProvider::new(
+            embedding_client,
+            config.embedding_provider.clone(),
+            usage.clone(),
+            None,
+        ));
+        Ok(Self::from_parts(
+            config,
+            Arc::new(router.metered(usage.clone(), None)),
+            embedding_client,
+            sessions,
+            memory_store,
+        )
+        .with_router(router)
+        .with_usage_ledger(usage))
+    }
+
+    /// Assembles an engine from already constructed providers and stores, registering the
+    /// built-in tools.
+    ///
+    /// Useful to run the engine against a [`MockProvider`](crate::llm::MockProvider) and
+    /// in-memory stores.
+    pub fn from_parts(
+        config: Arc<AppConfig>,
+        llm_client: Arc<dyn LLMProvider>,
+        embedding_client: Arc<dyn LLMProvider>,
+        sessions: SessionStore,
+        memory_store: Arc<dyn MemoryStore>,
+    ) -> Self {
+        let mut tool_registry = ToolRegistry::new();
+
+        // Register tools with their categories
+        tool_registry.register_embedding(Calculator, ToolCategory::Utilities);
+        tool_registry.register_embedding(DateTime, ToolCategory::Utilities);
+        // File tools only exist once the user opened directories to them
+        if !config.tools.files.roots.is_empty() {
+            match FileSandbox::new(config.tools.files.clone()) {
+                Ok(sandbox) => register_file_tools(&mut tool_registry, Arc::new(sandbox)),
+                Err(e) => warn!("File tools are unavailable: {}", e),
+            }
+        }
+        if config.tools.shell.enabled {
+            let shell = Shell::new(config.tools.shell.clone(), &config.tools.approval);
+            tool_registry.register(shell, ToolCategory::System);
+        }
+        tool_registry.configure(&config.tools);
+
+        let memory = Arc::new(
+            MemoryManager::new(
+                llm_client.clone(),
+                embedding_client.clone(),
+                memory_store.clone(),
+                Retriever::new(memory_store, config.retrieval.clone()),
+            )
+            .with_summary_template(config.templates.summarization.clone()),
+        );
+
+        Self {
+            config,
+            llm_client,
+            embedding_client,
+            sessions,
+            tool_registry: Arc::new(tool_registry),
+            tool_index: Arc::new(ToolIndex::new()),
+            memory,
+            router: None,
+            usage: Arc::new(UsageLedger::in_memory(Default::default())),
+            approvals: ToolApprovals::new(),
+            context_windows: ContextWindows::default(),
+        }
+    }
+
+    /// Checks that the chat and embedding providers are reachable and offer their models, so
+    /// misconfiguration surfaces before the first prompt.
+    ///
+    /// Only clients answering prompts need this; managing sessions, memories and usage works
+    /// without any provider.
+    pub async fn probe(&self) -> Result<()> {
+        match &self.router {
+            Some(router) => router.probe(ProviderRole::Chat).await?,
+            None => self.llm_client.probe(ProviderRole::Chat).await?,
+        }
+        // A shared provider was already probed along with the chat model
+        if self.config.embedding_provider != self.config.provider {
+            self.embedding_client.probe(ProviderRole::Embedding).await?;
+        }
+        info!("Providers are reachable.");
+        Ok(())
+    }
+
+    /// Routes each session's prompts through `router`, so private sessions stay on local
+    /// providers and personas can prefer a provider. Without a router every prompt goes to
+    /// the chat provider and private sessions are refused.
+    pub fn with_router(mut self, router: ProviderRouter) -> Self {
+        self.router = Some(router);
+        self
+    }
+
+    /// Records the provider calls made for each session in `ledger` instead of keeping them
+    /// in memory.
+    pub fn with_usage_ledger(mut self, ledger: Arc<UsageLedger>) -> Self {
+        self.usage = ledger;
+        self
+    }
+
+    /// The provider answering prompts of a session.
+    fn chat_client(&self, session: &Session, persona: &Persona) -> Result<Arc<dyn LLMProvider>> {
+        let Some(router) = &self.router else {
+            if session.private {
+                return Err(Error::LLM(LLMError::Configuration(String::from(
+                    "Private sessions need a local provider",
+                ))));
+            }
+            return Ok(Arc::new(MeteredProvider::new(
+                self.llm_client.clone(),
+                self.config.provider.clone(),
+                self.usage.clone(),
+                Some(session.id),
+            )));
+        };
+
+        let mut router = if session.private {
+            router.local_only()?
+        } else {
+            router.clone()
+        };
+        if let Some(provider) = &persona.provider {
+            router = router.preferring(provider);
+        }
+        Ok(Arc::new(router.metered(self.usage.clone(), Some(session.id))))
+    }
+
+    /// The tool definitions offered with a prompt: the persona's tools, narrowed down to the
+    /// most relevant ones when the config asks for retrieval.
+    ///
+    /// Retrieval embeds the prompt, so private sessions are offered all of the persona's tools
+    /// rather than sending their prompts to a possibly remote embedding provider.
+    async fn offered_tools(
+        &self,
+        session: &Session,
+        persona: &Persona,
+        prompt: &str,
+    ) -> Vec<ToolDefinition> {
+        let tools = persona.select_tools(self.tool_registry.get_tool_definitions(prompt).await);
+        if self.config.tools.selection == ToolSelection::All || session.private {
+            return tools;
+        }
+        let all = tools.clone();
+        self.tool_index
+            .retrieve(
+                &*self.embedding_client,
+                &self.tool_registry,
+                prompt,
+                tools,
+                self.config.tools.max_tools,
+                self.config.tools.min_tool_score,
+            )
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to retrieve relevant tools, offering all: {}", e);
+                all
+            })
+    }
+
+    /// The persona answering a session.
+    fn persona(&self, session: &Session) -> Result<Persona> {
+        Persona::resolve(&self.config, session.persona.as_deref())
+    }
+
+    /// Starts a new conversation session and returns its id.
+    pub async fn create_session(&self) -> Result<SessionId> {
+        self.sessions.create().await
+    }
+
+    /// Lists the stored conversation sessions, most recently updated first.
+    pub async fn list_sessions(&self) -> Result<Vec<SessionInfo>> {
+        self.sessions.list().await
+    }
+
+    /// Loads an existing session so the conversation can be continued.
+    pub async fn resume_session(&self, session_id: SessionId) -> Result<Session> {
+        self.sessions.get(session_id).await
+    }
+
+    /// Marks a session as private: its prompts are only sent to local providers and its
+    /// exchanges are kept out of long-term memory.
+    pub async fn set_session_private(&self, session_id: SessionId, private: bool) -> Result<()> {
+        self.sessions.set_private(session_id, private).await
+    }
+
+    /// Picks the persona answering a session; `None` returns to the configured default.
+    pub async fn set_session_persona(
+        &self,
+        session_id: SessionId,
+        persona: Option<String>,
+    ) -> Result<()> {
+        // Fail on unknown names now rather than on the next prompt
+        Persona::resolve(&self.config, persona.as_deref())?;
+        self.sessions.set_persona(session_id, persona).await
+    }
+
+    /// Lists the names of the personas sessions can pick.
+    pub fn list_personas(&self) -> Vec<String> {
+        Persona::names(&self.config)
+    }
+
+    /// Deletes a session and its history.
+    pub async fn drop_session(&self, session_id: SessionId) -> Result<()> {
+        self.sessions.remove(session_id).await
+    }
+
+    /// Lists the stored memories, oldest first.
+    pub async fn list_memories(&self) -> Result<Vec<MemoryRecord>> {
+        self.memory.list().await
+    }
+
+    /// Deletes a stored memory so it is no longer recalled.
+    pub async fn forget_memory(&self, memory_id: MemoryId) -> Result<()> {
+        self.memory.forget(memory_id).await
+    }
+
+    /// Totals of the provider calls made in the last `days` days, or ever, per day or session.
+    pub fn usage_summary(
+        &self,
+        grouping: UsageGrouping,
+        days: Option<u32>,
+    ) -> Result<Vec<UsageSummary>> {
+        self.usage.summarize(grouping, days)
+    }
+
+    /// Handle on the tool calls waiting for approval, for clients to answer the
+    /// [`EngineEvent::ApprovalRequired`] events of streamed prompts.
+    pub fn approvals(&self) -> ToolApprovals {
+        self.approvals.clone()
+    }
+
+    /// Approves or denies a tool call waiting for approval.
+    pub fn answer_approval(&self, request_id: Uuid, approved: bool) -> Result<()> {
+        self.approvals.answer(request_id, approved)
+    }
+
+    /// Lists the models offered by the chat provider.
+    pub async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.llm_client.get_models().await
+    }
+
+    /// Processes a user prompt within a session, dispatching any tool calls the model makes
+    /// until it produces a final answer, and returning that response along with the memories
+    /// it was given and the resources it used.
+    ///
+    /// The oldest history and least relevant memories are left out when the request would not
+    /// fit the model's context window.
+    pub async fn process_prompt(
+        &self,
+        session_id: SessionId,
+        user_prompt: &str,
+    ) -> Result<PromptResponse> {
+        trace!("Engine processing prompt: '{}'", user_prompt);
+        let started = Instant::now();
+
+        let mut session = self.sessions.get(session_id).await?;
+        let persona = self.persona(&session)?;
+        let llm_client = self.chat_client(&session, &persona)?;
+        // Private sessions neither draw on nor add to long-term memory
+        let mut memories = if session.private {
+            Vec::new()
+        } else {
+            recall(&self.memory, user_prompt).await
+        };
+        let tools = self.offered_tools(&session, &persona, user_prompt).await;
+        let budget =
+            ContextBudget::for_provider(&self.config.context, &*llm_client, &self.context_windows)
+                .await;
+        if let Err(e) = compact(
+            &self.sessions,
+            &mut session,
+            &*llm_client,
+            &self.config.templates,
+            &self.config.context,
+            budget.estimator(),
+            &CancellationToken::new(),
+        )
+        .await
+        {
+            warn!("Failed to compact the conversation: {}", e);
+        }
+        let FittedRequest {
+            preamble,
+            history: mut chat_history,
+            report,
+        } = budget.fit(
+            &persona,
+            &self.config.templates,
+            &session,
+            user_prompt,
+            &tools,
+            &mut memories,
+        )?;
+        let mut usage = Usage {
+            context: Some(report),
+            ..Default::default()
+        };
+        let mut prompt = Message::user(user_prompt);
+        // Session messages at the start of the history, which tool rounds may leave out
+        let mut trimmable = chat_history.len();
+
+        let mut response_content = None;
+        for iteration in 0..self.config.tools.max_iterations {
+            debug!("Sending prompt with {} tools (round {})", tools.len(), iteration + 1);
+            let mut request = build_request(
+                &persona,
+                preamble.clone(),
+                prompt.clone(),
+                chat_history.clone(),
+                tools.clone(),
+            );
+            if iteration > 0 {
+                let report = usage.context.get_or_insert_default();
+                let trimmed = budget.fit_round(&mut request, trimmable, report)?;
+                chat_history.drain(..trimmed);
+                trimmable -= trimmed;
+            }
+            usage.requests += 1;
+            let input_tokens = budget.estimator().estimate_request(&request) as u64;
+            let Completion {
+                choice,
+                usage: reported,
+            } = llm_client
+                .complete(request)
+                .await
+                .map_err(|e| Error::LLM(LLMError::Api(f!("Coordinator chat error: {}", e))))?; // Map error
+            let output_tokens = choice
+                .iter()
+                .map(|content| budget.estimator().estimate_content(content))
+                .sum::<usize>();
+            usage.add_tokens(
+                reported,
+                TokenUsage {
+                    input_tokens,
+                    output_tokens: output_tokens as u64,
+                },
+            );
+
+            let (text, tool_calls) = split_tool_calls(&choice);
+            if tool_calls.is_empty() {
+                response_content = Some(text);
+                break;
+            }
+
+            let results =
+                dispatch_tool_calls(&self.tool_registry, &self.config.tools, &tool_calls).await?;
+            usage.tool_calls += tool_calls.len();
+            chat_history.push(prompt);
+            chat_history.push(Message::Assistant { content: choice });
+            prompt = results;
+        }
+
+        let response_content = response_content.ok_or(Error::Tool(ToolError::IterationLimit(
+            self.config.tools.max_iterations,
+        )))?;
+
+        let turn = self
+            .sessions
+            .record_turn(
+                session_id,
+                Message::user(user_prompt),
+                Message::assistant(response_content.clone()),
+            )
+            .await?;
+
+        // A memory that could not be stored should not cost the user their answer
+        if !session.private
+            && let Err(e) = self
+                .memory
+                .remember(session_id, turn, user_prompt, &response_content)
+                .await
+        {
+            warn!("Failed to store memory of the interaction: {}", e);
+        }
+
+        usage.elapsed_ms = started.elapsed().as_millis() as u64;
+        Ok(PromptResponse {
+            content: response_content,
+            memories,
+            usage,
+        })
+    }
+
+    /// Processes a user prompt within a session, dispatching any tool calls the model makes
+    /// until it produces a final answer, and returning the progress as a stream of events.
+    ///
+    /// The stream ends with [`EngineEvent::Done`], [`EngineEvent::Interrupted`] or
+    /// [`EngineEvent::Error`]. The exchange is added to the session history once the answer
+    /// is complete, or with the partial answer when `cancel` stops it early.
+    ///
+    /// Tool calls the approval policy asks about wait after [`EngineEvent::ApprovalRequired`]
+    /// until they are answered through [`Engine::answer_approval`].
+    pub async fn process_prompt_stream(
+        &self,
+        session_id: SessionId,
+        user_prompt: String,
+        cancel: CancellationToken,
+    ) -> Result<EngineStream> {
+        trace!("Engine processing prompt (stream): '{}'", user_prompt);
+
+        let mut session = self.sessions.get(session_id).await?;
+        let persona = self.persona(&session)?;
+        let llm_client = self.chat_client(&session, &persona)?;
+        let private = session.private;
+        let tools = self.offered_tools(&session, &persona, &user_prompt).await;
+        let budget =
+            ContextBudget::for_provider(&self.config.context, &*llm_client, &self.context_windows)
+                .await;
+
+        let tool_registry = self.tool_registry.clone();
+        let tools_config = self.config.tools.clone();
+        let approvals = self.approvals.clone();
+        let sessions = self.sessions.clone();
+        let memory = self.memory.clone();
+        let max_iterations = self.config.tools.max_iterations;
+        let templates = self.config.templates.clone();
+        let context = self.config.context.clone();
+
+        let session_stream = async_stream::stream! {
+            let started = Instant::now();
+            let mut memories = if private { Vec::new() } else { recall(&memory, &user_prompt).await };
+            // A prompt cancelled while compacting goes on to report an empty interrupted answer
+            match compact(&sessions, &mut session, &*llm_client, &templates, &context, budget.estimator(), &cancel).await {
+                Ok(true) => yield EngineEvent::Compacted {
+                    summary: session.summary.clone().unwrap_or_default(),
+                    messages: session.compacted,
+                },
+                Ok(false) => {}
+                Err(e) => warn!("Failed to compact the conversation: {}", e),
+            }
+            let FittedRequest { preamble, history: mut chat_history, report } = match budget.fit(&persona, &templates, &session, &user_prompt, &tools, &mut memories) {
+                Ok(fitted) => fitted,
+                Err(e) => {
+                    yield EngineEvent::Error(e.to_string());
+                    return;
+                }
+            };
+            if !memories.is_empty() {
+                yield EngineEvent::MemoriesRecalled(memories);
+            }
+
+            let mut usage = Usage { context: Some(report), ..Default::default() };
+            let mut prompt = Message::user(user_prompt.clone());
+            let mut response = String::new();
+            let mut interrupted = false;
+            // Session messages at the start of the history, which tool rounds may leave out
+            let mut trimmable = chat_history.len();
+
+            'rounds: for round in 0..max_iterations {
+                let mut request = build_request(&persona, preamble.clone(), prompt.clone(), chat_history.clone(), tools.clone());
+                if round > 0 {
+                    match budget.fit_round(&mut request, trimmable, usage.context.get_or_insert_default()) {
+                        Ok(trimmed) => {
+                            chat_history.drain(..trimmed);
+                            trimmable -= trimmed;
+                        }
+                        Err(e) => {
+                            yield EngineEvent::Error(e.to_string());
+                            return;
+                        }
+                    }
+                }
+                usage.requests += 1;
+                let input_tokens = budget.estimator().estimate_request(&request) as u64;
+                let mut result_stream = match cancel.run(llm_client.generate_stream(request)).await {
+                    Some(Ok(stream)) => stream,
+                    Some(Err(e)) => {
+                        yield EngineEvent::Error(f!("Coordinator chat error: {}", e));
+                        return;
+                    }
+                    None => {
+                        interrupted = true;
+                        break 'rounds;
+                    }
+                };
+
+                let mut splitter = ReasoningSplitter::default();
+                // Everything the model streamed, its reasoning included
+                let mut output = String::new();
+                let mut text = String::new();
+                let mut tool_calls = Vec::new();
+                loop {
+                    let chunk = match cancel.run(result_stream.next()).await {
+                        Some(Some(chunk)) => chunk,
+                        Some(None) => break,
+                        None => {
+                            interrupted = true;
+                            break;
+                        }
+                    };
+                    match chunk {
+                        Ok(StreamingChoice::Message(chunk)) => {
+                            output.push_str(&chunk);
+                            for event in splitter.push(&chunk) {
+                                if let EngineEvent::TextDelta(delta) = &event {
+                                    text.push_str(delta);
+                                }
+                                yield event;
+                            }
+                        }
+                        Ok(StreamingChoice::ToolCall(name, id, arguments)) => {
+                            tool_calls.push(ToolCall { id, function: ToolFunction { name, arguments } });
+                        }
+                        Err(e) => {
+                            yield EngineEvent::Error(e.to_string());
+                            return;
+                        }
+                    }
+                }
+                for event in splitter.finish() {
+                    if let EngineEvent::TextDelta(delta) = &event {
+                        text.push_str(delta);
+                    }
+                    yield event;
+                }
+                // Streams carry no token counts, so they are estimated as the ledger does
+                let output_tokens = budget.estimator().estimate(&output)
+                    + tool_calls.iter().map(|call| budget.estimator().estimate_tool_call(call)).sum::<usize>();
+                usage.add_tokens(None, TokenUsage { input_tokens, output_tokens: output_tokens as u64 });
+                response.push_str(&text);
+                if interrupted {
+                    break 'rounds;
+                }
+
+                if tool_calls.is_empty() {
+                    let turn = match sessions
+                        .record_turn(session_id, Message::user(user_prompt.clone()), Message::assistant(response.clone()))
+                        .await
+                    {
+                        Ok(turn) => turn,
+                        Err(e) => {
+                            yield EngineEvent::Error(e.to_string());
+                            return;
+                        }
+                    };
+                    usage.elapsed_ms = started.elapsed().as_millis() as u64;
+                    yield EngineEvent::Usage(usage);
+                    yield EngineEvent::Done { content: response.clone() };
+
+                    // Remember the exchange once the client has the full answer
+                    if !private && let Err(e) = memory.remember(session_id, turn, &user_prompt, &response).await {
+                        warn!("Failed to store memory of the interaction: {}", e);
+                    }
+                    return;
+                }
+
+                let assistant = match tool_call_message(text, &tool_calls) {
+                    Ok(assistant) => assistant,
+                    Err(e) => {
+                        yield EngineEvent::Error(e.to_string());
+                        return;
+                    }
+                };
+
+                let mut outcomes = Vec::with_capacity(tool_calls.len());
+                for call in &tool_calls {
+                    if cancel.is_cancelled() {
+                        interrupted = true;
+                        break 'rounds;
+                    }
+                    yield EngineEvent::ToolCallStarted {
+                        id: tool_call_id(call),
+                        name: call.function.name.clone(),
+                        arguments: call.function.arguments.clone(),
+                    };
+                    let outcome = match tool_registry.policy(&call.function.name, &call.function.arguments, &tools_config) {
+                        ToolPolicy::Allow => run_tool_call(&tool_registry, call).await,
+                        ToolPolicy::Deny => refused_tool_call(call, DENIED_BY_POLICY),
+                        ToolPolicy::Ask => {
+                            let (id, answer) = approvals.request();
+                            yield EngineEvent::ApprovalRequired(ApprovalRequest {
+                                id,
+                                tool: call.function.name.clone(),
+                                arguments: call.function.arguments.clone(),
+                                risk: tool_registry.call_risk(&call.function.name, &call.function.arguments).unwrap_or(RiskLevel::High),
+                                preview: tool_registry.preview(&call.function.name, &call.function.arguments),
+                            });
+                            match approvals.wait(id, answer, &cancel).await {
+                                Some(true) => run_tool_call(&tool_registry, call).await,
+                                Some(false) => refused_tool_call(call, DENIED_BY_USER),
+                                None => {
+                                    interrupted = true;
+                                    break 'rounds;
+                                }
+                            }
+                        }
+                    };
+                    yield EngineEvent::ToolResult {
+                        id: outcome.id.clone(),
+                        name: outcome.name.clone(),
+                        output: outcome.output.clone(),
+                        is_error: outcome.is_error,
+                    };
+                    outcomes.push(outcome);
+                }
+                usage.tool_calls += outcomes.len();
+
+                match tool_results_message(&outcomes) {
+                    Ok(results) => {
+                        chat_history.push(prompt);
+                        chat_history.push(assistant);
+                        prompt = results;
+                    }
+                    Err(e) => {
+                        yield EngineEvent::Error(e.to_string());
+                        return;
+                    }
+                }
+            }
+
+            if interrupted {
+                // The partial answer is kept so the conversation shows what was said. Nothing
+                // is kept when nothing was said, as providers reject empty messages
+                if !response.is_empty() && let Err(e) = sessions
+                    .record_interrupted_turn(session_id, Message::user(user_prompt.clone()), Message::assistant(response.clone()))
+                    .await
+                {
+                    yield EngineEvent::Error(e.to_string());
+                    return;
+                }
+                usage.elapsed_ms = started.elapsed().as_millis() as u64;
+                yield EngineEvent::Usage(usage);
+                yield EngineEvent::Interrupted { content: response };
+                return;
+            }
+
+            yield EngineEvent::Error(ToolError::IterationLimit(max_iterations).to_string());
+        };
+
+        Ok(Box::pin(session_stream))
+    }
+
+    /// Returns a clone of the engine's configuration
+    pub fn get_config(&self) -> AppConfig {
+        // Dereference the Arc and clone the AppConfig
+        (*self.config).clone()
+    }
+}
+
+/// Recalls the memories relevant to a prompt; answering does not depend on memory being
+/// available, so failures only produce a warning.
+async fn recall(memory: &MemoryManager, prompt: &str) -> Vec<ScoredMemory> {
+    memory.recall(prompt).await.unwrap_or_else(|e| {
+        warn!("Failed to recall memories: {}", e);
+        Vec::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        llm::{CallKind, LLMProviders, MockProvider, MockReply, MockToolCall},
+        memory::InMemoryStore,
+    };
+
+    fn engine(provider: &Arc<MockProvider>) -> Engine {
+        Engine::from_parts(
+            Arc::new(AppConfig::default()),
+            provider.clone(),
+            provider.clone(),
+            SessionStore::in_memory(),
+            Arc::new(InMemoryStore::new()),
+        )
+    }
+
+    #[async_std::test]
+    async fn test_process_prompt_runs_tools_and_remembers() {
+        let provider = Arc::new(MockProvider::scripted([
+            MockReply::tool_call("calculator", json!({ "expression": "2 + 2" })).with_usage(100, 5),
+            MockReply::text("It is 4").with_usage(120, 3),
+            MockReply::text("User asked for 2 + 2"),
+        ]));
+        let engine = engine(&provider);
+        let session_id = engine.create_session().await.unwrap();
+
+        let response = engine
+            .process_prompt(session_id, "What is 2 + 2?")
+            .await
+            .unwrap();
+        assert_eq!(response.content, "It is 4");
+        assert_eq!(
+            (response.usage.input_tokens, response.usage.output_tokens),
+            (Some(220), Some(8))
+        );
+        assert!(!response.usage.estimated_tokens);
+
+        let session = engine.resume_session(session_id).await.unwrap();
+        assert_eq!(session.transcript().len(), 2);
+        let memories = engine.list_memories().await.unwrap();
+        assert_eq!(memories[0].content, "User asked for 2 + 2");
+        assert!(provider.prompts().await[2].contains("What is 2 + 2?"));
+
+        let usage = engine.usage_summary(UsageGrouping::Session, None).unwrap();
+        assert_eq!(usage[0].group, session_id.to_string());
+        assert_eq!(usage[0].calls, 2);
+    }
+
+    #[async_std::test]
+    async fn test_process_prompt_stream_events() {
+        let provider = Arc::new(MockProvider::scripted([
+            MockReply::tool_call("calculator", json!({ "expression": "2 + 2" })),
+            MockReply::chunks(["<think>Add</think>", "It is ", "4"]),
+            MockReply::text("User asked for 2 + 2"),
+        ]));
+        let engine = engine(&provider);
+        let session_id = engine.create_session().await.unwrap();
+
+        let events = engine
+            .process_prompt_stream(
+                session_id,
+                "What is 2 + 2?".to_string(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+
+        assert!(
+            matches!(&events[0], EngineEvent::ToolCallStarted { name, .. } if name == "calculator")
+        );
+        assert!(matches!(
+            &events[1],
+            EngineEvent::ToolResult {
+                is_error: false,
+                ..
+            }
+        ));
+        assert_eq!(events[2], EngineEvent::Reasoning("Add".to_string()));
+        assert!(
+            matches!(&events[5], EngineEvent::Usage(usage) if usage.requests == 2 && usage.tool_calls == 1 && usage.estimated_tokens && usage.output_tokens > Some(0))
+        );
+        assert_eq!(
+            events[6],
+            EngineEvent::Done {
+                content: "It is 4".to_string()
+            }
+        );
+        assert_eq!(engine.list_memories().await.unwrap().len(), 1);
+    }
+
+    #[async_std::test]
+    async fn test_stream_pairs_tool_calls_without_id() {
+        // Ollama and Gemini stream tool calls without an id
+        let provider = Arc::new(MockProvider::scripted([
+            MockReply {
+                tool_calls: vec![MockToolCall {
+                    id: String::new(),
+                    name: String::from("calculator"),
+                    arguments: json!({ "expression": "2 + 2" }),
+                }],
+                ..Default::default()
+            },
+            MockReply::text("It is 4"),
+            MockReply::text("User asked for 2 + 2"),
+        ]));
+        let engine = engine(&provider);
+        let session_id = engine.create_session().await.unwrap();
+
+        let events = engine
+            .process_prompt_stream(
+                session_id,
+                "What is 2 + 2?".to_string(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+
+        let (
+            EngineEvent::ToolCallStarted { id: started, .. },
+            EngineEvent::ToolResult { id: finished, .. },
+        ) = (&events[0], &events[1])
+        else {
+            panic!("The tool call must start and finish first");
+        };
+        assert_eq!(started, "calculator");
+        assert_eq!(started, finished);
+    }
+
+    #[async_std::test]
+    async fn test_private_sessions_do_not_embed_prompts() {
+        let chat = Arc::new(MockProvider::scripted([
+            MockReply::text("Hello"),
+            MockReply::text("Hello again"),
+            MockReply::text("User greeted"),
+        ]));
+        let ledger = Arc::new(UsageLedger::in_memory(Default::default()));
+        let embedding = Arc::new(MeteredProvider::new(
+            Arc::new(MockProvider::scripted([])),
+            LLMProviders::Mock,
+            ledger.clone(),
+            None,
+        ));
+        let mut config = AppConfig::default();
+        config.tools.selection = ToolSelection::Retrieved;
+        let local: Arc<dyn LLMProvider> = chat.clone();
+        let router = ProviderRouter::with_providers(
+            [(LLMProviders::Ollama, local, false)],
+            Default::default(),
+        )
+        .unwrap();
+        let engine = Engine::from_parts(
+            Arc::new(config),
+            chat,
+            embedding,
+            SessionStore::in_memory(),
+            Arc::new(InMemoryStore::new()),
+        )
+        .with_router(router);
+        let embeddings = || {
+            ledger
+                .records()
+                .unwrap()
+                .iter()
+                .filter(|record| record.kind == CallKind::Embedding)
+                .count()
+        };
+
+        let session_id = engine.create_session().await.unwrap();
+        engine.set_session_private(session_id, true).await.unwrap();
+        engine.process_prompt(session_id, "Hi").await.unwrap();
+        assert_eq!(embeddings(), 0);
+
+        engine.set_session_private(session_id, false).await.unwrap();
+        engine.process_prompt(session_id, "Hi").await.unwrap();
+        assert!(embeddings() > 0);
+    }
+
+    #[async_std::test]
+    async fn test_cancelled_stream_keeps_partial_answer() {
+        let provider = Arc::new(MockProvider::scripted([MockReply::chunks([
+            "It is ",
+            "4",
+            " for sure",
+        ])]));
+        let engine = engine(&provider);
+        let session_id = engine.create_session().await.unwrap();
+
+        let cancel = CancellationToken::new();
+        let mut stream = engine
+            .process_prompt_stream(session_id, "What is 2 + 2?".to_string(), cancel.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            stream.next().await,
+            Some(EngineEvent::TextDelta("It is ".to_string()))
+        );
+        cancel.cancel();
+        let events = stream.collect::<Vec<_>>().await;
+
+        assert_eq!(
+            events.last(),
+            Some(&EngineEvent::Interrupted {
+                content: "It is ".to_string()
+            })
+        );
+        let transcript = engine
+            .resume_session(session_id)
+            .await
+            .unwrap()
+            .transcript();
+        assert_eq!(transcript[1].content, "It is ");
+        assert!(transcript[1].interrupted);
+        assert!(engine.list_memories().await.unwrap().is_empty());
+
+        // Cancelling before any text leaves no empty answer in the history
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let events = engine
+            .process_prompt_stream(session_id, "And 3 + 3?".to_string(), cancel)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            events.last(),
+            Some(&EngineEvent::Interrupted {
+                content: String::new()
+            })
+        );
+        let session = engine.resume_session(session_id).await.unwrap();
+        assert_eq!(session.history.len(), 2);
+    }
+
+    #[async_std::test]
+    async fn test_stream_waits_for_tool_approval() {
+        let provider = Arc::new(MockProvider::scripted([
+            MockReply::tool_call("calculator", json!({ "expression": "2 + 2" })),
+            MockReply::text("I was not allowed to calculate"),
+            MockReply::text("User asked for 2 + 2"),
+        ]));
+        let mut config = AppConfig::default();
+        config
+            .tools
+            .approval
+            .tools
+            .insert(String::from("calculator"), ToolPolicy::Ask);
+        let engine = Engine::from_parts(
+            Arc::new(config),
+            provider.clone(),
+            provider.clone(),
+            SessionStore::in_memory(),
+            Arc::new(InMemoryStore::new()),
+        );
+        let session_id = engine.create_session().await.unwrap();
+
+        let mut stream = engine
+            .process_prompt_stream(
+                session_id,
+                "What is 2 + 2?".to_string(),
+                CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            stream.next().await,
+            Some(EngineEvent::ToolCallStarted { .. })
+        ));
+        let Some(EngineEvent::ApprovalRequired(request)) = stream.next().await else {
+            panic!("Calculator calls must wait for approval");
+        };
+        assert_eq!(request.tool, "calculator");
+        assert_eq!(request.risk, RiskLevel::Low);
+
+        engine.answer_approval(request.id, false).unwrap();
+        assert!(engine.answer_approval(request.id, true).is_err());
+        let events = stream.collect::<Vec<_>>().await;
+        assert!(matches!(
+            &events[0],
+            EngineEvent::ToolResult { output, is_error: true, .. } if output.contains(DENIED_BY_USER)
+        ));
+        assert_eq!(
+            events.last(),
+            Some(&EngineEvent::Done {
+                content: "I was not allowed to calculate".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Named profiles shaping how the assistant answers.
+
+use rig::completion::ToolDefinition;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+
+use crate::{
+    config::{AppConfig, ConfigError},
+    llm::LLMProviders,
+    prelude::*,
+    templates::{
+        DATE, DEFAULT_PREAMBLE, MEMORIES, PREAMBLE_VARIABLES, PromptTemplate, TOOLS, today,
+        tool_list,
+    },
+};
+
+/// Name of the persona available without any configuration.
+pub const DEFAULT_PERSONA: &str = "default";
+
+/// A system preamble and generation settings applied to every prompt of a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Persona {
+    /// System prompt sent with every request, a template that may use `{{date}}`,
+    /// `{{memories}}` and `{{tools}}`, checked when the persona is loaded
+    #[serde(serialize_with = "serialize_preamble")]
+    #[serde(deserialize_with = "deserialize_preamble")]
+    pub preamble: PromptTemplate,
+
+    #[serde(default)]
+    pub temperature: Option<f64>,
+
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+
+    /// Names of the tools offered to the model; empty offers every registered tool
+    #[serde(default)]
+    pub tools: Vec<String>,
+
+    /// Provider tried first for this persona's prompts
+    #[serde(default)]
+    pub provider: Option<LLMProviders>,
+}
+
+impl Default for Persona {
+    fn default() -> Self {
+        Self {
+            preamble: PromptTemplate::builtin("preamble", DEFAULT_PREAMBLE, PREAMBLE_VARIABLES),
+            temperature: None,
+            max_tokens: None,
+            tools: Vec::new(),
+            provider: None,
+        }
+    }
+}
+
+impl Persona {
+    /// Looks up a persona by name; `None` picks the configured default persona.
+    ///
+    /// The built-in persona, whose preamble comes from the `preamble` template, is used for
+    /// [`DEFAULT_PERSONA`] unless the config overrides it.
+    pub fn resolve(config: &AppConfig, name: Option<&str>) -> Result<Self> {
+        let name = name
+            .or(config.default_persona.as_deref())
+            .unwrap_or(DEFAULT_PERSONA);
+        match config.personas.get(name) {
+            Some(persona) => Ok(persona.clone()),
+            None if name == DEFAULT_PERSONA => Ok(Self {
+                preamble: config.templates.preamble.clone(),
+                ..Default::default()
+            }),
+            None => Err(Error::Config(ConfigError::ValidationError(f!(
+                "Unknown persona '{}'",
+                name
+            )))),
+        }
+    }
+
+    /// Names of the configured personas and the built-in one, sorted.
+    pub fn names(config: &AppConfig) -> Vec<String> {
+        let mut names = config.personas.keys().cloned().collect::<Vec<_>>();
+        if !config.personas.contains_key(DEFAULT_PERSONA) {
+            names.push(DEFAULT_PERSONA.to_string());
+        }
+        names.sort();
+        names
+    }
+
+    /// Renders the preamble for a prompt offering `tools`. Recalled memories are appended
+    /// unless the preamble places them itself.
+    pub(crate) fn render_preamble(&self, memories: &str, tools: &[ToolDefinition]) -> String {
+        let preamble = self.preamble.render(&[
+            (DATE, &today()),
+            (MEMORIES, memories),
+            (TOOLS, &tool_list(tools)),
+        ]);
+        if memories.is_empty() || self.preamble.uses(MEMORIES) {
+            preamble
+        } else {
+            f!("{}\n\n{}", preamble, memories)
+        }
+    }
+
+    /// Keeps the tool definitions this persona offers to the model.
+    pub(crate) fn select_tools(&self, tools: Vec<ToolDefinition>) -> Vec<ToolDefinition> {
+        if self.tools.is_empty() {
+            return tools;
+        }
+        tools
+            .into_iter()
+            .filter(|tool| self.tools.contains(&tool.name))
+            .collect()
+    }
+}
+
+fn serialize_preamble<S: Serializer>(
+    preamble: &PromptTemplate,
+    serializer: S,
+) -> StdResult<S::Ok, S::Error> {
+    serializer.serialize_str(preamble.text())
+}
+
+fn deserialize_preamble<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> StdResult<PromptTemplate, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    PromptTemplate::parse("persona", text, PREAMBLE_VARIABLES).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_persona() {
+        let mut config = AppConfig::default();
+        assert_eq!(Persona::resolve(&config, None).unwrap(), Persona::default());
+
+        let terse = Persona {
+            preamble: PromptTemplate::parse(
+                "persona",
+                "Answer in one sentence.",
+                PREAMBLE_VARIABLES,
+            )
+            .unwrap(),
+            tools: vec!["calculator".to_string()],
+            ..Default::default()
+        };
+        config.personas.insert("terse".to_string(), terse.clone());
+        config.default_persona = Some("terse".to_string());
+
+        assert_eq!(Persona::resolve(&config, None).unwrap(), terse);
+        assert_eq!(
+            Persona::resolve(&config, Some(DEFAULT_PERSONA)).unwrap(),
+            Persona::default()
+        );
+        assert!(Persona::resolve(&config, Some("missing")).is_err());
+        assert!(
+            serde_json::from_value::<Persona>(serde_json::json!({ "preamble": "Hi {{user}}" }))
+                .is_err()
+        );
+        assert_eq!(Persona::names(&config), ["default", "terse"]);
+
+        // The built-in preamble lists the tools offered for the prompt
+        let calculator = ToolDefinition {
+            name: "calculator".to_string(),
+            description: "Evaluates arithmetic expressions".to_string(),
+            parameters: serde_json::json!({}),
+        };
+        let preamble = Persona::default().render_preamble("", &[calculator]);
+        assert!(preamble.ends_with("\n- calculator: Evaluates arithmetic expressions"));
+        assert!(
+            Persona::default()
+                .render_preamble("", &[])
+                .ends_with("(none)")
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! Conversation sessions and their persistence.
+
+use std::{cmp::Reverse, collections::HashMap, fs, path::PathBuf, sync::Arc};
+
+use async_std::sync::RwLock;
+use chrono::{DateTime, Utc};
+use rig::{
+    completion::Message,
+    message::{AssistantContent, UserContent},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{config, prelude::*};
+
+const SESSIONS_DIR_NAME: &str = "sessions";
+const TITLE_MAX_CHARS: usize = 48;
+
+/// Identifier of a conversation session.
+pub type SessionId = Uuid;
+
+/// A single conversation with its accumulated chat history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: SessionId,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub history: Vec<Message>,
+    /// Answered by local providers only and kept out of long-term memory
+    #[serde(default)]
+    pub private: bool,
+    /// Positions in `history` of the answers cancelled before they were complete
+    #[serde(default)]
+    pub interrupted_messages: Vec<usize>,
+    /// Persona answering the session; the configured default when unset
+    #[serde(default)]
+    pub persona: Option<String>,
+    /// Running summary sent in place of the compacted start of the history
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// Number of leading history messages covered by `summary`
+    #[serde(default)]
+    pub compacted: usize,
+}
+
+impl Session {
+    /// Creates a new, empty session.
+    pub fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            created_at: now,
+            updated_at: now,
+            history: Vec::new(),
+            private: false,
+            interrupted_messages: Vec::new(),
+            persona: None,
+            summary: None,
+            compacted: 0,
+        }
+    }
+
+    /// Appends a completed user/assistant exchange to the history.
+    pub fn push_turn(&mut self, prompt: Message, response: Message) {
+        self.history.push(prompt);
+        self.history.push(response);
+        self.updated_at = Utc::now();
+    }
+
+    /// The history not covered by the summary, which is sent to the model verbatim.
+    pub fn active_history(&self) -> Vec<Message> {
+        self.history[self.compacted.min(self.history.len())..].to_vec()
+    }
+
+    /// A short title derived from the first user message.
+    pub fn title(&self) -> String {
+        let Some(text) = self.history.iter().find_map(message_text) else {
+            return String::from("New conversation");
+        };
+
+        let mut title = text.chars().take(TITLE_MAX_CHARS).collect::<String>();
+        if text.chars().count() > TITLE_MAX_CHARS {
+            title.push('…');
+        }
+        title
+    }
+
+    /// The text-only exchange of this session, suitable for displaying to the user.
+    pub fn transcript(&self) -> Vec<TranscriptEntry> {
+        self.history
+            .iter()
+            .enumerate()
+            .filter_map(|(index, message)| {
+                let role = match message {
+                    Message::User { .. } => Role::User,
+                    Message::Assistant { .. } => Role::Assistant,
+                };
+                message_text(message).map(|content| TranscriptEntry {
+                    role,
+                    content,
+                    interrupted: self.interrupted_messages.contains(&index),
+                    compacted: index < self.compacted,
+                })
+            })
+            .collect()
+    }
+
+    /// Lightweight description of this session for listings.
+    pub fn info(&self) -> SessionInfo {
+        SessionInfo {
+            id: self.id,
+            title: self.title(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            message_count: self.history.len(),
+            private: self.private,
+        }
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Summary of a session as returned by [`SessionStore::list`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: SessionId,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub message_count: usize,
+    #[serde(default)]
+    pub private: bool,
+}
+
+/// Author of a transcript entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+/// A displayable message of a session transcript.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    pub role: Role,
+    pub content: String,
+    /// The answer was cancelled and is only partial
+    #[serde(default)]
+    pub interrupted: bool,
+    /// The message is covered by the session summary and no longer sent to the model
+    #[serde(default)]
+    pub compacted: bool,
+}
+
+/// Returns the plain text of a message, ignoring tool calls and other content.
+pub fn message_text(message: &Message) -> Option<String> {
+    let text = match message {
+        Message::User { content } => content
+            .iter()
+            .filter_map(|c| match c {
+                UserContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Message::Assistant { content } => content
+            .iter()
+            .filter_map(|c| match c {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    (!text.is_empty()).then_some(text)
+}
+
+/// Keeps conversation sessions in memory and, when backed by a directory,
+/// persists each one as a JSON file so it can be resumed between runs.
+#[derive(Clone, Default)]
+pub struct SessionStore {
+    dir: Option<PathBuf>,
+    sessions: Arc<RwLock<HashMap<SessionId, Session>>>,
+}
+
+impl SessionStore {
+    /// Creates a store persisting sessions under `<data dir>/sessions`.
+    pub fn new() -> Result<Self> {
+        let dir = config::get_data_dir()?.join(SESSIONS_DIR_NAME);
+        fs::create_dir_all(&dir)?;
+        Ok(Self::with_dir(dir))
+    }
+
+    /// Creates a store persisting sessions in the given directory.
+    pub fn with_dir(dir: PathBuf) -> Self {
+        Self {
+            dir: Some(dir),
+            sessions: Arc::default(),
+        }
+    }
+
+    /// Creates a store that never touches the filesystem.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Starts a new session and returns its id.
+    pub async fn create(&self) -> Result<SessionId> {
+        let session = Session::new();
+        let id = session.id;
+        self.save(&session)?;
+        self.sessions.write().await.insert(id, session);
+        debug!("Created session {}", id);
+        Ok(id)
+    }
+
+    /// Returns a session, loading it from disk if it is not cached yet.
+    pub async fn get(&self, id: SessionId) -> Result<Session> {
+        if let Some(session) = self.sessions.read().await.get(&id) {
+            return Ok(session.clone());
+        }
+
+        let session = self.load(id)?;
+        self.sessions.write().await.insert(id, session.clone());
+        Ok(session)
+    }
+
+    /// Returns the chat history of a session.
+    pub async fn history(&self, id: SessionId) -> Result<Vec<Message>> {
+        self.get(id).await.map(|session| session.history)
+    }
+
+    /// Lists all known sessions, most recently updated first.
+    pub async fn list(&self) -> Result<Vec<SessionInfo>> {
+        let mut infos: HashMap<SessionId, SessionInfo> = HashMap::new();
+
+        if let Some(dir) = &self.dir {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.extension().is_none_or(|ext| ext != "json") {
+                    continue;
+                }
+                match read_session(&path) {
+                    Ok(session) => {
+                        infos.insert(session.id, session.info());
+                    }
+                    Err(e) => warn!("Skipping unreadable session {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        for session in self.sessions.read().await.values() {
+            infos.insert(session.id, session.info());
+        }
+
+        let mut infos = infos.into_values().collect::<Vec<_>>();
+        infos.sort_by_key(|info| Reverse(info.updated_at));
+        Ok(infos)
+    }
+
+    /// Records a completed exchange in the session and persists it, returning the index of
+    /// the exchange within the session.
+    pub async fn record_turn(
+        &self,
+        id: SessionId,
+        prompt: Message,
+        response: Message,
+    ) -> Result<usize> {
+        self.store_turn(id, prompt, response, false).await
+    }
+
+    /// Records an exchange whose answer was cancelled, keeping the partial answer and
+    /// marking it as interrupted.
+    pub async fn record_interrupted_turn(
+        &self,
+        id: SessionId,
+        prompt: Message,
+        partial_response: Message,
+    ) -> Result<usize> {
+        self.store_turn(id, prompt, partial_response, true).await
+    }
+
+    async fn store_turn(
+        &self,
+        id: SessionId,
+        prompt: Message,
+        response: Message,
+        interrupted: bool,
+    ) -> Result<usize> {
+        self.update(id, |session| {
+            session.push_turn(prompt, response);
+            if interrupted {
+                session.interrupted_messages.push(session.history.len() - 1);
+            }
+            session.history.len() / 2 - 1
+        })
+        .await
+    }
+
+    /// Sets the persona answering a session and persists the change.
+    pub async fn set_persona(&self, id: SessionId, persona: Option<String>) -> Result<()> {
+        self.update(id, |session| session.persona = persona).await
+    }
+
+    /// Replaces the summary of a session, which now covers its first `compacted` history
+    /// messages, and persists the change.
+    pub async fn set_summary(
+        &self,
+        id: SessionId,
+        summary: String,
+        compacted: usize,
+    ) -> Result<()> {
+        self.update(id, |session| {
+            session.summary = Some(summary);
+            session.compacted = compacted;
+        })
+        .await
+    }
+
+    /// Marks a session as private, or lifts the mark, and persists the change.
+    pub async fn set_private(&self, id: SessionId, private: bool) -> Result<()> {
+        self.update(id, |session| session.private = private).await
+    }
+
+    /// Drops a session from memory and deletes its file.
+    pub async fn remove(&self, id: SessionId) -> Result<()> {
+        let cached = self.sessions.write().await.remove(&id).is_some();
+
+        let on_disk = match self.path(id) {
+            Some(path) if path.exists() => {
+                fs::remove_file(path)?;
+                true
+            }
+            _ => false,
+        };
+
+        if !cached && !on_disk {
+            return Err(Error::SessionNotFound(id));
+        }
+
+        debug!("Dropped session {}", id);
+        Ok(())
+    }
+
+    /// Changes a session and persists it. The write lock is held from reading the session to
+    /// storing it, so changes made at the same time are applied one after the other rather
+    /// than overwriting each other.
+    async fn update<T>(&self, id: SessionId, change: impl FnOnce(&mut Session) -> T) -> Result<T> {
+        let mut sessions = self.sessions.write().await;
+        let mut session = match sessions.get(&id) {
+            Some(session) => session.clone(),
+            None => self.load(id)?,
+        };
+        let output = change(&mut session);
+        self.save(&session)?;
+        sessions.insert(id, session);
+        Ok(output)
+    }
+
+    fn path(&self, id: SessionId) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(f!("{}.json", id)))
+    }
+
+    fn load(&self, id: SessionId) -> Result<Session> {
+        match self.path(id) {
+            Some(path) if path.exists() => read_session(&path),
+            _ => Err(Error::SessionNotFound(id)),
+        }
+    }
+
+    fn save(&self, session: &Session) -> Result<()> {
+        let Some(path) = self.path(session.id) else {
+            return Ok(());
+        };
+        fs::write(path, serde_json::to_string_pretty(session)?)?;
+        Ok(())
+    }
+}
+
+fn read_session(path: &PathBuf) -> Result<Session> {
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::temp_dir;
+
+    #[async_std::test]
+    async fn test_record_turn_accumulates_history() {
+        let store = SessionStore::in_memory();
+        let id = store.create().await.unwrap();
+
+        let first = store
+            .record_turn(id, Message::user("Hi"), Message::assistant("Hello!"))
+            .await
+            .unwrap();
+        let second = store
+            .record_turn(
+                id,
+                Message::user("How are you?"),
+                Message::assistant("Fine."),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!((first, second), (0, 1));
+
+        let history = store.history(id).await.unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[2], Message::user("How are you?"));
+    }
+
+    #[async_std::test]
+    async fn test_sessions_persist_between_stores() {
+        let dir = temp_dir();
+
+        let store = SessionStore::with_dir(dir.path().to_path_buf());
+        let id = store.create().await.unwrap();
+        store
+            .record_turn(id, Message::user("Remember me"), Message::assistant("Sure"))
+            .await
+            .unwrap();
+
+        let resumed = SessionStore::with_dir(dir.path().to_path_buf());
+        let session = resumed.get(id).await.unwrap();
+        assert_eq!(session.history.len(), 2);
+        assert_eq!(session.title(), "Remember me");
+        assert_eq!(resumed.list().await.unwrap().len(), 1);
+
+        resumed.remove(id).await.unwrap();
+        assert!(resumed.list().await.unwrap().is_empty());
+        assert!(matches!(
+            resumed.get(id).await,
+            Err(Error::SessionNotFound(_))
+        ));
+    }
+}
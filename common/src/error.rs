@@ -1,8 +1,10 @@
 use std::io::Error as IoError;
 
 use reqwest::Error as ReqwestError;
+use serde_json::Error as JsonError;
 use thiserror::Error as ThisError;
 use url::ParseError as UrlParseError;
+use uuid::Uuid;
 
 use crate::{config::ConfigError, llm::LLMError, memory::error::MemoryError, tools::ToolError};
 
@@ -23,6 +25,9 @@ pub enum Error {
     #[error("Requested tool not found: {0}")]
     ToolNotFound(String),
 
+    #[error("Conversation session not found: {0}")]
+    SessionNotFound(Uuid),
+
     #[error("Tool error: {0}")]
     Tool(#[from] ToolError),
 
@@ -32,6 +37,9 @@ pub enum Error {
     #[error(transparent)]
     Reqwest(#[from] ReqwestError),
 
+    #[error(transparent)]
+    Json(#[from] JsonError),
+
     #[error("URL parse error: {0}")]
     Url(#[from] UrlParseError),
 
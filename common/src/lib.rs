@@ -5,6 +5,7 @@ mod error;
 pub mod llm;
 pub mod memory;
 pub mod prelude;
+pub mod templates;
 pub mod tools;
 pub mod utils; // Added utils module
 
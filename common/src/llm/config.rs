@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
-use url::Url;
+use url::{Host, Url};
 
 use super::OllamaProviderConfig;
 use super::gemini::GeminiProviderConfig;
+use super::mock::{MockMode, MockProviderConfig};
+use super::openai_compatible::OpenAICompatibleProviderConfig;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)] // Added PartialEq
 #[serde(rename_all = "lowercase")]
@@ -10,16 +12,68 @@ pub enum LLMProviders {
     #[default]
     Ollama,
     Gemini,
+    /// Any server implementing the OpenAI API (llama.cpp, vLLM, LM Studio)
+    #[serde(rename = "openai_compatible")]
+    OpenAICompatible,
+    /// Scripted or recorded responses, for running without a live model
+    Mock,
+}
+
+impl LLMProviders {
+    /// Whether prompts sent to this provider leave the machine or local network.
+    pub fn is_cloud(&self, configs: &LLMConfig) -> bool {
+        match self {
+            LLMProviders::Gemini => true,
+            LLMProviders::Ollama => Url::parse(&configs.ollama.url)
+                .map(|url| !is_local(&url))
+                .unwrap_or(true),
+            LLMProviders::OpenAICompatible => !is_local(&configs.openai_compatible.base_url),
+            LLMProviders::Mock => {
+                configs.mock.mode == MockMode::Record
+                    && configs.mock.record_provider.is_cloud(configs)
+            }
+        }
+    }
+}
+
+/// Whether a URL points at this machine or a private network.
+fn is_local(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".local"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        Some(Host::Ipv6(ip)) => {
+            ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local()
+        }
+        None => false,
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct LLMConfig {
     pub ollama: OllamaProviderConfig,
     pub gemini: Option<GeminiProviderConfig>,
+    #[serde(default)]
+    pub openai_compatible: OpenAICompatibleProviderConfig,
+    #[serde(default)]
+    pub mock: MockProviderConfig,
+}
+
+/// Where long-term memories are stored.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryBackend {
+    /// A file in the user's data directory; needs no external service
+    #[default]
+    Local,
+    /// The Qdrant instance at `url`
+    Qdrant,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VectorDbConfig {
+    #[serde(default)]
+    pub backend: MemoryBackend,
+
     #[serde(default = "default_qdrant_url")]
     pub url: Url,
 
@@ -33,6 +87,7 @@ pub struct VectorDbConfig {
 impl Default for VectorDbConfig {
     fn default() -> Self {
         Self {
+            backend: MemoryBackend::default(),
             url: default_qdrant_url(),
             collection_name: default_collection_name(),
             vector_size: default_vector_size(),
@@ -0,0 +1,112 @@
+//! Embedding inputs and the shared logic for embedding them with rig models.
+
+use rig::{completion::Document, embeddings::EmbeddingModel};
+
+use crate::{llm::LLMError, prelude::*};
+
+/// Something that can be turned into an embedding vector.
+#[derive(Debug, Clone)]
+pub enum EmbeddingType {
+    Text(String),
+    Document(Document),
+}
+
+impl EmbeddingType {
+    /// The text sent to the embedding model.
+    pub fn into_text(self) -> String {
+        match self {
+            EmbeddingType::Text(text) => text,
+            EmbeddingType::Document(document) => document.text,
+        }
+    }
+}
+
+impl From<String> for EmbeddingType {
+    fn from(text: String) -> Self {
+        EmbeddingType::Text(text)
+    }
+}
+
+impl From<&str> for EmbeddingType {
+    fn from(text: &str) -> Self {
+        EmbeddingType::Text(text.to_string())
+    }
+}
+
+impl From<Document> for EmbeddingType {
+    fn from(document: Document) -> Self {
+        EmbeddingType::Document(document)
+    }
+}
+
+/// Embeds the inputs with a rig embedding model, in batches no larger than the model accepts,
+/// and checks every vector has the dimension expected by the vector store.
+pub(crate) async fn embed_with<M: EmbeddingModel>(
+    model: &M,
+    inputs: Vec<EmbeddingType>,
+    vector_size: u64,
+) -> Result<Vec<Vec<f32>>> {
+    let texts = inputs
+        .into_iter()
+        .map(EmbeddingType::into_text)
+        .collect::<Vec<_>>();
+    debug!("Embedding {} input(s)", texts.len());
+
+    let mut vectors = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(M::MAX_DOCUMENTS) {
+        let embeddings = model
+            .embed_texts(batch.to_vec())
+            .await
+            .map_err(|e| LLMError::Embedding(f!("{} input(s)", batch.len()), e.to_string()))?;
+        vectors.extend(
+            embeddings
+                .into_iter()
+                .map(|embedding| embedding.vec.into_iter().map(|x| x as f32).collect()),
+        );
+    }
+
+    check_dimensions(&vectors, vector_size)?;
+    Ok(vectors)
+}
+
+/// Ensures every vector has the configured dimension.
+pub(crate) fn check_dimensions(vectors: &[Vec<f32>], vector_size: u64) -> Result<()> {
+    match vectors.iter().find(|v| v.len() as u64 != vector_size) {
+        Some(vector) => Err(Error::LLM(LLMError::Embedding(
+            String::from("input"),
+            f!(
+                "Model returned {} dimensions but the vector store expects {}",
+                vector.len(),
+                vector_size
+            ),
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_embeds_its_text() {
+        let document = Document {
+            id: "doc-1".to_string(),
+            text: "Lyn remembers things".to_string(),
+            additional_props: Default::default(),
+        };
+        assert_eq!(
+            EmbeddingType::from(document).into_text(),
+            "Lyn remembers things"
+        );
+    }
+
+    #[test]
+    fn test_check_dimensions() {
+        assert!(check_dimensions(&[vec![0.0; 3], vec![1.0; 3]], 3).is_ok());
+        assert!(matches!(
+            check_dimensions(&[vec![0.0; 3], vec![1.0; 4]], 3),
+            Err(Error::LLM(LLMError::Embedding(_, _)))
+        ));
+    }
+}
@@ -18,6 +18,9 @@ pub enum LLMError {
     #[error("Error while embedding {0}. {1}")]
     Embedding(String, String),
 
+    #[error("Request of ~{tokens} tokens does not fit the context window of {window} tokens")]
+    ContextOverflow { tokens: usize, window: u64 },
+
     #[error("Other error: {0}")]
     Other(String),
 
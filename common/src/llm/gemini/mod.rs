@@ -1,55 +1,254 @@
 pub mod config;
 
-use std::{pin::Pin, sync::Arc};
+use std::sync::Arc;
 
 use async_std::stream::StreamExt;
-use futures::Stream;
 
+use reqwest::StatusCode;
 use rig::{
-    completion::{CompletionModel, CompletionRequest},
-    embeddings::EmbeddingsBuilder,
-    message::AssistantContent,
-    providers::gemini::Client as GeminiClient,
-    streaming::{StreamingChoice, StreamingCompletionModel},
+    OneOrMany,
+    completion::{self, CompletionRequest},
+    providers::gemini::{
+        Client as GeminiClient,
+        completion::gemini_api_types::{Content, GenerateContentResponse, Part, Role, Tool},
+        streaming::StreamGenerateContentResponse,
+    },
+    streaming::StreamingChoice,
 };
 
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
 use super::EmbeddingType;
 use crate::{
     config::AppConfig,
-    llm::{LLMError, LLMProvider},
+    llm::{
+        Completion, LLMError, LLMProvider, LLMStream, ModelCache, ModelInfo, TokenUsage,
+        embed_with, gemini_schema,
+    },
     prelude::*,
 };
 pub use config::GeminiProviderConfig;
 
+const GEMINI_MODELS_URL: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+
 pub struct GeminiProvider {
     client: GeminiClient,
     config: Arc<AppConfig>,
+    models: ModelCache,
 }
 
 impl GeminiProvider {
     pub fn new(config: Arc<AppConfig>) -> Result<Self> {
-        let client = GeminiClient::new(
-            &config
-                .provider_configs
-                .gemini
-                .as_ref()
-                .ok_or_else(|| LLMError::Configuration(String::from("Missing Gemini Config")))?
-                .api_key,
-        );
+        let settings = config
+            .provider_configs
+            .gemini
+            .as_ref()
+            .ok_or_else(|| LLMError::Configuration(String::from("Missing Gemini Config")))?;
+        if settings.api_key.is_empty() {
+            return Err(Error::LLM(LLMError::Configuration(String::from(
+                "The Gemini API key is empty",
+            ))));
+        }
+        let client = GeminiClient::new(&settings.api_key);
 
-        Ok(Self { client, config })
+        Ok(Self {
+            client,
+            config,
+            models: ModelCache::default(),
+        })
     }
-}
 
-#[async_trait::async_trait]
-impl LLMProvider for GeminiProvider {
-    fn model(&self) -> &str {
+    fn settings(&self) -> &GeminiProviderConfig {
         self.config
             .provider_configs
             .gemini
             .as_ref()
-            .map(|g| g.api_key.as_str())
-            .unwrap_or("gemini-2.0-flash-lite")
+            .expect("Gemini config is checked in GeminiProvider::new")
+    }
+
+    /// Builds a `generateContent` body from a rig request.
+    ///
+    /// Rig's Gemini client drops safety settings, so the body is assembled here from the
+    /// configured `safety_settings` and `generation_config`, with the request's own
+    /// parameters taking precedence.
+    fn request_body(&self, request: CompletionRequest) -> Result<Value> {
+        let settings = self.settings();
+        let mut generation_config = Map::new();
+        if let Some(temperature) = settings.generation_config.temperature {
+            generation_config.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(max_output_tokens) = settings.generation_config.max_output_tokens {
+            generation_config.insert("maxOutputTokens".to_string(), json!(max_output_tokens));
+        }
+        if let Some(Value::Object(params)) = request.additional_params.clone() {
+            generation_config.extend(params);
+        }
+        if let Some(temperature) = request.temperature {
+            generation_config.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            generation_config.insert("maxOutputTokens".to_string(), json!(max_tokens));
+        }
+
+        let prompt = request.prompt_with_context();
+        let contents = request
+            .chat_history
+            .into_iter()
+            .chain([prompt])
+            .map(Content::try_from)
+            .collect::<StdResult<Vec<_>, _>>()
+            .map_err(|e| LLMError::Api(e.to_string()))?;
+
+        let mut body = json!({
+            "contents": contents,
+            "generationConfig": generation_config,
+        });
+        let fields = body.as_object_mut().expect("body is an object");
+
+        if !settings.safety_settings.is_empty() {
+            fields.insert(
+                "safetySettings".to_string(),
+                json!(settings.safety_settings),
+            );
+        }
+        if !request.tools.is_empty() {
+            let tools = request
+                .tools
+                .into_iter()
+                .map(Tool::try_from)
+                .collect::<StdResult<Vec<_>, _>>()
+                .map_err(LLMError::Response)?;
+            fields.insert("tools".to_string(), json!(tools));
+        }
+        if let Some(preamble) = request.preamble {
+            let instruction = Content {
+                parts: OneOrMany::one(Part::Text(preamble)),
+                role: Some(Role::Model),
+            };
+            fields.insert("systemInstruction".to_string(), json!(instruction));
+        }
+
+        Ok(body)
+    }
+
+    /// Pages through the models available to the configured API key.
+    async fn fetch_models(&self) -> Result<Vec<ModelInfo>> {
+        let api_key = self.settings().api_key.as_str();
+
+        let http = reqwest::Client::new();
+        let mut models = Vec::new();
+        let mut page_token = None;
+        loop {
+            let mut request = http.get(GEMINI_MODELS_URL).query(&[("key", api_key)]);
+            if let Some(token) = &page_token {
+                request = request.query(&[("pageToken", token)]);
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(|e| LLMError::Connection(f!("Cannot reach the Gemini API: {}", e)))?;
+            let page = check_status(response)
+                .await?
+                .json::<ModelsPage>()
+                .await
+                .map_err(|e| LLMError::Parsing(e.to_string()))?;
+
+            models.extend(page.models.into_iter().map(ModelInfo::from));
+            match page.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => return Ok(models),
+            }
+        }
+    }
+}
+
+/// Turns an unsuccessful response into an error; the key is the only input to the models
+/// listing, so a rejected request means the credentials are wrong.
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(Error::LLM(match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            LLMError::Configuration(f!("Gemini rejected the API key: {}", body))
+        }
+        _ => LLMError::Api(f!("{}: {}", status, body)),
+    }))
+}
+
+/// Response of `GET /v1beta/models`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelsPage {
+    #[serde(default)]
+    models: Vec<GeminiModel>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiModel {
+    name: String,
+    input_token_limit: Option<u64>,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+impl From<GeminiModel> for ModelInfo {
+    fn from(model: GeminiModel) -> Self {
+        let supports = |method: &str| {
+            model
+                .supported_generation_methods
+                .iter()
+                .any(|m| m == method)
+        };
+        ModelInfo {
+            context_length: model.input_token_limit,
+            supports_embeddings: supports("embedContent"),
+            // Every model that generates content accepts function declarations
+            supports_tools: supports("generateContent"),
+            size: None,
+            // The API names models `models/<name>`, the client expects just the name
+            ..ModelInfo::new(model.name.trim_start_matches("models/"))
+        }
+    }
+}
+
+/// Extracts the text and function calls of one server-sent event.
+fn parse_event(line: &str) -> Vec<StreamingChoice> {
+    let Some(data) = line.strip_prefix("data: ") else {
+        return Vec::new();
+    };
+    let Ok(response) = serde_json::from_str::<StreamGenerateContentResponse>(data) else {
+        return Vec::new();
+    };
+
+    response
+        .candidates
+        .into_iter()
+        .take(1)
+        .flat_map(|candidate| candidate.content.parts)
+        .filter_map(|part| match part {
+            Part::Text(text) => Some(StreamingChoice::Message(text)),
+            // Gemini does not id function calls; the name identifies the result
+            Part::FunctionCall(call) => Some(StreamingChoice::ToolCall(
+                call.name.clone(),
+                call.name,
+                call.args,
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for GeminiProvider {
+    fn model(&self) -> &str {
+        &self.settings().model
     }
 
     fn embedding_model(&self) -> Option<&str> {
@@ -64,61 +263,124 @@ impl LLMProvider for GeminiProvider {
         )
     }
 
-    async fn generate(&self, request: CompletionRequest) -> Result<String> {
-        self.client
-            .completion_model(&self.model())
-            .completion(request)
+    async fn complete(&self, request: CompletionRequest) -> Result<Completion> {
+        let body = self.request_body(request)?;
+        let response = self
+            .client
+            .post(&f!("/v1beta/models/{}:generateContent", self.model()))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| LLMError::Connection(e.to_string()))?;
+        let response = check_status(response)
+            .await?
+            .json::<GenerateContentResponse>()
             .await
-            .map(|response| match response.choice.first() {
-                AssistantContent::Text(choice) => choice.text,
-                AssistantContent::ToolCall(tool_call) => tool_call.function.name,
+            .map_err(|e| LLMError::Parsing(e.to_string()))?;
+
+        let usage = response.usage_metadata.as_ref().map(|usage| TokenUsage {
+            input_tokens: usage.prompt_token_count.max(0) as u64,
+            output_tokens: usage.candidates_token_count.max(0) as u64,
+        });
+        completion::CompletionResponse::try_from(response)
+            .map(|response| Completion {
+                choice: response.choice,
+                usage,
             })
-            .map_err(|e| Error::LLM(LLMError::Api(e.to_string())))
-        }
+            .map_err(|e| Error::LLM(LLMError::Response(e)))
+    }
 
-    async fn generate_stream(
+    /// Constrains the answer with a response schema in the generation config.
+    async fn complete_json(
         &self,
-        request: CompletionRequest,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
-        let stream = self
+        mut request: CompletionRequest,
+        schema: &Value,
+    ) -> Result<Completion> {
+        let mut params = match request.additional_params.take() {
+            Some(Value::Object(params)) => params,
+            _ => Map::new(),
+        };
+        params.insert("responseMimeType".to_string(), json!("application/json"));
+        params.insert("responseSchema".to_string(), gemini_schema(schema));
+        request.additional_params = Some(Value::Object(params));
+        self.complete(request).await
+    }
+
+    async fn generate_stream(&self, request: CompletionRequest) -> Result<LLMStream> {
+        let body = self.request_body(request)?;
+        let response = self
             .client
-            .completion_model(&self.model())
-            .stream(request)
+            .post_sse(&f!("/v1beta/models/{}:streamGenerateContent", self.model()))
+            .json(&body)
+            .send()
             .await
-            .map(|stream| {
-                stream.map(|c| match c {
-                    Ok(StreamingChoice::Message(choice)) => Ok(choice),
-                    Ok(StreamingChoice::ToolCall(name, _id, _params)) => Ok(name),
-                    Err(e) => Err(Error::LLM(LLMError::Response(e))),
-                })
-            })
-            .map_err(|e| Error::LLM(LLMError::Api(e.to_string())))?;
-        Ok(Box::pin(stream) as Pin<Box<dyn Stream<Item = Result<String>> + Send>>)
-    }
+            .map_err(|e| LLMError::Connection(e.to_string()))?;
+        let mut bytes = check_status(response).await?.bytes_stream();
 
-    async fn generate_embedding(&self, to_embed: EmbeddingType) -> Result<()> {
-        let model = self.client.embedding_model(&self.model());
-        let embedder = EmbeddingsBuilder::new(model.clone());
-        match to_embed {
-            EmbeddingType::Text(text) => {
-                if let Err(e) = embedder.document(text.to_string()) {
-                    error!("Embedding error: {}", e);
+        let stream = async_stream::stream! {
+            while let Some(chunk) = bytes.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        yield Err(Error::LLM(LLMError::Response(e.into())));
+                        return;
+                    }
+                };
+                for choice in String::from_utf8_lossy(&chunk).lines().flat_map(parse_event) {
+                    yield Ok(choice);
                 }
             }
-            EmbeddingType::Document(document) => {
-                if let Err(e) = embedder.document(document.to_string()) {
-                    error!("Embedding error: {}", e);
-                }
-            }
-        }
-        Ok(())
+        };
+        Ok(Box::pin(stream) as LLMStream)
     }
 
-    async fn get_models(&self) -> Result<Vec<String>> {
-        reqwest::get("http://127.0.0.1:11434/v1/models")
-            .await?
-            .json::<Vec<String>>()
-            .await
-            .map_err(|e| Error::LLM(LLMError::Other(e.to_string())))
+    async fn generate_embeddings(&self, inputs: Vec<EmbeddingType>) -> Result<Vec<Vec<f32>>> {
+        let model_name = self.embedding_model().ok_or_else(|| {
+            LLMError::Configuration(String::from("No embedding model configured"))
+        })?;
+        let model = self.client.embedding_model(model_name);
+        embed_with(&model, inputs, self.config.vector_db.vector_size).await
+    }
+
+    async fn get_models(&self) -> Result<Vec<ModelInfo>> {
+        self.models.get_or_fetch(self.fetch_models()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rig::message::Message;
+
+    use super::*;
+    use crate::llm::gemini::config::GenerationConfig;
+
+    #[test]
+    fn test_request_body_applies_config() {
+        let mut config = AppConfig::default();
+        config.provider_configs.gemini = Some(GeminiProviderConfig {
+            api_key: "key".to_string(),
+            model: "gemini-2.0-flash".to_string(),
+            safety_settings: serde_json::from_value(json!([
+                { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_NONE" }
+            ]))
+            .unwrap(),
+            generation_config: GenerationConfig {
+                temperature: Some(0.2),
+                max_output_tokens: Some(512),
+            },
+            embedding_model: "text-embedding-004".to_string(),
+            embedding: false,
+        });
+        let provider = GeminiProvider::new(Arc::new(config)).unwrap();
+
+        let mut request = provider.create_prompt(Message::user("Hello"));
+        request.max_tokens = Some(64);
+        let body = provider.request_body(request).unwrap();
+
+        assert_eq!(provider.model(), "gemini-2.0-flash");
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 64);
+        assert!(body["generationConfig"]["temperature"].as_f64().unwrap() < 0.3);
+        assert_eq!(body["safetySettings"][0]["threshold"], "BLOCK_NONE");
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "Hello");
     }
 }
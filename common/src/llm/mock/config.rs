@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::MockReply;
+use crate::llm::LLMProviders;
+
+/// How the mock provider produces its answers.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MockMode {
+    /// Serve the configured `responses` in order
+    #[default]
+    Script,
+    /// Forward requests to `record_provider` and write its answers to `fixture`
+    Record,
+    /// Serve the answers stored in `fixture`
+    Replay,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MockProviderConfig {
+    #[serde(default)]
+    pub mode: MockMode,
+
+    /// File written in record mode and read in replay mode
+    #[serde(default)]
+    pub fixture: Option<PathBuf>,
+
+    /// Provider whose traffic is captured in record mode
+    #[serde(default)]
+    pub record_provider: LLMProviders,
+
+    /// Answers served in order in script mode
+    #[serde(default)]
+    pub responses: Vec<MockReply>,
+}
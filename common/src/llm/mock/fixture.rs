@@ -0,0 +1,200 @@
+//! Scripted replies and the fixture files they are recorded to.
+
+use std::{fs, path::Path};
+
+use rig::{
+    OneOrMany, completion::CompletionRequest, message::AssistantContent, streaming::StreamingChoice,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    llm::{Completion, LLMError, TokenUsage},
+    prelude::*,
+};
+
+/// A model answer, either scripted by a test or captured from a real provider.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MockReply {
+    /// Answer text, split into the chunks it is streamed as
+    #[serde(default)]
+    pub chunks: Vec<String>,
+    /// Tools the model asks to run
+    #[serde(default)]
+    pub tool_calls: Vec<MockToolCall>,
+    /// Tokens the provider reports the completion used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MockToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+impl MockReply {
+    /// A plain text answer sent as a single chunk.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::chunks([text])
+    }
+
+    /// A text answer streamed as the given chunks.
+    pub fn chunks<S: Into<String>>(chunks: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            chunks: chunks.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// An answer asking for a single tool call.
+    pub fn tool_call(name: impl Into<String>, arguments: Value) -> Self {
+        Self::default().with_tool_call(name, arguments)
+    }
+
+    /// Adds a tool call to the answer.
+    pub fn with_tool_call(mut self, name: impl Into<String>, arguments: Value) -> Self {
+        let name = name.into();
+        self.tool_calls.push(MockToolCall {
+            id: f!("call-{}-{}", name, self.tool_calls.len()),
+            name,
+            arguments,
+        });
+        self
+    }
+
+    /// Reports that answering used the given tokens, as real providers do for completions.
+    pub fn with_usage(mut self, input_tokens: u64, output_tokens: u64) -> Self {
+        self.usage = Some(TokenUsage {
+            input_tokens,
+            output_tokens,
+        });
+        self
+    }
+
+    pub(crate) fn into_completion(self) -> Result<Completion> {
+        let text = self.chunks.concat();
+        let content = (!text.is_empty())
+            .then(|| AssistantContent::text(text))
+            .into_iter()
+            .chain(
+                self.tool_calls
+                    .into_iter()
+                    .map(|call| AssistantContent::tool_call(call.id, call.name, call.arguments)),
+            )
+            .collect::<Vec<_>>();
+
+        let choice = OneOrMany::many(content)
+            .map_err(|_| Error::LLM(LLMError::Parsing("Mock reply is empty".to_string())))?;
+        Ok(Completion {
+            choice,
+            usage: self.usage,
+        })
+    }
+
+    pub(crate) fn into_stream(self) -> Vec<StreamingChoice> {
+        self.chunks
+            .into_iter()
+            .map(StreamingChoice::Message)
+            .chain(
+                self.tool_calls
+                    .into_iter()
+                    .map(|call| StreamingChoice::ToolCall(call.name, call.id, call.arguments)),
+            )
+            .collect()
+    }
+
+    pub(crate) fn from_completion(completion: &Completion) -> Self {
+        let mut reply = Self {
+            usage: completion.usage,
+            ..Default::default()
+        };
+        for item in completion.choice.iter() {
+            match item {
+                AssistantContent::Text(text) => reply.chunks.push(text.text.clone()),
+                AssistantContent::ToolCall(call) => reply.tool_calls.push(MockToolCall {
+                    id: call.id.clone(),
+                    name: call.function.name.clone(),
+                    arguments: call.function.arguments.clone(),
+                }),
+            }
+        }
+        reply
+    }
+
+    pub(crate) fn push_choice(&mut self, choice: &StreamingChoice) {
+        match choice {
+            StreamingChoice::Message(text) => self.chunks.push(text.clone()),
+            StreamingChoice::ToolCall(name, id, arguments) => self.tool_calls.push(MockToolCall {
+                id: id.clone(),
+                name: name.clone(),
+                arguments: arguments.clone(),
+            }),
+        }
+    }
+}
+
+/// One captured request and the provider's answer to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Exchange {
+    Completion { request: String, reply: MockReply },
+    Embedding { input: String, vector: Vec<f32> },
+}
+
+/// Provider traffic captured in record mode.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Fixture {
+    pub exchanges: Vec<Exchange>,
+}
+
+impl Fixture {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            LLMError::Configuration(f!("Cannot read fixture {}: {}", path.display(), e))
+        })?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Removes and returns the first recorded answer to the request.
+    ///
+    /// Answers are consumed so a request sent twice can be answered differently each time.
+    pub(crate) fn take_reply(&mut self, request: &str) -> Option<MockReply> {
+        let position = self.exchanges.iter().position(
+            |exchange| matches!(exchange, Exchange::Completion { request: r, .. } if r == request),
+        )?;
+        match self.exchanges.remove(position) {
+            Exchange::Completion { reply, .. } => Some(reply),
+            Exchange::Embedding { .. } => None,
+        }
+    }
+
+    pub(crate) fn vector(&self, input: &str) -> Option<Vec<f32>> {
+        self.exchanges.iter().find_map(|exchange| match exchange {
+            Exchange::Embedding { input: i, vector } if i == input => Some(vector.clone()),
+            _ => None,
+        })
+    }
+}
+
+/// Identifies a request by the conversation it carries.
+///
+/// Document metadata such as timestamps is left out so replays match recordings made earlier.
+pub(crate) fn request_key(request: &CompletionRequest) -> String {
+    json!({
+        "preamble": request.preamble,
+        "chat_history": request.chat_history,
+        "prompt": request.prompt,
+        "documents": request.documents.iter().map(|d| &d.text).collect::<Vec<_>>(),
+    })
+    .to_string()
+}
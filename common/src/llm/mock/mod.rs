@@ -0,0 +1,398 @@
+//! A deterministic provider for running the pipeline without a live model.
+//!
+//! In script mode it serves prepared replies, in record mode it captures a real provider's
+//! traffic to a fixture file, and in replay mode it serves that fixture back.
+
+mod config;
+mod fixture;
+
+use std::{
+    collections::{VecDeque, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_std::sync::Mutex;
+use futures::StreamExt;
+use rig::completion::CompletionRequest;
+
+use super::EmbeddingType;
+use crate::{
+    config::AppConfig,
+    core::message_text,
+    llm::{
+        Completion, LLMError, LLMProvider, LLMProviders, LLMStream, ModelInfo, ProviderRole,
+        VectorDbConfig, create_llm_provider,
+    },
+    prelude::*,
+};
+pub use config::{MockMode, MockProviderConfig};
+use fixture::request_key;
+pub use fixture::{Exchange, Fixture, MockReply, MockToolCall};
+
+const MOCK_MODEL: &str = "mock";
+
+pub struct MockProvider {
+    source: Source,
+    vector_size: u64,
+    /// Text of every prompt received, for tests to inspect
+    prompts: Mutex<Vec<String>>,
+}
+
+enum Source {
+    Script(Mutex<VecDeque<MockReply>>),
+    Record {
+        inner: Arc<dyn LLMProvider>,
+        recorder: Arc<Recorder>,
+    },
+    Replay {
+        path: PathBuf,
+        fixture: Mutex<Fixture>,
+    },
+}
+
+/// Appends captured exchanges to a fixture, saving it after each one so a crash keeps them.
+struct Recorder {
+    path: PathBuf,
+    fixture: Mutex<Fixture>,
+}
+
+impl Recorder {
+    async fn record(&self, exchanges: impl IntoIterator<Item = Exchange>) -> Result<()> {
+        let mut fixture = self.fixture.lock().await;
+        fixture.exchanges.extend(exchanges);
+        fixture.save(&self.path)
+    }
+}
+
+impl MockProvider {
+    /// Creates the provider described by the `mock` provider config.
+    pub fn new(config: Arc<AppConfig>) -> Result<Self> {
+        let settings = &config.provider_configs.mock;
+        let provider = match settings.mode {
+            MockMode::Script => Self::scripted(settings.responses.clone()),
+            MockMode::Record => {
+                if settings.record_provider == LLMProviders::Mock {
+                    return Err(Error::LLM(LLMError::Configuration(String::from(
+                        "The mock provider cannot record itself",
+                    ))));
+                }
+                let mut inner_config = (*config).clone();
+                inner_config.provider = settings.record_provider.clone();
+                inner_config.embedding_provider = settings.record_provider.clone();
+                let inner = create_llm_provider(Arc::new(inner_config), ProviderRole::Chat)?;
+                Self::recording(inner, fixture_path(settings)?)
+            }
+            MockMode::Replay => Self::replaying(fixture_path(settings)?)?,
+        };
+
+        Ok(provider.with_vector_size(config.vector_db.vector_size))
+    }
+
+    /// Serves the replies in order, failing once they run out.
+    pub fn scripted(replies: impl IntoIterator<Item = MockReply>) -> Self {
+        Self::with_source(Source::Script(Mutex::new(replies.into_iter().collect())))
+    }
+
+    /// Forwards requests to `inner`, writing every answer to a new fixture at `path`.
+    pub fn recording(inner: Arc<dyn LLMProvider>, path: impl Into<PathBuf>) -> Self {
+        Self::with_source(Source::Record {
+            inner,
+            recorder: Arc::new(Recorder {
+                path: path.into(),
+                fixture: Mutex::new(Fixture::default()),
+            }),
+        })
+    }
+
+    /// Serves the answers recorded in the fixture at `path`.
+    pub fn replaying(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let fixture = Fixture::load(&path)?;
+        Ok(Self::with_source(Source::Replay {
+            path,
+            fixture: Mutex::new(fixture),
+        }))
+    }
+
+    /// Sets the dimension of generated embeddings.
+    pub fn with_vector_size(mut self, vector_size: u64) -> Self {
+        self.vector_size = vector_size;
+        self
+    }
+
+    /// Text of the prompts received so far, oldest first.
+    pub async fn prompts(&self) -> Vec<String> {
+        self.prompts.lock().await.clone()
+    }
+
+    fn with_source(source: Source) -> Self {
+        Self {
+            source,
+            vector_size: VectorDbConfig::default().vector_size,
+            prompts: Mutex::new(Vec::new()),
+        }
+    }
+
+    async fn next_reply(&self, request: &CompletionRequest) -> Result<MockReply> {
+        match &self.source {
+            Source::Script(replies) => replies.lock().await.pop_front().ok_or_else(|| {
+                Error::LLM(LLMError::Other(String::from(
+                    "Mock provider has no scripted replies left",
+                )))
+            }),
+            Source::Replay { path, fixture } => fixture
+                .lock()
+                .await
+                .take_reply(&request_key(request))
+                .ok_or_else(|| {
+                    Error::LLM(LLMError::Other(f!(
+                        "Fixture {} has no recorded reply for this request",
+                        path.display()
+                    )))
+                }),
+            Source::Record { .. } => unreachable!("recorded replies come from the inner provider"),
+        }
+    }
+
+    async fn log_prompt(&self, request: &CompletionRequest) {
+        let text = message_text(&request.prompt).unwrap_or_default();
+        self.prompts.lock().await.push(text);
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for MockProvider {
+    fn model(&self) -> &str {
+        match &self.source {
+            Source::Record { inner, .. } => inner.model(),
+            _ => MOCK_MODEL,
+        }
+    }
+
+    fn embedding_model(&self) -> Option<&str> {
+        match &self.source {
+            Source::Record { inner, .. } => inner.embedding_model(),
+            _ => Some(MOCK_MODEL),
+        }
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<Completion> {
+        self.log_prompt(&request).await;
+        let Source::Record { inner, recorder } = &self.source else {
+            return self.next_reply(&request).await?.into_completion();
+        };
+
+        let key = request_key(&request);
+        let completion = inner.complete(request).await?;
+        recorder
+            .record([Exchange::Completion {
+                request: key,
+                reply: MockReply::from_completion(&completion),
+            }])
+            .await?;
+        Ok(completion)
+    }
+
+    async fn generate_stream(&self, request: CompletionRequest) -> Result<LLMStream> {
+        self.log_prompt(&request).await;
+        let Source::Record { inner, recorder } = &self.source else {
+            let choices = self.next_reply(&request).await?.into_stream();
+            return Ok(Box::pin(futures::stream::iter(choices.into_iter().map(Ok))) as LLMStream);
+        };
+
+        let key = request_key(&request);
+        let mut inner_stream = inner.generate_stream(request).await?;
+        let recorder = Arc::clone(recorder);
+        let stream = async_stream::stream! {
+            let mut reply = MockReply::default();
+            let mut failed = false;
+            while let Some(choice) = inner_stream.next().await {
+                match &choice {
+                    Ok(choice) => reply.push_choice(choice),
+                    Err(_) => failed = true,
+                }
+                yield choice;
+            }
+            // Interrupted answers would make replays diverge from real runs
+            if !failed
+                && let Err(e) = recorder.record([Exchange::Completion { request: key, reply }]).await
+            {
+                warn!("Failed to record streamed reply: {}", e);
+            }
+        };
+        Ok(Box::pin(stream) as LLMStream)
+    }
+
+    async fn get_models(&self) -> Result<Vec<ModelInfo>> {
+        match &self.source {
+            Source::Record { inner, .. } => inner.get_models().await,
+            _ => Ok(vec![ModelInfo {
+                supports_embeddings: true,
+                supports_tools: true,
+                ..ModelInfo::new(MOCK_MODEL)
+            }]),
+        }
+    }
+
+    async fn generate_embeddings(&self, inputs: Vec<EmbeddingType>) -> Result<Vec<Vec<f32>>> {
+        let texts = inputs
+            .iter()
+            .cloned()
+            .map(EmbeddingType::into_text)
+            .collect::<Vec<_>>();
+
+        match &self.source {
+            Source::Script(_) => Ok(texts
+                .iter()
+                .map(|text| hashed_embedding(text, self.vector_size))
+                .collect()),
+            Source::Record { inner, recorder } => {
+                let vectors = inner.generate_embeddings(inputs).await?;
+                recorder
+                    .record(
+                        texts
+                            .into_iter()
+                            .zip(vectors.iter().cloned())
+                            .map(|(input, vector)| Exchange::Embedding { input, vector }),
+                    )
+                    .await?;
+                Ok(vectors)
+            }
+            Source::Replay { path, fixture } => {
+                let fixture = fixture.lock().await;
+                texts
+                    .iter()
+                    .map(|text| {
+                        fixture.vector(text).ok_or_else(|| {
+                            Error::LLM(LLMError::Embedding(
+                                f!("\"{}\"", text),
+                                f!("Fixture {} has no recorded vector", path.display()),
+                            ))
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn fixture_path(settings: &MockProviderConfig) -> Result<&Path> {
+    settings.fixture.as_deref().ok_or_else(|| {
+        Error::LLM(LLMError::Configuration(f!(
+            "The mock provider needs a fixture file in {:?} mode",
+            settings.mode
+        )))
+    })
+}
+
+/// Embeds text as a normalized bag of hashed words, so texts sharing words score as similar.
+fn hashed_embedding(text: &str, vector_size: u64) -> Vec<f32> {
+    let mut vector = vec![0.0; vector_size as usize];
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        let mut hasher = DefaultHasher::new();
+        word.to_lowercase().hash(&mut hasher);
+        vector[(hasher.finish() % vector_size) as usize] += 1.0;
+    }
+
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+#[cfg(test)]
+mod tests {
+    use rig::streaming::StreamingChoice;
+    use serde_json::json;
+
+    use super::*;
+    use crate::utils::{cosine_similarity, temp_dir};
+
+    fn request(provider: &MockProvider, prompt: &str) -> CompletionRequest {
+        provider.create_prompt(prompt.into())
+    }
+
+    #[async_std::test]
+    async fn test_scripted_stream() {
+        let provider = MockProvider::scripted([
+            MockReply::chunks(["Hel", "lo"]).with_tool_call("calculator", json!({"a": 1}))
+        ]);
+
+        let choices = provider
+            .generate_stream(request(&provider, "Hi"))
+            .await
+            .unwrap()
+            .map(|choice| choice.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(choices.len(), 3);
+        assert!(
+            matches!(&choices[2], StreamingChoice::ToolCall(name, _, _) if name == "calculator")
+        );
+
+        assert!(
+            provider
+                .generate(request(&provider, "Again"))
+                .await
+                .is_err()
+        );
+        assert_eq!(provider.prompts().await, vec!["Hi", "Again"]);
+    }
+
+    #[async_std::test]
+    async fn test_record_then_replay() {
+        let dir = temp_dir();
+        let path = dir.path().join("fixture.json");
+        let inner = Arc::new(MockProvider::scripted([
+            MockReply::text("Four"),
+            MockReply::chunks(["Fi", "ve"]),
+        ]));
+
+        let recorder = MockProvider::recording(inner, &path);
+        assert_eq!(
+            recorder.generate(request(&recorder, "2+2?")).await.unwrap(),
+            "Four"
+        );
+        let streamed = recorder
+            .generate_stream(request(&recorder, "2+3?"))
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(streamed.len(), 2);
+        let recorded = recorder.generate_embedding("tea".into()).await.unwrap();
+
+        let replayer = MockProvider::replaying(&path).unwrap();
+        assert_eq!(
+            replayer.generate(request(&replayer, "2+3?")).await.unwrap(),
+            "Five"
+        );
+        assert_eq!(
+            replayer.generate(request(&replayer, "2+2?")).await.unwrap(),
+            "Four"
+        );
+        assert!(replayer.generate(request(&replayer, "2+2?")).await.is_err());
+        assert_eq!(
+            replayer.generate_embedding("tea".into()).await.unwrap(),
+            recorded
+        );
+    }
+
+    #[test]
+    fn test_hashed_embedding_similarity() {
+        let tea = hashed_embedding("User likes green tea", 64);
+        let similar = hashed_embedding("Does the user like tea?", 64);
+        let unrelated = hashed_embedding("Cats sleep a lot", 64);
+        assert_eq!(tea.len(), 64);
+        assert!(
+            cosine_similarity(&tea, &similar).unwrap()
+                > cosine_similarity(&tea, &unrelated).unwrap()
+        );
+    }
+}
@@ -1,24 +1,68 @@
 mod config;
+mod embedding;
 mod error;
 pub mod ollama;
 pub mod gemini;
+pub mod mock;
+mod models;
+pub mod openai_compatible;
+pub mod routing;
+mod structured;
+mod tokens;
+pub mod usage;
 
 use std::{pin::Pin, sync::Arc};
 
 use crate::{config::AppConfig, prelude::*};
-pub use config::{LLMConfig, LLMProviders, VectorDbConfig};
+pub use config::{LLMConfig, LLMProviders, MemoryBackend, VectorDbConfig};
+pub use embedding::EmbeddingType;
+pub(crate) use embedding::{check_dimensions, embed_with};
 pub use error::LLMError;
 use futures::Stream;
 pub use ollama::{OllamaProvider, OllamaProviderConfig};
 pub use gemini::{GeminiProvider, GeminiProviderConfig};
+pub use mock::{MockProvider, MockProviderConfig, MockReply, MockToolCall};
+pub use models::{ModelCache, ModelInfo};
+pub(crate) use models::looks_like_embedding_model;
+pub use openai_compatible::{OpenAICompatibleProvider, OpenAICompatibleProviderConfig};
+pub use routing::{ProviderRouter, RoutingConfig, RoutingRule};
+pub use structured::StructuredOutput;
+pub(crate) use structured::gemini_schema;
+pub use tokens::TokenEstimator;
+pub use usage::{
+    CallKind, MeteredProvider, ModelPrice, TokenUsage, UsageConfig, UsageGrouping, UsageLedger,
+    UsageRecord, UsageSummary,
+};
 use rig::{
-    completion::{CompletionRequest, Document},
-    message::Message,
+    OneOrMany,
+    completion::CompletionRequest,
+    message::{AssistantContent, Message},
+    streaming::StreamingChoice,
 };
+use serde_json::Value;
+
+/// Stream of raw response chunks (text or tool calls) produced by a provider.
+pub type LLMStream = Pin<Box<dyn Stream<Item = Result<StreamingChoice>> + Send>>;
 
-pub enum EmbeddingType {
-    Text(String),
-    Document(Document),
+/// Assistant content answering a prompt, including any tool calls.
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub choice: OneOrMany<AssistantContent>,
+    /// Tokens the provider reports the call used, when it reports them
+    pub usage: Option<TokenUsage>,
+}
+
+impl Completion {
+    /// The text of the answer, leaving out tool calls.
+    pub fn text(&self) -> String {
+        self.choice
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                AssistantContent::ToolCall(_) => None,
+            })
+            .collect()
+    }
 }
 
 #[async_trait::async_trait]
@@ -27,19 +71,81 @@ pub trait LLMProvider: Send + Sync {
 
     fn embedding_model(&self) -> Option<&str>;
 
-    /// Sends a prompt to the LLM and returns the generated response.
-    async fn generate(&self, prompt: CompletionRequest) -> Result<String>;
+    /// Sends a prompt to the LLM and returns the assistant content, including any tool calls.
+    async fn complete(&self, prompt: CompletionRequest) -> Result<Completion>;
 
-    /// Sends a prompt to the LLM and returns a stream of response chunks.
-    async fn generate_stream(
+    /// Sends a prompt to the LLM and returns the generated text response.
+    async fn generate(&self, prompt: CompletionRequest) -> Result<String> {
+        let text = self.complete(prompt).await?.text();
+        if text.is_empty() {
+            return Err(Error::LLM(LLMError::Parsing(
+                "Response did not contain any text".to_string(),
+            )));
+        }
+        Ok(text)
+    }
+
+    /// Sends a prompt asking for JSON matching `schema` and returns the answer, constrained by
+    /// the provider's JSON mode when it has one.
+    ///
+    /// Prefer [`StructuredOutput::generate_structured`], which also parses and validates the
+    /// answer.
+    async fn complete_json(
         &self,
         prompt: CompletionRequest,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>>;
+        _schema: &Value,
+    ) -> Result<Completion> {
+        self.complete(prompt).await
+    }
 
-    // TODO: May remove this later in favor of hard coding models
-    async fn get_models(&self) -> Result<Vec<String>>;
+    /// Sends a prompt to the LLM and returns a stream of response chunks.
+    async fn generate_stream(&self, prompt: CompletionRequest) -> Result<LLMStream>;
 
-    async fn generate_embedding(&self, to_embed: EmbeddingType) -> Result<()>;
+    /// Lists the models the provider offers, as reported by its API.
+    async fn get_models(&self) -> Result<Vec<ModelInfo>>;
+
+    /// Checks that the provider is reachable, accepts the configured credentials and offers
+    /// the model configured for `role`, so misconfiguration surfaces at startup rather than on
+    /// the first prompt.
+    async fn probe(&self, role: ProviderRole) -> Result<()> {
+        let models = self.get_models().await?;
+        let model = match role {
+            ProviderRole::Chat => self.model(),
+            ProviderRole::Embedding => self.embedding_model().ok_or_else(|| {
+                LLMError::Configuration(String::from("No embedding model configured"))
+            })?,
+        };
+        if !models.iter().any(|m| is_model(&m.name, model)) {
+            return Err(Error::LLM(LLMError::Configuration(f!(
+                "Model '{}' is not offered by the provider",
+                model
+            ))));
+        }
+        Ok(())
+    }
+
+    /// Estimates the tokens the chat model's tokenizer produces.
+    fn token_estimator(&self) -> TokenEstimator {
+        TokenEstimator::for_model(self.model())
+    }
+
+    /// Number of tokens the chat model accepts, when the provider reports it. Fails when the
+    /// provider's models cannot be listed.
+    async fn context_window(&self) -> Result<Option<u64>> {
+        let models = self.get_models().await?;
+        Ok(models
+            .into_iter()
+            .find(|m| is_model(&m.name, self.model()))
+            .and_then(|m| m.context_length))
+    }
+
+    /// Embeds each input with the configured embedding model, returning one vector per input.
+    async fn generate_embeddings(&self, inputs: Vec<EmbeddingType>) -> Result<Vec<Vec<f32>>>;
+
+    /// Embeds a single text or document, returning its vector.
+    async fn generate_embedding(&self, to_embed: EmbeddingType) -> Result<Vec<Vec<f32>>> {
+        self.generate_embeddings(vec![to_embed]).await
+    }
 
     fn create_prompt(&self, prompt: Message) -> CompletionRequest {
         CompletionRequest {
@@ -55,9 +161,40 @@ pub trait LLMProvider: Send + Sync {
     }
 }
 
-pub fn create_llm_provider(config: Arc<AppConfig>) -> Result<Arc<dyn LLMProvider>> {
+/// Whether a listed model name refers to `model`; Ollama lists untagged models as `:latest`.
+fn is_model(name: &str, model: &str) -> bool {
+    name == model || name == f!("{}:latest", model)
+}
+
+/// What a provider is used for; chat and embeddings can be served by different providers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderRole {
+    /// Answers prompts and calls tools, configured by `provider` and `provider_configs`
+    Chat,
+    /// Embeds memories and queries, configured by `embedding_provider` and
+    /// `embedding_provider_configs`
+    Embedding,
+}
+
+pub fn create_llm_provider(
+    config: Arc<AppConfig>,
+    role: ProviderRole,
+) -> Result<Arc<dyn LLMProvider>> {
+    let config = match role {
+        ProviderRole::Chat => config,
+        // Providers connect using `provider_configs`, so the embedding section takes its place
+        ProviderRole::Embedding => {
+            let mut embedding_config = (*config).clone();
+            embedding_config.provider = config.embedding_provider.clone();
+            embedding_config.provider_configs = config.embedding_provider_configs.clone();
+            Arc::new(embedding_config)
+        }
+    };
+
     match config.provider {
         LLMProviders::Ollama => Ok(Arc::new(OllamaProvider::new(config)?)),
         LLMProviders::Gemini => Ok(Arc::new(GeminiProvider::new(config)?)),
+        LLMProviders::OpenAICompatible => Ok(Arc::new(OpenAICompatibleProvider::new(config)?)),
+        LLMProviders::Mock => Ok(Arc::new(MockProvider::new(config)?)),
     }
 }
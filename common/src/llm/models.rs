@@ -0,0 +1,105 @@
+//! Descriptions of the models a provider offers.
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use async_std::sync::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// How long a fetched model list is reused before the provider is asked again.
+const MODEL_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A model offered by a provider.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// Name to put in the provider config
+    pub name: String,
+    /// Maximum number of tokens the model accepts, when the provider reports it
+    pub context_length: Option<u64>,
+    /// Whether the model produces embeddings
+    pub supports_embeddings: bool,
+    /// Whether the model can call tools
+    pub supports_tools: bool,
+    /// Size of the model weights in bytes, for locally hosted models
+    pub size: Option<u64>,
+}
+
+impl ModelInfo {
+    /// A model about which nothing but the name is known.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            context_length: None,
+            supports_embeddings: false,
+            supports_tools: false,
+            size: None,
+        }
+    }
+}
+
+/// Guesses whether a model produces embeddings when the provider does not say.
+pub(crate) fn looks_like_embedding_model(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.contains("embed") || name.contains("bge-") || name.contains("minilm")
+}
+
+/// Keeps a provider's model list so its API is not queried on every call.
+#[derive(Debug, Default)]
+pub struct ModelCache {
+    entry: RwLock<Option<(Instant, Vec<ModelInfo>)>>,
+}
+
+impl ModelCache {
+    /// Returns the cached list, running `fetch` when it is missing or stale.
+    ///
+    /// Failed fetches are not cached.
+    pub async fn get_or_fetch<F>(&self, fetch: F) -> Result<Vec<ModelInfo>>
+    where
+        F: Future<Output = Result<Vec<ModelInfo>>>,
+    {
+        if let Some((fetched_at, models)) = &*self.entry.read().await
+            && fetched_at.elapsed() < MODEL_CACHE_TTL
+        {
+            return Ok(models.clone());
+        }
+
+        let models = fetch.await?;
+        *self.entry.write().await = Some((Instant::now(), models.clone()));
+        Ok(models)
+    }
+
+    /// Forgets the cached list so the next call queries the provider.
+    pub async fn invalidate(&self) {
+        *self.entry.write().await = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn test_cache_reuses_models_until_invalidated() {
+        let cache = ModelCache::default();
+        let first = cache
+            .get_or_fetch(async { Ok(vec![ModelInfo::new("llama3.2:1b")]) })
+            .await
+            .unwrap();
+        let cached = cache
+            .get_or_fetch(async { Ok(vec![ModelInfo::new("other")]) })
+            .await
+            .unwrap();
+        assert_eq!(first, cached);
+
+        cache.invalidate().await;
+        let refreshed = cache
+            .get_or_fetch(async { Ok(vec![ModelInfo::new("other")]) })
+            .await
+            .unwrap();
+        assert_eq!(refreshed[0].name, "other");
+    }
+}
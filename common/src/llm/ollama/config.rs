@@ -33,6 +33,13 @@ impl Default for OllamaProviderConfig {
     }
 }
 
+impl OllamaProviderConfig {
+    /// Address of the Ollama server, combining `url` and `port`.
+    pub fn base_url(&self) -> String {
+        format!("{}:{}", self.url.trim_end_matches('/'), self.port)
+    }
+}
+
 fn default_ollama_url() -> String {
     String::from("http://127.0.0.1")
 }
@@ -46,5 +53,6 @@ fn default_ollama_model() -> String {
 }
 
 fn default_ollama_embedding_model() -> String {
-    String::from("llama3.2:1b")
+    // Produces 768-dimensional vectors, matching the default vector store size
+    String::from("nomic-embed-text")
 }
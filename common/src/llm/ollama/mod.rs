@@ -1,36 +1,141 @@
 mod config;
 
-use std::{pin::Pin, sync::Arc};
+use std::{collections::HashMap, sync::Arc};
 
 use async_std::stream::StreamExt;
-use futures::Stream;
+use futures::future::join_all;
 
 use rig::{
-    completion::{CompletionModel, CompletionRequest},
-    embeddings::EmbeddingsBuilder,
-    message::AssistantContent,
-    providers::ollama::Client as OllamaClient,
-    streaming::{StreamingChoice, StreamingCompletionModel},
+    completion::{self, CompletionModel, CompletionRequest},
+    message::{Message, ToolResultContent, UserContent},
+    providers::ollama::{self, Client as OllamaClient},
+    streaming::StreamingCompletionModel,
 };
 
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
 use super::EmbeddingType;
 use crate::{
     config::AppConfig,
-    llm::{LLMError, LLMProvider},
+    llm::{
+        Completion, LLMError, LLMProvider, LLMStream, ModelCache, ModelInfo, TokenUsage,
+        embed_with, looks_like_embedding_model,
+    },
     prelude::*,
 };
 pub use config::OllamaProviderConfig;
 
 pub struct OllamaProvider {
     client: OllamaClient,
+    // Sends the requests rig's client cannot make, sharing its connections between them
+    http: reqwest::Client,
     config: Arc<AppConfig>,
+    models: ModelCache,
 }
 
 impl OllamaProvider {
     pub fn new(config: Arc<AppConfig>) -> Result<Self> {
-        let client = OllamaClient::new();
+        let client = OllamaClient::from_url(&config.provider_configs.ollama.base_url());
+
+        Ok(Self {
+            client,
+            http: reqwest::Client::new(),
+            config,
+            models: ModelCache::default(),
+        })
+    }
+
+    /// Lists the installed models, then asks Ollama for the details of each.
+    async fn fetch_models(&self) -> Result<Vec<ModelInfo>> {
+        let base_url = self.config.provider_configs.ollama.base_url();
+        let http = &self.http;
+        let tags = http
+            .get(f!("{}/api/tags", base_url))
+            .send()
+            .await
+            .map_err(|e| LLMError::Connection(f!("Cannot reach Ollama at {}: {}", base_url, e)))?
+            .error_for_status()
+            .map_err(|e| LLMError::Api(e.to_string()))?
+            .json::<TagsResponse>()
+            .await
+            .map_err(|e| LLMError::Parsing(e.to_string()))?;
 
-        Ok(Self { client, config })
+        let models = tags
+            .models
+            .into_iter()
+            .map(|model| describe_model(http, &base_url, model));
+        Ok(join_all(models).await)
+    }
+}
+
+/// Response of `GET /api/tags`.
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    models: Vec<TaggedModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaggedModel {
+    name: String,
+    size: Option<u64>,
+}
+
+/// Response of `POST /api/show`; `capabilities` is only reported by recent Ollama versions.
+#[derive(Debug, Default, Deserialize)]
+struct ShowResponse {
+    #[serde(default)]
+    capabilities: Vec<String>,
+    #[serde(default)]
+    model_info: HashMap<String, Value>,
+}
+
+async fn describe_model(http: &reqwest::Client, base_url: &str, model: TaggedModel) -> ModelInfo {
+    let details = match show_model(http, base_url, &model.name).await {
+        Ok(details) => details,
+        Err(e) => {
+            warn!(
+                "Failed to fetch details of Ollama model {}: {}",
+                model.name, e
+            );
+            ShowResponse::default()
+        }
+    };
+    model_info(model, details)
+}
+
+async fn show_model(http: &reqwest::Client, base_url: &str, name: &str) -> Result<ShowResponse> {
+    Ok(http
+        .post(f!("{}/api/show", base_url))
+        .json(&json!({ "model": name }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<ShowResponse>()
+        .await?)
+}
+
+fn model_info(model: TaggedModel, details: ShowResponse) -> ModelInfo {
+    // Keys are prefixed with the architecture, e.g. `llama.context_length`
+    let context_length = details
+        .model_info
+        .iter()
+        .find(|(key, _)| key.ends_with(".context_length"))
+        .and_then(|(_, value)| value.as_u64());
+
+    let has = |capability: &str| details.capabilities.iter().any(|c| c == capability);
+    let supports_embeddings = if details.capabilities.is_empty() {
+        looks_like_embedding_model(&model.name)
+    } else {
+        has("embedding")
+    };
+
+    ModelInfo {
+        context_length,
+        supports_embeddings,
+        supports_tools: has("tools"),
+        size: model.size,
+        ..ModelInfo::new(model.name)
     }
 }
 
@@ -50,65 +155,236 @@ impl LLMProvider for OllamaProvider {
         )
     }
 
-    async fn generate(&self, request: CompletionRequest) -> Result<String> {
+    async fn complete(&self, request: CompletionRequest) -> Result<Completion> {
         self.client
-            .completion_model(&self.model())
-            .completion(request)
+            .completion_model(self.model())
+            .completion(inline_tool_results(request))
             .await
             .map_err(|e| Error::LLM(LLMError::Api(e.to_string())))
-            .map(|response| match response.choice.first() {
-                AssistantContent::Text(choice) => choice.text,
-                // TODO: I'm just spitting out the tool name for now, need to figure out how to
-                // handle this better
-                AssistantContent::ToolCall(tool_call) => tool_call.function.name,
+            .map(|response| Completion {
+                usage: usage(&response.raw_response),
+                choice: response.choice,
             })
     }
 
-    async fn generate_stream(
+    /// Rig sends additional parameters as model options, so the request carrying the schema
+    /// as `format` is sent here.
+    async fn complete_json(
         &self,
         request: CompletionRequest,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        schema: &Value,
+    ) -> Result<Completion> {
+        let body = json_request_body(self.model(), inline_tool_results(request), schema)?;
+        let base_url = self.config.provider_configs.ollama.base_url();
+        let response = self
+            .http
+            .post(f!("{}/api/chat", base_url))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| LLMError::Connection(f!("Cannot reach Ollama at {}: {}", base_url, e)))?
+            .error_for_status()
+            .map_err(|e| LLMError::Api(e.to_string()))?
+            .json::<ollama::CompletionResponse>()
+            .await
+            .map_err(|e| LLMError::Parsing(e.to_string()))?;
+
+        let usage = usage(&response);
+        completion::CompletionResponse::try_from(response)
+            .map(|response| Completion {
+                choice: response.choice,
+                usage,
+            })
+            .map_err(|e| Error::LLM(LLMError::Response(e)))
+    }
+
+    async fn generate_stream(&self, request: CompletionRequest) -> Result<LLMStream> {
         let stream = self
             .client
-            .completion_model(&self.model())
-            .stream(request)
+            .completion_model(self.model())
+            .stream(inline_tool_results(request))
             .await
-            .map(|stream| {
-                stream.map(|c| match c {
-                    Ok(StreamingChoice::Message(choice)) => Ok(choice),
-                    // TODO: I'm just spitting out the tool name for now, need to figure out how to
-                    // handle this better
-                    Ok(StreamingChoice::ToolCall(name, _id, _params)) => Ok(name),
-                    Err(e) => Err(Error::LLM(LLMError::Response(e))),
-                })
-            })
+            .map(|stream| stream.map(|c| c.map_err(|e| Error::LLM(LLMError::Response(e)))))
             .map_err(|e| Error::LLM(LLMError::Api(e.to_string())))?;
-        Ok(Box::pin(stream) as Pin<Box<dyn Stream<Item = Result<String>> + Send>>)
-    }
-
-    async fn generate_embedding(&self, to_embed: EmbeddingType) -> Result<()> {
-        let model = self.client.embedding_model(&self.model());
-        let embedder = EmbeddingsBuilder::new(model.clone());
-        match to_embed {
-            EmbeddingType::Text(text) => {
-                if let Err(e) = embedder.document(text.to_string()) {
-                    error!("Embedding error: {}", e);
-                }
-            }
-            EmbeddingType::Document(document) => {
-                if let Err(e) = embedder.document(document.to_string()) {
-                    error!("Embedding error: {}", e);
-                }
-            }
-        }
-        Ok(())
+        Ok(Box::pin(stream) as LLMStream)
     }
 
-    async fn get_models(&self) -> Result<Vec<String>> {
-        reqwest::get("http://127.0.0.1:11434/v1/models")
-            .await?
-            .json::<Vec<String>>()
-            .await
-            .map_err(|e| Error::LLM(LLMError::Other(e.to_string())))
+    async fn generate_embeddings(&self, inputs: Vec<EmbeddingType>) -> Result<Vec<Vec<f32>>> {
+        let model_name = self.embedding_model().ok_or_else(|| {
+            LLMError::Configuration(String::from("No embedding model configured"))
+        })?;
+        let model = self.client.embedding_model(model_name);
+        embed_with(&model, inputs, self.config.vector_db.vector_size).await
+    }
+
+    async fn get_models(&self) -> Result<Vec<ModelInfo>> {
+        self.models.get_or_fetch(self.fetch_models()).await
+    }
+}
+
+/// Body of a `/api/chat` request constraining the answer to `schema`. Options left unset in
+/// the request are left out, so Ollama applies the model's defaults.
+fn json_request_body(model: &str, request: CompletionRequest, schema: &Value) -> Result<Value> {
+    let prompt = request.prompt_with_context();
+    let messages = request
+        .preamble
+        .iter()
+        .map(|preamble| Ok(ollama::Message::system(preamble)))
+        .chain(
+            request
+                .chat_history
+                .into_iter()
+                .chain([prompt])
+                .map(ollama::Message::try_from),
+        )
+        .collect::<StdResult<Vec<_>, _>>()
+        .map_err(|e| LLMError::Api(e.to_string()))?;
+
+    let mut options = match request.additional_params {
+        Some(Value::Object(params)) => params,
+        _ => Map::new(),
+    };
+    if let Some(temperature) = request.temperature {
+        options.insert("temperature".to_string(), json!(temperature));
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        options.insert("num_predict".to_string(), json!(max_tokens));
+    }
+
+    let mut body = json!({
+        "model": model,
+        "messages": messages,
+        "format": schema,
+        "stream": false,
+    });
+    if !options.is_empty() {
+        body["options"] = Value::Object(options);
+    }
+    if !request.tools.is_empty() {
+        body["tools"] = json!(
+            request
+                .tools
+                .into_iter()
+                .map(ollama::ToolDefinition::from)
+                .collect::<Vec<_>>()
+        );
+    }
+    Ok(body)
+}
+
+/// Tokens of the prompt and of the answer, which Ollama reports once the answer is done.
+fn usage(response: &ollama::CompletionResponse) -> Option<TokenUsage> {
+    Some(TokenUsage {
+        input_tokens: response.prompt_eval_count?,
+        output_tokens: response.eval_count?,
+    })
+}
+
+/// Rig's Ollama message conversion drops `UserContent::ToolResult`, so tool results are
+/// rewritten as plain text the model can read.
+fn inline_tool_results(mut request: CompletionRequest) -> CompletionRequest {
+    request.prompt = inline_message_tool_results(request.prompt);
+    request.chat_history = request
+        .chat_history
+        .into_iter()
+        .map(inline_message_tool_results)
+        .collect();
+    request
+}
+
+fn inline_message_tool_results(mut message: Message) -> Message {
+    let Message::User { content } = &mut message else {
+        return message;
+    };
+
+    for item in content.iter_mut() {
+        let UserContent::ToolResult(result) = item else {
+            continue;
+        };
+        let output = result
+            .content
+            .iter()
+            .filter_map(|c| match c {
+                ToolResultContent::Text(text) => Some(text.text.as_str()),
+                ToolResultContent::Image(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        *item = UserContent::text(f!("Result of tool `{}`: {}", result.id, output));
+    }
+
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use rig::completion::ToolDefinition;
+
+    use super::*;
+
+    #[test]
+    fn test_json_request_body_leaves_out_unset_options() {
+        let request = CompletionRequest {
+            prompt: Message::user("Name a colour"),
+            preamble: Some("Answer in JSON".to_string()),
+            chat_history: vec![],
+            documents: vec![],
+            tools: vec![],
+            temperature: None,
+            max_tokens: None,
+            additional_params: None,
+        };
+        let schema = json!({ "type": "object" });
+        let body = json_request_body("llama3.2", request, &schema).unwrap();
+        assert_eq!(body["format"], schema);
+        assert_eq!(body["messages"].as_array().unwrap().len(), 2);
+        assert!(body.get("options").is_none() && body.get("tools").is_none());
+
+        let request = CompletionRequest {
+            prompt: Message::user("Name a colour"),
+            preamble: None,
+            chat_history: vec![],
+            documents: vec![],
+            tools: vec![ToolDefinition {
+                name: "calculator".to_string(),
+                description: "Evaluates arithmetic".to_string(),
+                parameters: json!({ "type": "object" }),
+            }],
+            temperature: Some(0.5),
+            max_tokens: Some(64),
+            additional_params: None,
+        };
+        let body = json_request_body("llama3.2", request, &schema).unwrap();
+        assert_eq!(
+            body["options"],
+            json!({ "temperature": 0.5, "num_predict": 64 })
+        );
+        assert_eq!(body["tools"][0]["function"]["name"], "calculator");
+    }
+
+    #[test]
+    fn test_model_info_from_show_response() {
+        let details = serde_json::from_value::<ShowResponse>(json!({
+            "capabilities": ["completion", "tools"],
+            "model_info": { "general.architecture": "llama", "llama.context_length": 131072 }
+        }))
+        .unwrap();
+        let model = TaggedModel {
+            name: "llama3.2:1b".to_string(),
+            size: Some(1_321_098_329),
+        };
+
+        let info = model_info(model, details);
+        assert_eq!(info.context_length, Some(131072));
+        assert!(info.supports_tools);
+        assert!(!info.supports_embeddings);
+        assert_eq!(info.size, Some(1_321_098_329));
+
+        // Older servers report no capabilities; embedding models are recognized by name
+        let model = TaggedModel {
+            name: "nomic-embed-text:latest".to_string(),
+            size: None,
+        };
+        assert!(model_info(model, ShowResponse::default()).supports_embeddings);
     }
 }
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Settings for any server speaking the OpenAI chat completions API (llama.cpp, vLLM, LM Studio).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OpenAICompatibleProviderConfig {
+    /// Root of the API, including the version segment (e.g. `http://127.0.0.1:8080/v1`)
+    #[serde(default = "default_base_url")]
+    pub base_url: Url,
+
+    /// Sent as a bearer token; local servers usually don't need one
+    #[serde(default)]
+    pub api_key: Option<String>,
+
+    #[serde(default = "default_model")]
+    pub model: String,
+
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+
+    /// Extra headers sent with every request
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl Default for OpenAICompatibleProviderConfig {
+    fn default() -> Self {
+        Self {
+            base_url: default_base_url(),
+            api_key: None,
+            model: default_model(),
+            embedding_model: None,
+            headers: HashMap::new(),
+        }
+    }
+}
+
+fn default_base_url() -> Url {
+    // llama.cpp's `llama-server` listens here by default
+    Url::parse("http://127.0.0.1:8080/v1").unwrap()
+}
+
+fn default_model() -> String {
+    // llama.cpp serves whichever model it was started with and ignores this name
+    String::from("default")
+}
@@ -0,0 +1,359 @@
+mod config;
+
+use std::sync::Arc;
+
+use async_std::stream::StreamExt;
+use reqwest::{
+    Client as HttpClient, RequestBuilder, StatusCode,
+    header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue},
+};
+use rig::{
+    completion::{self, CompletionRequest},
+    providers::openai::{
+        self, EmbeddingResponse, ToolDefinition, send_compatible_streaming_request,
+    },
+};
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+use super::EmbeddingType;
+use crate::{
+    config::AppConfig,
+    llm::{
+        Completion, LLMError, LLMProvider, LLMStream, ModelCache, ModelInfo, ProviderRole,
+        TokenUsage, check_dimensions, looks_like_embedding_model,
+    },
+    prelude::*,
+};
+pub use config::OpenAICompatibleProviderConfig;
+
+/// Number of inputs sent in a single embeddings request.
+const EMBEDDING_BATCH_SIZE: usize = 256;
+
+/// Talks to any server implementing the OpenAI API, such as llama.cpp, vLLM or LM Studio.
+///
+/// Requests are sent directly rather than through rig's OpenAI client so the configured
+/// headers are included; rig's message and response types are still used for the wire format.
+pub struct OpenAICompatibleProvider {
+    http: HttpClient,
+    config: Arc<AppConfig>,
+    models: ModelCache,
+}
+
+impl OpenAICompatibleProvider {
+    pub fn new(config: Arc<AppConfig>) -> Result<Self> {
+        let settings = &config.provider_configs.openai_compatible;
+        let http = HttpClient::builder()
+            .default_headers(default_headers(settings)?)
+            .build()
+            .map_err(|e| LLMError::Configuration(e.to_string()))?;
+
+        Ok(Self {
+            http,
+            config,
+            models: ModelCache::default(),
+        })
+    }
+
+    fn settings(&self) -> &OpenAICompatibleProviderConfig {
+        &self.config.provider_configs.openai_compatible
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.http.get(endpoint(&self.settings().base_url, path))
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.http.post(endpoint(&self.settings().base_url, path))
+    }
+
+    /// Converts a rig request into an OpenAI chat completions body.
+    fn request_body(&self, request: CompletionRequest) -> Result<Value> {
+        let mut messages = request
+            .preamble
+            .as_deref()
+            .map(openai::Message::system)
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let prompt = request.prompt_with_context();
+        for message in request.chat_history.into_iter().chain([prompt]) {
+            let converted: Vec<openai::Message> = message
+                .try_into()
+                .map_err(|e: rig::message::MessageError| LLMError::Api(e.to_string()))?;
+            messages.extend(converted);
+        }
+
+        let mut body = json!({
+            "model": self.model(),
+            "messages": messages,
+        });
+        let fields = body.as_object_mut().expect("body is an object");
+
+        if !request.tools.is_empty() {
+            let tools = request
+                .tools
+                .into_iter()
+                .map(ToolDefinition::from)
+                .collect::<Vec<_>>();
+            fields.insert("tools".to_string(), json!(tools));
+            fields.insert("tool_choice".to_string(), json!("auto"));
+        }
+        if let Some(temperature) = request.temperature {
+            fields.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            fields.insert("max_tokens".to_string(), json!(max_tokens));
+        }
+        if let Some(Value::Object(params)) = request.additional_params {
+            fields.extend(params);
+        }
+
+        Ok(body)
+    }
+
+    async fn fetch_models(&self) -> Result<Vec<ModelInfo>> {
+        let models = send(self.get("models"))
+            .await?
+            .json::<ModelList>()
+            .await
+            .map_err(|e| Error::LLM(LLMError::Parsing(e.to_string())))?;
+        Ok(models.data.into_iter().map(ModelInfo::from).collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for OpenAICompatibleProvider {
+    fn model(&self) -> &str {
+        &self.settings().model
+    }
+
+    fn embedding_model(&self) -> Option<&str> {
+        self.config
+            .embedding_provider_configs
+            .openai_compatible
+            .embedding_model
+            .as_deref()
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<Completion> {
+        let body = self.request_body(request)?;
+        let response = send(self.post("chat/completions").json(&body)).await?;
+
+        let response = response
+            .json::<openai::CompletionResponse>()
+            .await
+            .map_err(|e| LLMError::Parsing(e.to_string()))?;
+        // Only the prompt and total tokens are read by rig; the rest is the answer
+        let usage = response.usage.as_ref().map(|usage| TokenUsage {
+            input_tokens: usage.prompt_tokens as u64,
+            output_tokens: usage.total_tokens.saturating_sub(usage.prompt_tokens) as u64,
+        });
+        let response: completion::CompletionResponse<_> =
+            response.try_into().map_err(LLMError::Response)?;
+        Ok(Completion {
+            choice: response.choice,
+            usage,
+        })
+    }
+
+    /// Constrains the answer with a JSON schema response format.
+    async fn complete_json(
+        &self,
+        mut request: CompletionRequest,
+        schema: &Value,
+    ) -> Result<Completion> {
+        let mut params = match request.additional_params.take() {
+            Some(Value::Object(params)) => params,
+            _ => Map::new(),
+        };
+        params.insert(
+            "response_format".to_string(),
+            json!({
+                "type": "json_schema",
+                "json_schema": { "name": "response", "schema": schema },
+            }),
+        );
+        request.additional_params = Some(Value::Object(params));
+        self.complete(request).await
+    }
+
+    async fn generate_stream(&self, request: CompletionRequest) -> Result<LLMStream> {
+        let mut body = self.request_body(request)?;
+        body["stream"] = json!(true);
+
+        let stream = send_compatible_streaming_request(self.post("chat/completions").json(&body))
+            .await
+            .map(|stream| stream.map(|c| c.map_err(|e| Error::LLM(LLMError::Response(e)))))
+            .map_err(|e| Error::LLM(LLMError::Api(e.to_string())))?;
+        Ok(Box::pin(stream) as LLMStream)
+    }
+
+    async fn generate_embeddings(&self, inputs: Vec<EmbeddingType>) -> Result<Vec<Vec<f32>>> {
+        let model = self.embedding_model().ok_or_else(|| {
+            LLMError::Configuration(String::from("No embedding model configured"))
+        })?;
+        let texts = inputs
+            .into_iter()
+            .map(EmbeddingType::into_text)
+            .collect::<Vec<_>>();
+        debug!("Embedding {} input(s)", texts.len());
+
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(EMBEDDING_BATCH_SIZE) {
+            let request = self
+                .post("embeddings")
+                .json(&json!({ "model": model, "input": batch }));
+            let mut response = send(request)
+                .await
+                .map_err(|e| LLMError::Embedding(f!("{} input(s)", batch.len()), e.to_string()))?
+                .json::<EmbeddingResponse>()
+                .await
+                .map_err(|e| LLMError::Embedding(f!("{} input(s)", batch.len()), e.to_string()))?;
+
+            // Servers may answer out of order; `index` refers to the position in the batch
+            response.data.sort_by_key(|data| data.index);
+            vectors.extend(
+                response
+                    .data
+                    .into_iter()
+                    .map(|data| data.embedding.into_iter().map(|x| x as f32).collect()),
+            );
+        }
+
+        check_dimensions(&vectors, self.config.vector_db.vector_size)?;
+        Ok(vectors)
+    }
+
+    /// Only checks that the server answers; llama.cpp serves a single model whatever name is
+    /// requested, so the configured model is not looked up.
+    async fn probe(&self, _role: ProviderRole) -> Result<()> {
+        self.get_models().await.map(|_| ())
+    }
+
+    async fn get_models(&self) -> Result<Vec<ModelInfo>> {
+        self.models.get_or_fetch(self.fetch_models()).await
+    }
+}
+
+/// Response of `GET /models`.
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+/// A model of `GET /models`; besides `id`, servers add their own optional fields.
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+    /// Reported by vLLM
+    max_model_len: Option<u64>,
+    /// Reported by llama.cpp
+    meta: Option<LlamaCppMeta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LlamaCppMeta {
+    n_ctx_train: Option<u64>,
+    size: Option<u64>,
+}
+
+impl From<ModelEntry> for ModelInfo {
+    fn from(model: ModelEntry) -> Self {
+        // The API does not describe capabilities, so they are inferred from the name
+        let supports_embeddings = looks_like_embedding_model(&model.id);
+        ModelInfo {
+            context_length: model
+                .max_model_len
+                .or(model.meta.as_ref().and_then(|meta| meta.n_ctx_train)),
+            supports_embeddings,
+            supports_tools: !supports_embeddings,
+            size: model.meta.and_then(|meta| meta.size),
+            ..ModelInfo::new(model.id)
+        }
+    }
+}
+
+/// Sends a request, turning transport failures and non-success statuses into errors.
+async fn send(request: RequestBuilder) -> Result<reqwest::Response> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| LLMError::Connection(e.to_string()))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(Error::LLM(match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                LLMError::Configuration(f!("The server rejected the API key: {}", body))
+            }
+            _ => LLMError::Api(f!("{}: {}", status, body)),
+        }));
+    }
+    Ok(response)
+}
+
+/// Joins an API path onto the base URL, keeping any path the base URL already has.
+fn endpoint(base_url: &url::Url, path: &str) -> String {
+    f!("{}/{}", base_url.as_str().trim_end_matches('/'), path)
+}
+
+fn default_headers(settings: &OpenAICompatibleProviderConfig) -> Result<HeaderMap> {
+    let invalid = |name: &str| {
+        Error::LLM(LLMError::Configuration(f!(
+            "Invalid value for header `{}`",
+            name
+        )))
+    };
+
+    let mut headers = HeaderMap::new();
+    if let Some(api_key) = &settings.api_key {
+        let value = HeaderValue::from_str(&f!("Bearer {}", api_key))
+            .map_err(|_| invalid(AUTHORIZATION.as_str()))?;
+        headers.insert(AUTHORIZATION, value);
+    }
+    for (name, value) in &settings.headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid(name))?;
+        let header_value = HeaderValue::from_str(value).map_err(|_| invalid(name))?;
+        headers.insert(header_name, header_value);
+    }
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_keeps_base_path() {
+        let base = url::Url::parse("http://127.0.0.1:8080/v1/").unwrap();
+        assert_eq!(
+            endpoint(&base, "chat/completions"),
+            "http://127.0.0.1:8080/v1/chat/completions"
+        );
+    }
+
+    #[test]
+    fn test_default_headers() {
+        let mut settings = OpenAICompatibleProviderConfig {
+            api_key: Some("secret".to_string()),
+            ..Default::default()
+        };
+        settings
+            .headers
+            .insert("X-Title".to_string(), "Lyn".to_string());
+
+        let headers = default_headers(&settings).unwrap();
+        assert_eq!(headers[AUTHORIZATION], "Bearer secret");
+        assert_eq!(headers["x-title"], "Lyn");
+
+        settings
+            .headers
+            .insert("Bad Header".to_string(), "value".to_string());
+        assert!(matches!(
+            default_headers(&settings),
+            Err(Error::LLM(LLMError::Configuration(_)))
+        ));
+    }
+}
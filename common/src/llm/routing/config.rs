@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::llm::LLMProviders;
+
+/// Which providers answer a prompt, and in what order they are tried.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoutingConfig {
+    /// Providers tried in order until one answers; empty means only `provider`
+    #[serde(default)]
+    pub fallback: Vec<LLMProviders>,
+
+    /// Never send prompts to cloud providers
+    #[serde(default)]
+    pub private: bool,
+
+    /// Attempts repeated on a provider that cannot be reached before moving to the next one
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// Delay before the first retry, doubled on each following one
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+
+    /// Rules checked in order; the first matching one replaces the fallback chain
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            fallback: Vec::new(),
+            private: false,
+            max_retries: default_max_retries(),
+            backoff_ms: default_backoff_ms(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Sends the prompts matching every condition set on the rule to its own providers.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RoutingRule {
+    /// Matches prompts whose estimated size, history and documents included, reaches this
+    /// many tokens
+    #[serde(default)]
+    pub min_prompt_tokens: Option<usize>,
+
+    /// Matches prompts offering tools to the model. Every prompt is offered the enabled tools
+    /// unless `tools.selection` is `retrieved` with a `min_tool_score`, which leaves out the
+    /// tools irrelevant to the prompt
+    #[serde(default)]
+    pub requires_tools: bool,
+
+    /// Providers tried in order for matching prompts
+    pub providers: Vec<LLMProviders>,
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_backoff_ms() -> u64 {
+    500
+}
@@ -0,0 +1,404 @@
+//! Chooses which of the configured providers answers each request.
+
+pub mod config;
+
+use std::{sync::Arc, time::Duration};
+
+use rig::completion::CompletionRequest;
+use serde_json::Value;
+
+use crate::{
+    config::AppConfig,
+    core::SessionId,
+    llm::{
+        Completion, EmbeddingType, LLMError, LLMProvider, LLMProviders, LLMStream, MeteredProvider,
+        ModelInfo, ProviderRole, UsageLedger, create_llm_provider,
+    },
+    prelude::*,
+};
+pub use config::{RoutingConfig, RoutingRule};
+
+/// A provider taking part in routing.
+#[derive(Clone)]
+struct Route {
+    kind: LLMProviders,
+    provider: Arc<dyn LLMProvider>,
+    cloud: bool,
+}
+
+/// Sends each request through a chain of providers, retrying the ones that cannot be reached
+/// and falling back to the next when one fails.
+///
+/// The chain is the configured `fallback` list, unless a routing rule matches the request.
+/// Cloud providers are left out entirely when routing is private.
+#[derive(Clone)]
+pub struct ProviderRouter {
+    routes: Vec<Route>,
+    config: RoutingConfig,
+    local_only: bool,
+}
+
+impl ProviderRouter {
+    /// Creates every provider named by the routing config or preferred by a persona, or only
+    /// `provider` when neither names any.
+    pub fn new(config: Arc<AppConfig>) -> Result<Self> {
+        let routing = config.routing.clone();
+        let mut kinds = if routing.fallback.is_empty() {
+            vec![config.provider.clone()]
+        } else {
+            routing.fallback.clone()
+        };
+        kinds.extend(
+            routing
+                .rules
+                .iter()
+                .flat_map(|r| r.providers.iter().cloned()),
+        );
+        kinds.extend(config.personas.values().filter_map(|p| p.provider.clone()));
+
+        let mut routes: Vec<Route> = Vec::new();
+        for kind in kinds {
+            if routes.iter().any(|route| route.kind == kind) {
+                continue;
+            }
+            let cloud = kind.is_cloud(&config.provider_configs);
+            if cloud && routing.private {
+                info!("Private routing leaves out the {:?} provider", kind);
+                continue;
+            }
+            let mut provider_config = (*config).clone();
+            provider_config.provider = kind.clone();
+            let provider = create_llm_provider(Arc::new(provider_config), ProviderRole::Chat)?;
+            routes.push(Route {
+                kind,
+                provider,
+                cloud,
+            });
+        }
+
+        Self::from_routes(routes, routing)
+    }
+
+    /// Routes between already constructed providers, keeping their order. Each provider comes
+    /// with whether it is a cloud provider.
+    pub fn with_providers(
+        providers: impl IntoIterator<Item = (LLMProviders, Arc<dyn LLMProvider>, bool)>,
+        config: RoutingConfig,
+    ) -> Result<Self> {
+        let routes = providers
+            .into_iter()
+            .map(|(kind, provider, cloud)| Route {
+                kind,
+                provider,
+                cloud,
+            })
+            .collect();
+        Self::from_routes(routes, config)
+    }
+
+    fn from_routes(mut routes: Vec<Route>, config: RoutingConfig) -> Result<Self> {
+        if config.private {
+            routes.retain(|route| !route.cloud);
+        }
+        if routes.is_empty() {
+            return Err(Error::LLM(LLMError::Configuration(String::from(
+                "No provider is available for routing",
+            ))));
+        }
+        Ok(Self {
+            local_only: config.private,
+            routes,
+            config,
+        })
+    }
+
+    /// A router that never sends requests to cloud providers, for prompts that must stay on
+    /// this machine.
+    pub fn local_only(&self) -> Result<Self> {
+        let routes = self
+            .routes
+            .iter()
+            .filter(|route| !route.cloud)
+            .cloned()
+            .collect::<Vec<_>>();
+        if routes.is_empty() {
+            return Err(Error::LLM(LLMError::Configuration(String::from(
+                "Private prompts need a local provider, but only cloud providers are configured",
+            ))));
+        }
+        Ok(Self {
+            routes,
+            config: self.config.clone(),
+            local_only: true,
+        })
+    }
+
+    /// A router recording every call its providers make in `ledger`, attributed to
+    /// `session_id`.
+    pub fn metered(&self, ledger: Arc<UsageLedger>, session_id: Option<SessionId>) -> Self {
+        let mut router = self.clone();
+        for route in &mut router.routes {
+            route.provider = Arc::new(MeteredProvider::new(
+                route.provider.clone(),
+                route.kind.clone(),
+                ledger.clone(),
+                session_id,
+            ));
+        }
+        router
+    }
+
+    /// A router trying `kind` before the rest of the fallback chain. Routing rules still take
+    /// precedence, and a provider left out of routing cannot be preferred.
+    pub fn preferring(&self, kind: &LLMProviders) -> Self {
+        let mut router = self.clone();
+        let Some(position) = router.routes.iter().position(|route| &route.kind == kind) else {
+            warn!("Cannot prefer {:?}, it takes no part in routing", kind);
+            return router;
+        };
+
+        let preferred = router.routes.remove(position);
+        router.routes.insert(0, preferred);
+        let mut fallback = if router.config.fallback.is_empty() {
+            vec![self.primary().kind.clone()]
+        } else {
+            router.config.fallback.clone()
+        };
+        fallback.retain(|k| k != kind);
+        fallback.insert(0, kind.clone());
+        router.config.fallback = fallback;
+        router
+    }
+
+    /// Whether requests may be sent to cloud providers.
+    pub fn is_local_only(&self) -> bool {
+        self.local_only
+    }
+
+    /// The provider of the given kind, when it takes part in routing.
+    pub fn provider(&self, kind: &LLMProviders) -> Option<Arc<dyn LLMProvider>> {
+        self.routes
+            .iter()
+            .find(|route| &route.kind == kind)
+            .map(|route| route.provider.clone())
+    }
+
+    fn primary(&self) -> &Route {
+        &self.routes[0]
+    }
+
+    /// The providers to try for a request, in order.
+    ///
+    /// Providers left out of routing, such as cloud providers of a private session, are
+    /// skipped; when a matching rule names none of the others, the fallback chain is used.
+    fn chain(&self, request: &CompletionRequest) -> Vec<&Route> {
+        let tokens = self
+            .primary()
+            .provider
+            .token_estimator()
+            .estimate_request(request);
+        let available = |kinds: &[LLMProviders]| {
+            kinds
+                .iter()
+                .filter_map(|kind| self.routes.iter().find(|route| &route.kind == kind))
+                .collect::<Vec<_>>()
+        };
+
+        if let Some(rule) = self
+            .config
+            .rules
+            .iter()
+            .find(|rule| matches(rule, tokens, request))
+        {
+            let chain = available(&rule.providers);
+            if !chain.is_empty() {
+                debug!("Routing rule {:?} matches the request", rule);
+                return chain;
+            }
+            debug!(
+                "Routing rule {:?} matches the request, but none of its providers may answer it",
+                rule
+            );
+        }
+        let chain = available(&self.config.fallback);
+        if chain.is_empty() {
+            vec![self.primary()]
+        } else {
+            chain
+        }
+    }
+
+    /// Runs `call` against each provider of the chain until one succeeds, retrying
+    /// unreachable providers with exponential backoff.
+    async fn attempt<T, F, Fut>(&self, request: CompletionRequest, call: F) -> Result<T>
+    where
+        F: Fn(Arc<dyn LLMProvider>, CompletionRequest) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut last_error = None;
+        for route in self.chain(&request) {
+            let mut backoff = Duration::from_millis(self.config.backoff_ms);
+            for attempt in 0..=self.config.max_retries {
+                match call(route.provider.clone(), copy_request(&request)).await {
+                    Ok(value) => return Ok(value),
+                    Err(Error::LLM(LLMError::Connection(e)))
+                        if attempt < self.config.max_retries =>
+                    {
+                        warn!(
+                            "{:?} is unreachable ({}), retrying in {:?}",
+                            route.kind, e, backoff
+                        );
+                        async_std::task::sleep(backoff).await;
+                        backoff *= 2;
+                    }
+                    Err(e) => {
+                        warn!("{:?} failed: {}", route.kind, e);
+                        last_error = Some(e);
+                        break;
+                    }
+                }
+            }
+        }
+        Err(last_error.expect("the chain holds at least one provider"))
+    }
+}
+
+/// Rig requests are not `Clone`, but each attempt consumes one.
+pub(crate) fn copy_request(request: &CompletionRequest) -> CompletionRequest {
+    CompletionRequest {
+        prompt: request.prompt.clone(),
+        preamble: request.preamble.clone(),
+        chat_history: request.chat_history.clone(),
+        documents: request.documents.clone(),
+        tools: request.tools.clone(),
+        temperature: request.temperature,
+        max_tokens: request.max_tokens,
+        additional_params: request.additional_params.clone(),
+    }
+}
+
+fn matches(rule: &RoutingRule, tokens: usize, request: &CompletionRequest) -> bool {
+    let long_enough = rule.min_prompt_tokens.is_none_or(|min| tokens >= min);
+    let has_tools = !rule.requires_tools || !request.tools.is_empty();
+    long_enough && has_tools
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for ProviderRouter {
+    fn model(&self) -> &str {
+        self.primary().provider.model()
+    }
+
+    fn embedding_model(&self) -> Option<&str> {
+        self.primary().provider.embedding_model()
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<Completion> {
+        self.attempt(request, |provider, request| async move {
+            provider.complete(request).await
+        })
+        .await
+    }
+
+    async fn complete_json(
+        &self,
+        request: CompletionRequest,
+        schema: &Value,
+    ) -> Result<Completion> {
+        self.attempt(request, |provider, request| async move {
+            provider.complete_json(request, schema).await
+        })
+        .await
+    }
+
+    /// Falls back only while opening the stream; a stream failing midway ends the answer.
+    async fn generate_stream(&self, request: CompletionRequest) -> Result<LLMStream> {
+        self.attempt(request, |provider, request| async move {
+            provider.generate_stream(request).await
+        })
+        .await
+    }
+
+    async fn get_models(&self) -> Result<Vec<ModelInfo>> {
+        self.primary().provider.get_models().await
+    }
+
+    /// Succeeds when any provider of the chain is usable, so a local server being down does
+    /// not prevent falling back to another provider.
+    async fn probe(&self, role: ProviderRole) -> Result<()> {
+        let mut first_error = None;
+        for route in &self.routes {
+            match route.provider.probe(role).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    warn!("{:?} is not usable: {}", route.kind, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_error.expect("the router holds at least one provider"))
+    }
+
+    async fn generate_embeddings(&self, inputs: Vec<EmbeddingType>) -> Result<Vec<Vec<f32>>> {
+        self.primary().provider.generate_embeddings(inputs).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rig::message::Message;
+
+    use super::*;
+    use crate::llm::{MockProvider, MockReply};
+
+    fn route(reply: &str) -> Arc<dyn LLMProvider> {
+        Arc::new(MockProvider::scripted([MockReply::text(reply)]))
+    }
+
+    #[async_std::test]
+    async fn test_router_falls_back_and_honors_privacy() {
+        let config = RoutingConfig {
+            fallback: vec![LLMProviders::Ollama, LLMProviders::Gemini],
+            rules: vec![RoutingRule {
+                min_prompt_tokens: Some(10),
+                providers: vec![LLMProviders::Gemini],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let router = ProviderRouter::with_providers(
+            [
+                (LLMProviders::Ollama, route("from this machine"), false),
+                (
+                    LLMProviders::Gemini,
+                    Arc::new(MockProvider::scripted([
+                        MockReply::text("from the cloud"),
+                        MockReply::text("from the cloud again"),
+                    ])),
+                    true,
+                ),
+            ],
+            config,
+        )
+        .unwrap();
+        let long_prompt = "a rather long prompt that the rule routes to the cloud";
+
+        let request = router.create_prompt(Message::user(long_prompt));
+        assert_eq!(router.generate(request).await.unwrap(), "from the cloud");
+
+        // The rule only names a cloud provider, so the private router uses the local chain
+        let private = router.local_only().unwrap();
+        let request = private.create_prompt(Message::user(long_prompt));
+        assert_eq!(
+            private.generate(request).await.unwrap(),
+            "from this machine"
+        );
+
+        // The local provider has run out of replies, so the fallback chain moves on
+        let request = router.create_prompt(Message::user("Hi"));
+        assert_eq!(
+            router.generate(request).await.unwrap(),
+            "from the cloud again"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Machine-readable answers: asking a provider for JSON matching a Rust type.
+
+use rig::{completion::CompletionRequest, message::Message};
+use schemars::{JsonSchema, r#gen::SchemaSettings};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{
+    llm::{LLMError, LLMProvider, routing::copy_request},
+    prelude::*,
+};
+
+/// Answers parsed before giving up on a provider that does not return the expected JSON.
+const MAX_ATTEMPTS: usize = 3;
+
+/// Keywords of the OpenAPI schema subset Gemini accepts as a response schema.
+const GEMINI_SCHEMA_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "description",
+    "nullable",
+    "enum",
+    "properties",
+    "required",
+    "items",
+    "minItems",
+    "maxItems",
+    "anyOf",
+];
+
+/// Asks providers for answers deserialized into a Rust type.
+#[async_trait::async_trait]
+pub trait StructuredOutput {
+    /// Sends a prompt asking for a JSON answer matching the schema of `T`, and parses it.
+    ///
+    /// The provider's JSON mode is used when it has one. Answers that do not parse are sent
+    /// back with the error for another try; once the attempts run out, the error is an
+    /// [`LLMError::Parsing`] holding the raw text of the last answer.
+    async fn generate_structured<T>(&self, prompt: CompletionRequest) -> Result<T>
+    where
+        T: JsonSchema + DeserializeOwned;
+}
+
+#[async_trait::async_trait]
+impl<P: LLMProvider + ?Sized> StructuredOutput for P {
+    async fn generate_structured<T>(&self, mut prompt: CompletionRequest) -> Result<T>
+    where
+        T: JsonSchema + DeserializeOwned,
+    {
+        let schema = json_schema::<T>();
+        let instruction = f!(
+            "Reply only with a JSON value matching this JSON schema, without any other text:\n{}",
+            serde_json::to_string(&schema)?
+        );
+        prompt.preamble = Some(match prompt.preamble.take() {
+            Some(preamble) => f!("{}\n\n{}", preamble, instruction),
+            None => instruction,
+        });
+
+        let mut attempt = 1;
+        loop {
+            let question = prompt.prompt.clone();
+            let raw = self
+                .complete_json(copy_request(&prompt), &schema)
+                .await?
+                .text();
+            let error = match parse_json::<T>(&raw) {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            if attempt == MAX_ATTEMPTS {
+                return Err(Error::LLM(LLMError::Parsing(f!(
+                    "Response does not match the expected schema ({}): {}",
+                    error,
+                    raw
+                ))));
+            }
+
+            debug!("Structured answer did not parse ({}), asking again", error);
+            prompt
+                .chat_history
+                .extend([question, Message::assistant(raw)]);
+            prompt.prompt = Message::user(f!(
+                "That reply could not be read: {}. Reply again with only the JSON value.",
+                error
+            ));
+            attempt += 1;
+        }
+    }
+}
+
+/// JSON schema of `T`, with every definition inlined so providers need not resolve
+/// references.
+pub(crate) fn json_schema<T: JsonSchema>() -> Value {
+    let schema = SchemaSettings::draft07()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator()
+        .into_root_schema_for::<T>();
+    serde_json::to_value(schema).unwrap_or_default()
+}
+
+/// Rewrites a JSON schema into the subset Gemini accepts, turning `["T", "null"]` types into
+/// nullable ones and leaving out unsupported keywords.
+pub(crate) fn gemini_schema(schema: &Value) -> Value {
+    let Value::Object(fields) = schema else {
+        return schema.clone();
+    };
+
+    let mut converted = Map::new();
+    for (key, value) in fields {
+        match (key.as_str(), value) {
+            ("type", Value::Array(types)) => {
+                let null = Value::from("null");
+                if let Some(kind) = types.iter().find(|kind| **kind != null) {
+                    converted.insert(key.clone(), kind.clone());
+                }
+                if types.contains(&null) {
+                    converted.insert(String::from("nullable"), Value::Bool(true));
+                }
+            }
+            ("properties", Value::Object(properties)) => {
+                let properties = properties
+                    .iter()
+                    .map(|(name, property)| (name.clone(), gemini_schema(property)))
+                    .collect();
+                converted.insert(key.clone(), Value::Object(properties));
+            }
+            ("items", items) => {
+                converted.insert(key.clone(), gemini_schema(items));
+            }
+            ("anyOf", Value::Array(variants)) => {
+                let variants = variants.iter().map(gemini_schema).collect();
+                converted.insert(key.clone(), Value::Array(variants));
+            }
+            (key, value) if GEMINI_SCHEMA_KEYWORDS.contains(&key) => {
+                converted.insert(key.to_string(), value.clone());
+            }
+            _ => {}
+        }
+    }
+    Value::Object(converted)
+}
+
+/// Parses the JSON of an answer, tolerating a surrounding code fence or sentence.
+fn parse_json<T: DeserializeOwned>(text: &str) -> serde_json::Result<T> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .and_then(|fenced| fenced.strip_suffix("```"))
+        .unwrap_or(text)
+        .trim();
+    serde_json::from_str(text).or_else(|error| {
+        let start = text.find(['{', '[']);
+        let end = text.rfind(['}', ']']);
+        match (start, end) {
+            (Some(start), Some(end)) if start < end => serde_json::from_str(&text[start..=end]),
+            _ => Err(error),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::llm::{MockProvider, MockReply};
+
+    #[derive(Debug, PartialEq, Deserialize, JsonSchema)]
+    struct Answer {
+        title: String,
+        tags: Vec<String>,
+        rating: Option<u8>,
+    }
+
+    #[async_std::test]
+    async fn test_generate_structured_retries_until_valid() {
+        let provider = MockProvider::scripted([
+            MockReply::text("Sure! Here it is: {\"title\": \"Notes\"}"),
+            MockReply::text("```json\n{\"title\": \"Notes\", \"tags\": [\"a\"]}\n```"),
+        ]);
+        let answer = provider
+            .generate_structured::<Answer>(provider.create_prompt(Message::user("Describe it")))
+            .await
+            .unwrap();
+        assert_eq!(
+            answer,
+            Answer {
+                title: String::from("Notes"),
+                tags: vec![String::from("a")],
+                rating: None,
+            }
+        );
+        assert!(provider.prompts().await[1].contains("missing field `tags`"));
+
+        let provider = MockProvider::scripted(["one", "two", "not json"].map(MockReply::text));
+        let error = provider
+            .generate_structured::<Answer>(provider.create_prompt(Message::user("Describe it")))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, Error::LLM(LLMError::Parsing(text)) if text.ends_with("not json"))
+        );
+    }
+
+    #[test]
+    fn test_gemini_schema_keeps_supported_keywords() {
+        let schema = gemini_schema(&json_schema::<Answer>());
+        assert_eq!(
+            schema,
+            json!({
+                "type": "object",
+                "required": ["tags", "title"],
+                "properties": {
+                    "title": { "type": "string" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "rating": { "type": "integer", "format": "uint8", "nullable": true },
+                }
+            })
+        );
+    }
+}
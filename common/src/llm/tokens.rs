@@ -0,0 +1,153 @@
+//! Estimation of how many tokens a model's tokenizer turns a text into.
+
+use rig::{
+    completion::CompletionRequest,
+    message::{AssistantContent, Message, ToolCall, ToolResultContent, UserContent},
+};
+
+/// Tokens added by chat templates around each message, such as role markers.
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// Estimates token counts from character counts, with a ratio matching the model's tokenizer.
+///
+/// Running the actual tokenizer would need its vocabulary, which local servers and cloud APIs
+/// do not expose, so the estimate is only as good as the ratio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenEstimator {
+    chars_per_token: f64,
+}
+
+impl TokenEstimator {
+    pub fn new(chars_per_token: f64) -> Self {
+        Self {
+            chars_per_token: chars_per_token.max(1.0),
+        }
+    }
+
+    /// The estimator for a model, picked from the tokenizer family its name suggests.
+    ///
+    /// Families with small vocabularies split text into more tokens; unknown models use the
+    /// four characters per token of [`estimate_tokens`](crate::utils::estimate_tokens).
+    pub fn for_model(model: &str) -> Self {
+        let model = model.to_lowercase();
+        let chars_per_token = if ["llama2", "llama-2", "mistral", "mixtral", "phi"]
+            .iter()
+            .any(|family| model.contains(family))
+        {
+            3.5
+        } else if model.contains("qwen") || model.contains("deepseek") {
+            3.8
+        } else {
+            4.0
+        };
+        Self::new(chars_per_token)
+    }
+
+    /// Estimated tokens of a text.
+    pub fn estimate(&self, text: &str) -> usize {
+        (text.chars().count() as f64 / self.chars_per_token).ceil() as usize
+    }
+
+    /// Estimated tokens a message occupies in the context, its role markers, tool calls and
+    /// tool results included.
+    pub fn estimate_message(&self, message: &Message) -> usize {
+        let content = match message {
+            Message::User { content } => content
+                .iter()
+                .map(|content| match content {
+                    UserContent::Text(text) => self.estimate(&text.text),
+                    UserContent::ToolResult(result) => result
+                        .content
+                        .iter()
+                        .map(|content| match content {
+                            ToolResultContent::Text(text) => self.estimate(&text.text),
+                            ToolResultContent::Image(_) => 0,
+                        })
+                        .sum(),
+                    UserContent::Document(document) => self.estimate(&document.data),
+                    UserContent::Image(_) | UserContent::Audio(_) => 0,
+                })
+                .sum::<usize>(),
+            Message::Assistant { content } => content
+                .iter()
+                .map(|content| self.estimate_content(content))
+                .sum(),
+        };
+        content + MESSAGE_OVERHEAD
+    }
+
+    /// Estimated tokens of a part of a model's answer.
+    pub fn estimate_content(&self, content: &AssistantContent) -> usize {
+        match content {
+            AssistantContent::Text(text) => self.estimate(&text.text),
+            AssistantContent::ToolCall(call) => self.estimate_tool_call(call),
+        }
+    }
+
+    /// Estimated tokens of a tool call, its name and arguments.
+    pub fn estimate_tool_call(&self, call: &ToolCall) -> usize {
+        self.estimate(&call.function.name) + self.estimate(&call.function.arguments.to_string())
+    }
+
+    /// Estimated size of everything a request puts in the model's context.
+    pub fn estimate_request(&self, request: &CompletionRequest) -> usize {
+        let messages = request
+            .chat_history
+            .iter()
+            .chain([&request.prompt])
+            .map(|message| self.estimate_message(message))
+            .sum::<usize>();
+        let documents = request
+            .documents
+            .iter()
+            .map(|document| self.estimate(&document.text))
+            .sum::<usize>();
+        let tools = serde_json::to_string(&request.tools)
+            .map_or(0, |definitions| self.estimate(&definitions));
+        let preamble = request
+            .preamble
+            .as_deref()
+            .map_or(0, |preamble| self.estimate(preamble));
+        messages + documents + tools + preamble
+    }
+}
+
+impl Default for TokenEstimator {
+    fn default() -> Self {
+        Self::new(4.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rig::OneOrMany;
+
+    use super::*;
+
+    #[test]
+    fn test_estimate_per_model() {
+        let text = "a".repeat(70);
+        assert_eq!(TokenEstimator::for_model("llama3.2:1b").estimate(&text), 18);
+        assert_eq!(TokenEstimator::for_model("mistral:7b").estimate(&text), 20);
+        assert_eq!(
+            TokenEstimator::default().estimate(&text),
+            crate::utils::estimate_tokens(&text)
+        );
+        assert_eq!(
+            TokenEstimator::default().estimate_message(&Message::user("abcd")),
+            1 + MESSAGE_OVERHEAD
+        );
+
+        // Tool results are counted in full, not just the text around them
+        let result = Message::User {
+            content: OneOrMany::one(UserContent::tool_result(
+                "call-1",
+                OneOrMany::one(ToolResultContent::text("a".repeat(400))),
+            )),
+        };
+        assert_eq!(
+            TokenEstimator::default().estimate_message(&result),
+            100 + MESSAGE_OVERHEAD
+        );
+    }
+}
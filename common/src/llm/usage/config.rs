@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Whether provider calls are recorded, and what models cost.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UsageConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Prices by model name; calls to models without a price have no cost
+    #[serde(default)]
+    pub prices: HashMap<String, ModelPrice>,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            prices: HashMap::new(),
+        }
+    }
+}
+
+/// What a model costs, in the currency of the provider's bill.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct ModelPrice {
+    /// Price of a million prompt tokens
+    #[serde(default)]
+    pub input_per_million: f64,
+
+    /// Price of a million completion tokens
+    #[serde(default)]
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    /// Cost of a call with the given token counts.
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_million
+            + output_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
@@ -0,0 +1,271 @@
+//! Local record of provider calls, and its totals per day or session.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config,
+    core::SessionId,
+    llm::{LLMProviders, UsageConfig},
+    prelude::*,
+    utils::JsonLog,
+};
+
+const USAGE_FILE_NAME: &str = "usage.jsonl";
+
+/// What a provider was asked to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallKind {
+    Completion,
+    Stream,
+    Embedding,
+}
+
+/// A single call to a provider.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    /// Session the call answered; unset for memory summaries and embeddings
+    pub session_id: Option<SessionId>,
+    pub provider: LLMProviders,
+    pub model: String,
+    pub kind: CallKind,
+    /// Tokens of the request
+    pub input_tokens: u64,
+    /// Tokens of the response
+    pub output_tokens: u64,
+    pub latency_ms: u64,
+    /// Cost from the price table, when it lists the model
+    pub cost: Option<f64>,
+    /// The call failed
+    #[serde(default)]
+    pub failed: bool,
+    /// The token counts are estimated rather than reported by the provider, as they were
+    /// for every call recorded before providers reported them
+    #[serde(default = "default_estimated")]
+    pub estimated: bool,
+}
+
+fn default_estimated() -> bool {
+    true
+}
+
+/// How records are grouped into totals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageGrouping {
+    Day,
+    Session,
+}
+
+/// Totals of the calls to one model within a day or session.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageSummary {
+    /// The local day (`YYYY-MM-DD`) or session id; calls without a session are grouped as `-`
+    pub group: String,
+    pub provider: LLMProviders,
+    pub model: String,
+    pub calls: usize,
+    pub failed_calls: usize,
+    /// Calls whose token counts are estimated
+    pub estimated_calls: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub latency_ms: u64,
+    /// Total cost of the calls that have one, if any does
+    pub cost: Option<f64>,
+}
+
+/// Appends provider calls to `<data dir>/usage.jsonl` and totals them on request.
+///
+/// Recording is synchronous so it can happen when a stream is dropped midway.
+pub struct UsageLedger {
+    log: JsonLog<UsageRecord>,
+    config: UsageConfig,
+}
+
+impl UsageLedger {
+    /// Opens the ledger kept in the data directory.
+    pub fn new(config: UsageConfig) -> Result<Self> {
+        Ok(Self::with_file(
+            config::get_data_dir()?.join(USAGE_FILE_NAME),
+            config,
+        ))
+    }
+
+    pub fn with_file(path: PathBuf, config: UsageConfig) -> Self {
+        Self {
+            log: JsonLog::with_file(path),
+            config,
+        }
+    }
+
+    /// A ledger that never touches the filesystem.
+    pub fn in_memory(config: UsageConfig) -> Self {
+        Self {
+            log: JsonLog::in_memory(),
+            config,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Prices the call with the configured price table and stores it. Failures are only
+    /// logged, as accounting should never cost the user an answer.
+    pub fn record(&self, mut record: UsageRecord) {
+        if !self.config.enabled {
+            return;
+        }
+        record.cost = self
+            .config
+            .prices
+            .get(&record.model)
+            .map(|price| price.cost(record.input_tokens, record.output_tokens));
+
+        if let Err(e) = self.log.append(record) {
+            warn!("Failed to record usage: {}", e);
+        }
+    }
+
+    /// Every stored record, oldest first.
+    pub fn records(&self) -> Result<Vec<UsageRecord>> {
+        self.log.records()
+    }
+
+    /// Totals per model of the calls made in the last `days` days, or ever, grouped by day or
+    /// session, most recent group first.
+    pub fn summarize(
+        &self,
+        grouping: UsageGrouping,
+        days: Option<u32>,
+    ) -> Result<Vec<UsageSummary>> {
+        let since = days.map(|days| Utc::now() - Duration::days(i64::from(days)));
+        let records = self
+            .records()?
+            .into_iter()
+            .filter(|record| since.is_none_or(|since| record.timestamp >= since));
+        Ok(summarize(records, grouping))
+    }
+}
+
+fn summarize(
+    records: impl IntoIterator<Item = UsageRecord>,
+    grouping: UsageGrouping,
+) -> Vec<UsageSummary> {
+    let mut groups: BTreeMap<(String, String, String), (DateTime<Utc>, UsageSummary)> =
+        BTreeMap::new();
+    for record in records {
+        let group = match grouping {
+            UsageGrouping::Day => record
+                .timestamp
+                .with_timezone(&Local)
+                .format("%Y-%m-%d")
+                .to_string(),
+            UsageGrouping::Session => record
+                .session_id
+                .map_or_else(|| String::from("-"), |id| id.to_string()),
+        };
+        let key = (
+            group.clone(),
+            f!("{:?}", record.provider),
+            record.model.clone(),
+        );
+        let (last_call, summary) = groups.entry(key).or_insert_with(|| {
+            (
+                record.timestamp,
+                UsageSummary {
+                    group,
+                    provider: record.provider.clone(),
+                    model: record.model.clone(),
+                    calls: 0,
+                    failed_calls: 0,
+                    estimated_calls: 0,
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    latency_ms: 0,
+                    cost: None,
+                },
+            )
+        });
+        *last_call = (*last_call).max(record.timestamp);
+        summary.calls += 1;
+        summary.failed_calls += usize::from(record.failed);
+        summary.estimated_calls += usize::from(record.estimated);
+        summary.input_tokens += record.input_tokens;
+        summary.output_tokens += record.output_tokens;
+        summary.latency_ms += record.latency_ms;
+        if let Some(cost) = record.cost {
+            *summary.cost.get_or_insert(0.0) += cost;
+        }
+    }
+
+    let mut summaries = groups.into_values().collect::<Vec<_>>();
+    summaries.sort_by(|(a, _), (b, _)| b.cmp(a));
+    summaries.into_iter().map(|(_, summary)| summary).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{llm::ModelPrice, utils::temp_dir};
+
+    fn call(session_id: Option<SessionId>, model: &str, input_tokens: u64) -> UsageRecord {
+        UsageRecord {
+            timestamp: Utc::now(),
+            session_id,
+            provider: LLMProviders::Gemini,
+            model: model.to_string(),
+            kind: CallKind::Completion,
+            input_tokens,
+            output_tokens: 100,
+            latency_ms: 20,
+            cost: None,
+            failed: false,
+            estimated: false,
+        }
+    }
+
+    #[test]
+    fn test_ledger_prices_and_totals_calls() {
+        let dir = temp_dir();
+        let config = UsageConfig {
+            prices: HashMap::from([(
+                String::from("gemini-2.0-flash"),
+                ModelPrice {
+                    input_per_million: 1.0,
+                    output_per_million: 2.0,
+                },
+            )]),
+            ..Default::default()
+        };
+        let ledger = UsageLedger::with_file(dir.path().join(USAGE_FILE_NAME), config);
+        let session = Uuid::new_v4();
+        ledger.record(call(Some(session), "gemini-2.0-flash", 1_000));
+        ledger.record(call(Some(session), "gemini-2.0-flash", 3_000));
+        ledger.record(call(None, "llama3.2", 500));
+
+        let by_session = ledger.summarize(UsageGrouping::Session, None).unwrap();
+        assert_eq!(by_session.len(), 2);
+        let session_total = by_session
+            .iter()
+            .find(|summary| summary.group == session.to_string())
+            .unwrap();
+        assert_eq!(
+            (session_total.calls, session_total.input_tokens),
+            (2, 4_000)
+        );
+        assert!((session_total.cost.unwrap() - 0.0044).abs() < 1e-9);
+
+        let by_day = ledger.summarize(UsageGrouping::Day, Some(1)).unwrap();
+        assert_eq!(by_day.len(), 2);
+        assert!(by_day.iter().any(|summary| summary.cost.is_none()));
+    }
+}
@@ -0,0 +1,294 @@
+//! Accounting of the tokens, latency and cost of provider calls.
+
+mod config;
+mod ledger;
+
+use std::{sync::Arc, time::Instant};
+
+use chrono::Utc;
+use futures::StreamExt;
+use rig::{completion::CompletionRequest, streaming::StreamingChoice};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    core::SessionId,
+    llm::{
+        Completion, EmbeddingType, LLMProvider, LLMProviders, LLMStream, ModelInfo, ProviderRole,
+        TokenEstimator,
+    },
+    prelude::*,
+};
+pub use config::{ModelPrice, UsageConfig};
+pub use ledger::{CallKind, UsageGrouping, UsageLedger, UsageRecord, UsageSummary};
+
+/// Tokens a provider reports a call used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Records every completion, stream and embedding call of the provider it wraps in a
+/// [`UsageLedger`].
+///
+/// Completions are recorded with the token counts the provider reports. Streams, embeddings
+/// and completions of providers reporting nothing are estimated with the provider's
+/// [`TokenEstimator`], as rig does not pass the counts on, and recorded as estimated.
+#[derive(Clone)]
+pub struct MeteredProvider {
+    inner: Arc<dyn LLMProvider>,
+    kind: LLMProviders,
+    ledger: Arc<UsageLedger>,
+    session_id: Option<SessionId>,
+}
+
+impl MeteredProvider {
+    /// Meters `inner`, a provider of the given kind, attributing its calls to `session_id`.
+    pub fn new(
+        inner: Arc<dyn LLMProvider>,
+        kind: LLMProviders,
+        ledger: Arc<UsageLedger>,
+        session_id: Option<SessionId>,
+    ) -> Self {
+        Self {
+            inner,
+            kind,
+            ledger,
+            session_id,
+        }
+    }
+
+    fn call(&self, kind: CallKind, model: &str, input_tokens: usize) -> Call {
+        Call {
+            ledger: self.ledger.clone(),
+            record: UsageRecord {
+                timestamp: Utc::now(),
+                session_id: self.session_id,
+                provider: self.kind.clone(),
+                model: model.to_string(),
+                kind,
+                input_tokens: input_tokens as u64,
+                output_tokens: 0,
+                latency_ms: 0,
+                cost: None,
+                failed: false,
+                estimated: true,
+            },
+            started: Instant::now(),
+        }
+    }
+}
+
+/// A call being made, recorded once it finishes.
+struct Call {
+    ledger: Arc<UsageLedger>,
+    record: UsageRecord,
+    started: Instant,
+}
+
+impl Call {
+    fn finish(mut self, output_tokens: usize, failed: bool) {
+        self.record.output_tokens = output_tokens as u64;
+        self.record.failed = failed;
+        self.record.latency_ms = self.started.elapsed().as_millis() as u64;
+        self.ledger.record(self.record);
+    }
+
+    /// Records a finished completion with the tokens the provider reports, or estimates them.
+    fn finish_completion(mut self, estimator: &TokenEstimator, completion: &Completion) {
+        if let Some(usage) = completion.usage {
+            self.record.input_tokens = usage.input_tokens;
+            self.record.estimated = false;
+            return self.finish(usage.output_tokens as usize, false);
+        }
+        let output = completion
+            .choice
+            .iter()
+            .map(|content| estimator.estimate_content(content))
+            .sum();
+        self.finish(output, false);
+    }
+}
+
+/// Counts the text of a stream, recording the call when the stream is dropped, whether it
+/// was read to the end or cancelled.
+struct StreamMeter {
+    call: Option<Call>,
+    estimator: TokenEstimator,
+    output: String,
+    failed: bool,
+}
+
+impl Drop for StreamMeter {
+    fn drop(&mut self) {
+        if let Some(call) = self.call.take() {
+            call.finish(self.estimator.estimate(&self.output), self.failed);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for MeteredProvider {
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn embedding_model(&self) -> Option<&str> {
+        self.inner.embedding_model()
+    }
+
+    fn token_estimator(&self) -> TokenEstimator {
+        self.inner.token_estimator()
+    }
+
+    async fn context_window(&self) -> Result<Option<u64>> {
+        self.inner.context_window().await
+    }
+
+    async fn complete(&self, request: CompletionRequest) -> Result<Completion> {
+        let estimator = self.token_estimator();
+        let call = self.call(
+            CallKind::Completion,
+            self.model(),
+            estimator.estimate_request(&request),
+        );
+        let result = self.inner.complete(request).await;
+        match &result {
+            Ok(completion) => call.finish_completion(&estimator, completion),
+            Err(_) => call.finish(0, true),
+        }
+        result
+    }
+
+    async fn complete_json(
+        &self,
+        request: CompletionRequest,
+        schema: &Value,
+    ) -> Result<Completion> {
+        let estimator = self.token_estimator();
+        let call = self.call(
+            CallKind::Completion,
+            self.model(),
+            estimator.estimate_request(&request),
+        );
+        let result = self.inner.complete_json(request, schema).await;
+        match &result {
+            Ok(completion) => call.finish_completion(&estimator, completion),
+            Err(_) => call.finish(0, true),
+        }
+        result
+    }
+
+    async fn generate_stream(&self, request: CompletionRequest) -> Result<LLMStream> {
+        let estimator = self.token_estimator();
+        let call = self.call(
+            CallKind::Stream,
+            self.model(),
+            estimator.estimate_request(&request),
+        );
+        let stream = match self.inner.generate_stream(request).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                call.finish(0, true);
+                return Err(e);
+            }
+        };
+
+        let mut meter = StreamMeter {
+            call: Some(call),
+            estimator,
+            output: String::new(),
+            failed: false,
+        };
+        let stream = stream.map(move |chunk| {
+            match &chunk {
+                Ok(StreamingChoice::Message(text)) => meter.output.push_str(text),
+                Ok(StreamingChoice::ToolCall(name, _, arguments)) => {
+                    meter.output.push_str(name);
+                    meter.output.push_str(&arguments.to_string());
+                }
+                Err(_) => meter.failed = true,
+            }
+            chunk
+        });
+        Ok(Box::pin(stream) as LLMStream)
+    }
+
+    async fn get_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.get_models().await
+    }
+
+    async fn probe(&self, role: ProviderRole) -> Result<()> {
+        self.inner.probe(role).await
+    }
+
+    async fn generate_embeddings(&self, inputs: Vec<EmbeddingType>) -> Result<Vec<Vec<f32>>> {
+        let estimator = self.token_estimator();
+        let input = inputs
+            .iter()
+            .map(|input| match input {
+                EmbeddingType::Text(text) => estimator.estimate(text),
+                EmbeddingType::Document(document) => estimator.estimate(&document.text),
+            })
+            .sum();
+        let model = self.embedding_model().unwrap_or_default().to_string();
+        let call = self.call(CallKind::Embedding, &model, input);
+        let result = self.inner.generate_embeddings(inputs).await;
+        call.finish(0, result.is_err());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use rig::message::Message;
+
+    use super::*;
+    use crate::llm::{MockProvider, MockReply};
+
+    #[async_std::test]
+    async fn test_metered_calls_are_recorded() {
+        let ledger = Arc::new(UsageLedger::in_memory(UsageConfig::default()));
+        let inner: Arc<dyn LLMProvider> = Arc::new(MockProvider::scripted([
+            MockReply::text("Hello there").with_usage(12, 2),
+            MockReply::text("A streamed answer"),
+        ]));
+        let session = SessionId::new_v4();
+        let provider =
+            MeteredProvider::new(inner, LLMProviders::Mock, ledger.clone(), Some(session));
+
+        provider
+            .generate(provider.create_prompt(Message::user("Hi")))
+            .await
+            .unwrap();
+        let mut stream = provider
+            .generate_stream(provider.create_prompt(Message::user("Stream it")))
+            .await
+            .unwrap();
+        // Only part of the stream is read before it is dropped, as when cancelled
+        stream.next().await;
+        drop(stream);
+        assert!(
+            provider
+                .generate(provider.create_prompt(Message::user("Hi")))
+                .await
+                .is_err()
+        );
+
+        let records = ledger.records().unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].kind, CallKind::Completion);
+        assert_eq!((records[0].input_tokens, records[0].output_tokens), (12, 2));
+        assert!(!records[0].estimated);
+        assert_eq!(records[1].kind, CallKind::Stream);
+        assert!(records[1].output_tokens > 0 && records[1].estimated);
+        assert!(records[2].failed);
+        assert!(
+            records
+                .iter()
+                .all(|record| record.session_id == Some(session))
+        );
+    }
+}
@@ -3,8 +3,7 @@ use crate::llm::LLMProvider;
 use crate::memory::error::MemoryError;
 use crate::prelude::*;
 
-/// Generates embeddings for text using the specified LLM provider.
-/// Generates embeddings for text using the specified LLM provider.
+/// Generates the embedding vector of a text using the specified LLM provider.
 pub async fn generate_embedding(text: &str, llm_provider: &dyn LLMProvider) -> Result<Vec<f32>> {
     debug!(
         "Generating embedding for text: '{}'",
@@ -15,13 +14,12 @@ pub async fn generate_embedding(text: &str, llm_provider: &dyn LLMProvider) -> R
         .generate_embedding(EmbeddingType::Text(text.to_string()))
         .await
     {
-        Ok(()) => {
-            // TODO: Implement proper embedding response handling
-            Err(Error::Memory(MemoryError::Embedding(
+        Ok(vectors) => vectors.into_iter().next().ok_or_else(|| {
+            Error::Memory(MemoryError::Embedding(
                 "text embedding".to_string(),
-                "Embedding generation not fully implemented".to_string(),
-            )))
-        }
+                "Provider returned no vectors".to_string(),
+            ))
+        }),
         Err(e) => {
             error!("Failed to generate embedding: {}", e);
             Err(Error::Memory(MemoryError::Embedding(
@@ -31,30 +29,3 @@ pub async fn generate_embedding(text: &str, llm_provider: &dyn LLMProvider) -> R
         }
     }
 }
-
-/// Utility function to compute cosine similarity between two vectors.
-pub fn cosine_similarity(v1: &[f32], v2: &[f32]) -> Result<f32> {
-    if v1.len() != v2.len() {
-        return Err(Error::Memory(MemoryError::DataProcessing(format!(
-            "Vectors must have the same length ({} != {})",
-            v1.len(),
-            v2.len()
-        ))));
-    }
-
-    if v1.is_empty() {
-        return Err(Error::Memory(MemoryError::DataProcessing(
-            "Vectors cannot be empty".to_string(),
-        )));
-    }
-
-    let dot_product: f32 = v1.iter().zip(v2.iter()).map(|(a, b)| a * b).sum();
-    let norm1: f32 = v1.iter().map(|x| x * x).sum::<f32>().sqrt();
-    let norm2: f32 = v2.iter().map(|x| x * x).sum::<f32>().sqrt();
-
-    if norm1 == 0.0 || norm2 == 0.0 {
-        return Ok(0.0);
-    }
-
-    Ok((dot_product / (norm1 * norm2)).clamp(-1.0, 1.0))
-}
@@ -0,0 +1,95 @@
+//! Turns finished exchanges into long-term memories.
+
+use std::sync::Arc;
+
+use crate::{
+    core::SessionId,
+    llm::LLMProvider,
+    memory::{
+        MemoryId, MemoryRecord, MemoryStore, Retriever, ScoredMemory,
+        embedding::generate_embedding, summarize_interaction,
+    },
+    prelude::*,
+    templates::{PromptTemplate, PromptTemplates},
+};
+
+/// Summarizes exchanges into a memory store and recalls the ones relevant to new prompts.
+pub struct MemoryManager {
+    summarizer: Arc<dyn LLMProvider>,
+    embedder: Arc<dyn LLMProvider>,
+    store: Arc<dyn MemoryStore>,
+    retriever: Retriever,
+    summary_template: PromptTemplate,
+}
+
+impl MemoryManager {
+    pub fn new(
+        summarizer: Arc<dyn LLMProvider>,
+        embedder: Arc<dyn LLMProvider>,
+        store: Arc<dyn MemoryStore>,
+        retriever: Retriever,
+    ) -> Self {
+        Self {
+            summarizer,
+            embedder,
+            store,
+            retriever,
+            summary_template: PromptTemplates::default().summarization,
+        }
+    }
+
+    /// Asks for summaries with `template` instead of the built-in summarization template.
+    pub fn with_summary_template(mut self, template: PromptTemplate) -> Self {
+        self.summary_template = template;
+        self
+    }
+
+    /// Returns the stored memories relevant to a prompt, best match first.
+    pub async fn recall(&self, prompt: &str) -> Result<Vec<ScoredMemory>> {
+        if !self.retriever.is_enabled() {
+            return Ok(Vec::new());
+        }
+
+        let query = generate_embedding(prompt, &*self.embedder).await?;
+        let memories = self.retriever.retrieve(&query).await?;
+        debug!("Recalled {} memories for prompt", memories.len());
+        Ok(memories)
+    }
+
+    /// Summarizes an exchange and stores the summary, tagged with where it came from.
+    pub async fn remember(
+        &self,
+        session_id: SessionId,
+        source_turn: usize,
+        user_prompt: &str,
+        llm_response: &str,
+    ) -> Result<MemoryRecord> {
+        let summary = summarize_interaction(
+            &*self.summarizer,
+            &self.summary_template,
+            user_prompt,
+            llm_response,
+        )
+        .await?;
+        let vector = generate_embedding(&summary, &*self.embedder).await?;
+
+        let record = MemoryRecord::new(summary, session_id, source_turn);
+        self.store.upsert(&record, vector).await?;
+
+        info!(
+            "Remembered turn {} of session {} as memory {}",
+            source_turn, session_id, record.id
+        );
+        Ok(record)
+    }
+
+    /// Returns all stored memories, oldest first.
+    pub async fn list(&self) -> Result<Vec<MemoryRecord>> {
+        self.store.list().await
+    }
+
+    /// Deletes a stored memory.
+    pub async fn forget(&self, id: MemoryId) -> Result<()> {
+        self.store.delete(id).await
+    }
+}
@@ -3,7 +3,17 @@
 pub mod error;
 
 mod embedding;
+mod manager;
+mod record;
+mod retrieval;
+mod store;
 mod summarizer;
 
 pub use error::MemoryError;
-pub use summarizer::summarize_interaction;
+pub use manager::MemoryManager;
+pub use record::{MemoryId, MemoryRecord};
+pub use retrieval::{RetrievalConfig, Retriever};
+pub use store::{
+    FileMemoryStore, InMemoryStore, MemoryStore, QdrantMemory, ScoredMemory, open_store,
+};
+pub use summarizer::{summarize_conversation, summarize_interaction};
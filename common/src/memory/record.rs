@@ -0,0 +1,36 @@
+//! The unit of long-term memory.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::core::SessionId;
+
+/// Identifier of a stored memory.
+pub type MemoryId = Uuid;
+
+/// A summary of an exchange, stored alongside its embedding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryRecord {
+    pub id: MemoryId,
+    /// The summarized interaction
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+    /// Conversation the interaction belongs to
+    pub session_id: SessionId,
+    /// Index of the summarized exchange within its conversation
+    pub source_turn: usize,
+}
+
+impl MemoryRecord {
+    /// Creates a record for a summary of the given exchange.
+    pub fn new(content: impl Into<String>, session_id: SessionId, source_turn: usize) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            content: content.into(),
+            created_at: Utc::now(),
+            session_id,
+            source_turn,
+        }
+    }
+}
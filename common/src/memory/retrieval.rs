@@ -0,0 +1,126 @@
+//! Selection of stored memories relevant to a prompt.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    memory::{MemoryStore, ScoredMemory},
+    prelude::*,
+};
+
+/// How memories are retrieved and injected into prompts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetrievalConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Maximum number of memories considered per prompt
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+
+    /// Minimum similarity for a memory to be used
+    #[serde(default = "default_min_score")]
+    pub min_score: f32,
+}
+
+impl Default for RetrievalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            top_k: default_top_k(),
+            min_score: default_min_score(),
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_top_k() -> usize {
+    5
+}
+
+fn default_min_score() -> f32 {
+    0.6
+}
+
+/// Finds the memories of a store that are relevant to a query embedding.
+pub struct Retriever {
+    store: Arc<dyn MemoryStore>,
+    config: RetrievalConfig,
+}
+
+impl Retriever {
+    pub fn new(store: Arc<dyn MemoryStore>, config: RetrievalConfig) -> Self {
+        Self { store, config }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled && self.config.top_k > 0
+    }
+
+    /// Returns the best matching memories above the relevance threshold, best match first.
+    ///
+    /// How many of them fit the prompt is left to the context budget, which counts them with
+    /// the model's own estimator.
+    pub async fn retrieve(&self, query: &[f32]) -> Result<Vec<ScoredMemory>> {
+        if !self.is_enabled() {
+            return Ok(Vec::new());
+        }
+
+        let candidates = self.store.search(query, self.config.top_k).await?;
+        Ok(candidates
+            .into_iter()
+            .filter(|memory| memory.score >= self.config.min_score)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::memory::{InMemoryStore, MemoryRecord};
+
+    async fn store_with(memories: &[(&str, Vec<f32>)]) -> Arc<InMemoryStore> {
+        let store = Arc::new(InMemoryStore::new());
+        for (turn, (content, vector)) in memories.iter().enumerate() {
+            let record = MemoryRecord::new(*content, Uuid::new_v4(), turn);
+            store.upsert(&record, vector.clone()).await.unwrap();
+        }
+        store
+    }
+
+    #[async_std::test]
+    async fn test_retrieve_orders_and_filters_by_relevance() {
+        let store = store_with(&[
+            ("User likes tea", vec![1.0, 0.0]),
+            ("User has a cat named Miso", vec![0.0, 1.0]),
+            ("User drinks green tea daily", vec![0.9, 0.1]),
+        ])
+        .await;
+        let retriever = Retriever::new(store, RetrievalConfig::default());
+
+        let memories = retriever.retrieve(&[1.0, 0.0]).await.unwrap();
+        let contents = memories
+            .iter()
+            .map(|m| m.record.content.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(contents, ["User likes tea", "User drinks green tea daily"]);
+    }
+
+    #[async_std::test]
+    async fn test_disabled_retrieval_returns_nothing() {
+        let store = store_with(&[("User likes tea", vec![1.0, 0.0])]).await;
+        let config = RetrievalConfig {
+            enabled: false,
+            ..RetrievalConfig::default()
+        };
+
+        let retriever = Retriever::new(store, config);
+        assert!(retriever.retrieve(&[1.0, 0.0]).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+//! A memory store persisted as a file in the user's data directory.
+
+use std::{fs, path::PathBuf};
+
+use async_std::sync::RwLock;
+
+use super::{MemoryStore, ScoredMemory, StoredMemory, rank, records};
+use crate::{
+    config,
+    memory::{MemoryError, MemoryId, MemoryRecord},
+    prelude::*,
+};
+
+const MEMORY_FILE_NAME: &str = "memories.json";
+
+/// Keeps all records in memory, searches them by brute force and writes them back to a JSON
+/// file after every change, so memory works without any external service.
+pub struct FileMemoryStore {
+    path: PathBuf,
+    entries: RwLock<Vec<StoredMemory>>,
+}
+
+impl FileMemoryStore {
+    /// Opens the store at `<data dir>/memories.json`.
+    pub fn open_default() -> Result<Self> {
+        Self::open(config::get_data_dir()?.join(MEMORY_FILE_NAME))
+    }
+
+    /// Opens the store backed by the given file, which is created on the first change.
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let entries = if path.exists() {
+            let contents = fs::read_to_string(&path)?;
+            serde_json::from_str(&contents).map_err(|e| {
+                MemoryError::DataProcessing(f!("Unreadable memory file {}: {}", path.display(), e))
+            })?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path,
+            entries: RwLock::new(entries),
+        })
+    }
+
+    /// Writes the entries to a temporary file first so a crash never leaves a truncated store.
+    fn persist(&self, entries: &[StoredMemory]) -> Result<()> {
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string(entries)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MemoryStore for FileMemoryStore {
+    async fn upsert(&self, record: &MemoryRecord, vector: Vec<f32>) -> Result<()> {
+        let mut entries = self.entries.write().await;
+        entries.retain(|entry| entry.record.id != record.id);
+        entries.push(StoredMemory {
+            record: record.clone(),
+            vector,
+        });
+        self.persist(&entries)
+    }
+
+    async fn search(&self, vector: &[f32], limit: usize) -> Result<Vec<ScoredMemory>> {
+        rank(&self.entries.read().await, vector, limit)
+    }
+
+    async fn delete(&self, id: MemoryId) -> Result<()> {
+        let mut entries = self.entries.write().await;
+        let count = entries.len();
+        entries.retain(|entry| entry.record.id != id);
+        if entries.len() != count {
+            self.persist(&entries)?;
+        }
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<MemoryRecord>> {
+        Ok(records(&self.entries.read().await))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::utils::temp_dir;
+
+    #[async_std::test]
+    async fn test_memories_persist_between_opens() {
+        let dir = temp_dir();
+        let path = dir.path().join(MEMORY_FILE_NAME);
+
+        let store = FileMemoryStore::open(path.clone()).unwrap();
+        let tea = MemoryRecord::new("User likes tea", Uuid::new_v4(), 0);
+        let cat = MemoryRecord::new("User has a cat", Uuid::new_v4(), 1);
+        store.upsert(&tea, vec![1.0, 0.0]).await.unwrap();
+        store.upsert(&cat, vec![0.0, 1.0]).await.unwrap();
+
+        let reopened = FileMemoryStore::open(path.clone()).unwrap();
+        assert_eq!(
+            reopened.list().await.unwrap(),
+            vec![tea.clone(), cat.clone()]
+        );
+
+        let results = reopened.search(&[0.1, 0.9], 1).await.unwrap();
+        assert_eq!(results[0].record, cat);
+
+        reopened.delete(cat.id).await.unwrap();
+        let reopened = FileMemoryStore::open(path).unwrap();
+        assert_eq!(reopened.list().await.unwrap(), vec![tea]);
+    }
+}
@@ -0,0 +1,50 @@
+//! A memory store that lives only as long as the process.
+
+use async_std::sync::RwLock;
+
+use super::{MemoryStore, ScoredMemory, StoredMemory, rank, records};
+use crate::{
+    memory::{MemoryId, MemoryRecord},
+    prelude::*,
+};
+
+/// Keeps records in memory and searches them by brute force.
+#[derive(Default)]
+pub struct InMemoryStore {
+    entries: RwLock<Vec<StoredMemory>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl MemoryStore for InMemoryStore {
+    async fn upsert(&self, record: &MemoryRecord, vector: Vec<f32>) -> Result<()> {
+        let mut entries = self.entries.write().await;
+        entries.retain(|entry| entry.record.id != record.id);
+        entries.push(StoredMemory {
+            record: record.clone(),
+            vector,
+        });
+        Ok(())
+    }
+
+    async fn search(&self, vector: &[f32], limit: usize) -> Result<Vec<ScoredMemory>> {
+        rank(&self.entries.read().await, vector, limit)
+    }
+
+    async fn delete(&self, id: MemoryId) -> Result<()> {
+        self.entries
+            .write()
+            .await
+            .retain(|entry| entry.record.id != id);
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<MemoryRecord>> {
+        Ok(records(&self.entries.read().await))
+    }
+}
@@ -0,0 +1,85 @@
+//! Storage backends for memory records and their embeddings.
+
+mod file;
+mod in_memory;
+mod qdrant;
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    llm::{MemoryBackend, VectorDbConfig},
+    memory::{MemoryId, MemoryRecord},
+    prelude::*,
+    utils::cosine_similarity,
+};
+pub use file::FileMemoryStore;
+pub use in_memory::InMemoryStore;
+pub use qdrant::QdrantMemory;
+
+/// A stored memory together with its similarity to a search query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoredMemory {
+    pub record: MemoryRecord,
+    /// Cosine similarity to the query, between -1.0 and 1.0
+    pub score: f32,
+}
+
+/// A vector store holding memory records.
+#[async_trait::async_trait]
+pub trait MemoryStore: Send + Sync {
+    /// Stores a record with its embedding, replacing any record with the same id.
+    async fn upsert(&self, record: &MemoryRecord, vector: Vec<f32>) -> Result<()>;
+
+    /// Returns up to `limit` records closest to the query vector, best match first.
+    async fn search(&self, vector: &[f32], limit: usize) -> Result<Vec<ScoredMemory>>;
+
+    /// Removes a record. Removing a record that does not exist is not an error.
+    async fn delete(&self, id: MemoryId) -> Result<()>;
+
+    /// Returns all stored records, oldest first.
+    async fn list(&self) -> Result<Vec<MemoryRecord>>;
+}
+
+/// Opens the memory store selected by the configuration.
+pub fn open_store(config: &VectorDbConfig) -> Result<Arc<dyn MemoryStore>> {
+    match config.backend {
+        MemoryBackend::Local => Ok(Arc::new(FileMemoryStore::open_default()?)),
+        MemoryBackend::Qdrant => Ok(Arc::new(QdrantMemory::new(config)?)),
+    }
+}
+
+/// A record and its embedding as held by the brute-force stores.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredMemory {
+    record: MemoryRecord,
+    vector: Vec<f32>,
+}
+
+/// Scores every entry against the query and returns the best `limit` matches.
+fn rank(entries: &[StoredMemory], query: &[f32], limit: usize) -> Result<Vec<ScoredMemory>> {
+    let mut scored = entries
+        .iter()
+        .map(|entry| {
+            cosine_similarity(query, &entry.vector).map(|score| ScoredMemory {
+                record: entry.record.clone(),
+                score,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scored.truncate(limit);
+    Ok(scored)
+}
+
+/// Returns the records of the entries, oldest first.
+fn records(entries: &[StoredMemory]) -> Vec<MemoryRecord> {
+    let mut records = entries
+        .iter()
+        .map(|entry| entry.record.clone())
+        .collect::<Vec<_>>();
+    records.sort_by_key(|record| record.created_at);
+    records
+}
@@ -0,0 +1,175 @@
+//! Qdrant-backed storage of memory records.
+
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use qdrant_client::{
+    Payload, Qdrant,
+    qdrant::{
+        CreateCollectionBuilder, DeletePointsBuilder, Distance, PointStruct, ScrollPointsBuilder,
+        SearchPointsBuilder, UpsertPointsBuilder, Value, VectorParamsBuilder,
+    },
+};
+
+use super::{MemoryStore, ScoredMemory};
+use crate::{
+    llm::VectorDbConfig,
+    memory::{MemoryError, MemoryId, MemoryRecord},
+    prelude::*,
+};
+
+const SCROLL_PAGE_SIZE: u32 = 256;
+
+/// Stores memory records and their embeddings in the collection described by [`VectorDbConfig`].
+pub struct QdrantMemory {
+    client: Qdrant,
+    collection_name: String,
+    vector_size: u64,
+    collection_ready: AtomicBool,
+}
+
+impl QdrantMemory {
+    /// Creates a client for the configured Qdrant instance.
+    ///
+    /// No connection is made until the store is first used.
+    pub fn new(config: &VectorDbConfig) -> Result<Self> {
+        let client = Qdrant::from_url(config.url.as_str())
+            .build()
+            .map_err(|e| MemoryError::Connection(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            collection_name: config.collection_name.clone(),
+            vector_size: config.vector_size,
+            collection_ready: AtomicBool::new(false),
+        })
+    }
+
+    /// Creates the collection on first use if it does not exist yet.
+    async fn ensure_collection(&self) -> Result<()> {
+        if self.collection_ready.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        let exists = self
+            .client
+            .collection_exists(&self.collection_name)
+            .await
+            .map_err(|e| MemoryError::Connection(e.to_string()))?;
+
+        if !exists {
+            info!("Creating memory collection '{}'", self.collection_name);
+            self.client
+                .create_collection(
+                    CreateCollectionBuilder::new(&self.collection_name).vectors_config(
+                        VectorParamsBuilder::new(self.vector_size, Distance::Cosine),
+                    ),
+                )
+                .await
+                .map_err(|e| MemoryError::Collection(e.to_string()))?;
+        }
+
+        self.collection_ready.store(true, Ordering::Release);
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MemoryStore for QdrantMemory {
+    async fn upsert(&self, record: &MemoryRecord, vector: Vec<f32>) -> Result<()> {
+        self.ensure_collection().await?;
+
+        let payload = Payload::try_from(serde_json::to_value(record)?)
+            .map_err(|e| MemoryError::DataProcessing(e.to_string()))?;
+        let point = PointStruct::new(record.id.to_string(), vector, payload);
+
+        self.client
+            .upsert_points(UpsertPointsBuilder::new(&self.collection_name, vec![point]).wait(true))
+            .await
+            .map_err(|e| MemoryError::Collection(e.to_string()))?;
+
+        debug!(
+            "Stored memory {} in collection '{}'",
+            record.id, self.collection_name
+        );
+        Ok(())
+    }
+
+    async fn search(&self, vector: &[f32], limit: usize) -> Result<Vec<ScoredMemory>> {
+        self.ensure_collection().await?;
+
+        let response = self
+            .client
+            .search_points(
+                SearchPointsBuilder::new(&self.collection_name, vector, limit as u64)
+                    .with_payload(true),
+            )
+            .await
+            .map_err(|e| MemoryError::Collection(e.to_string()))?;
+
+        response
+            .result
+            .into_iter()
+            .map(|point| {
+                Ok(ScoredMemory {
+                    record: parse_record(point.payload)?,
+                    score: point.score,
+                })
+            })
+            .collect()
+    }
+
+    async fn delete(&self, id: MemoryId) -> Result<()> {
+        self.ensure_collection().await?;
+
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(&self.collection_name)
+                    .points(vec![id.to_string()])
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| MemoryError::Collection(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<MemoryRecord>> {
+        self.ensure_collection().await?;
+
+        let mut records = Vec::new();
+        let mut offset = None;
+        loop {
+            let mut request = ScrollPointsBuilder::new(&self.collection_name)
+                .limit(SCROLL_PAGE_SIZE)
+                .with_payload(true);
+            if let Some(offset) = offset {
+                request = request.offset(offset);
+            }
+
+            let response = self
+                .client
+                .scroll(request)
+                .await
+                .map_err(|e| MemoryError::Collection(e.to_string()))?;
+            for point in response.result {
+                records.push(parse_record(point.payload)?);
+            }
+
+            match response.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        records.sort_by_key(|record| record.created_at);
+        Ok(records)
+    }
+}
+
+fn parse_record(payload: HashMap<String, Value>) -> Result<MemoryRecord> {
+    Payload::from(payload)
+        .deserialize::<MemoryRecord>()
+        .map_err(|e| Error::Memory(MemoryError::DataProcessing(e.to_string())))
+}
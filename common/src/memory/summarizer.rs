@@ -1,27 +1,72 @@
 //! Logic for summarizing interactions using an LLM.
 
-use crate::{llm::LLMProvider, memory::error::MemoryError, prelude::*};
+use rig::completion::Message;
 
-/// Summarizes a user prompt and the corresponding LLM response.
+use crate::{
+    core::message_text,
+    llm::LLMProvider,
+    memory::error::MemoryError,
+    prelude::*,
+    templates::{ASSISTANT, CONVERSATION, DATE, PromptTemplate, SUMMARY, USER, today},
+};
+
+/// Summarizes a user prompt and the corresponding LLM response, asking for the summary with
+/// the given summarization template.
 // Simplified signature to accept any type implementing LLMProvider directly.
 // Added Sync bound as generate is async and called across .await
 pub async fn summarize_interaction(
     llm_provider: &(dyn LLMProvider + Sync), // Accept trait object directly
+    template: &PromptTemplate,
     user_prompt: &str,
     llm_response: &str,
 ) -> Result<String> {
-    // TODO: Make this prompt template configurable (LYN-6 requirement)
-    let summarization_prompt_content = f!(
-        "Summarize the following interaction concisely:\n\nUser: {}\nAssistant: {}\n\nSummary:",
-        user_prompt,
-        llm_response
-    );
+    let summarization_prompt_content = template.render(&[
+        (USER, user_prompt),
+        (ASSISTANT, llm_response),
+        (DATE, &today()),
+    ]);
 
     info!("Requesting summarization from LLM...");
-    debug!("Summarization prompt: {:?}", summarization_prompt_content);
+    summarize(llm_provider, summarization_prompt_content).await
+}
+
+/// Folds messages of a conversation into its running summary, asking for the new summary with
+/// the given compaction template.
+pub async fn summarize_conversation(
+    llm_provider: &(dyn LLMProvider + Sync),
+    template: &PromptTemplate,
+    previous_summary: Option<&str>,
+    messages: &[Message],
+) -> Result<String> {
+    let conversation = messages
+        .iter()
+        .filter_map(|message| {
+            let role = match message {
+                Message::User { .. } => "User",
+                Message::Assistant { .. } => "Assistant",
+            };
+            message_text(message).map(|text| f!("{}: {}", role, text))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = template.render(&[
+        (CONVERSATION, &conversation),
+        (SUMMARY, previous_summary.unwrap_or("Nothing yet.")),
+        (DATE, &today()),
+    ]);
+
+    info!(
+        "Requesting a summary of {} message(s) from LLM...",
+        messages.len()
+    );
+    summarize(llm_provider, prompt).await
+}
+
+async fn summarize(llm_provider: &(dyn LLMProvider + Sync), prompt: String) -> Result<String> {
+    debug!("Summarization prompt: {:?}", prompt);
 
     // Use llm_provider directly, no need for as_ref()
-    let request = llm_provider.create_prompt(summarization_prompt_content.into());
+    let request = llm_provider.create_prompt(prompt.into());
     match llm_provider.generate(request).await {
         Ok(response) => {
             info!("Summarization successful.");
@@ -37,3 +82,30 @@ pub async fn summarize_interaction(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        llm::{MockProvider, MockReply},
+        templates::PromptTemplates,
+    };
+
+    #[async_std::test]
+    async fn test_summarize_interaction() {
+        let provider = MockProvider::scripted([MockReply::text("User greeted Lyn")]);
+        let template = PromptTemplates::default().summarization;
+
+        let summary = summarize_interaction(&provider, &template, "Hello!", "Hi there")
+            .await
+            .unwrap();
+        assert_eq!(summary, "User greeted Lyn");
+        let prompts = provider.prompts().await;
+        assert!(prompts[0].contains("User: Hello!\nAssistant: Hi there"));
+
+        assert!(matches!(
+            summarize_interaction(&provider, &template, "Hello!", "Hi there").await,
+            Err(Error::Memory(MemoryError::Summarization(_)))
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+//! Prompt templates filled with values such as the exchange being summarized or the
+//! memories recalled for a prompt.
+//!
+//! Templates are plain text where `{{name}}` is replaced by the value of a variable. Each
+//! kind of template only has access to some variables, and a template using any other one is
+//! rejected when the configuration is loaded. The built-in templates can be overridden by
+//! files in the `templates` directory of the configuration directory.
+
+use std::{fs, io::ErrorKind, path::Path};
+
+use chrono::Local;
+use rig::completion::ToolDefinition;
+
+use crate::{config::ConfigError, memory::ScoredMemory, prelude::*};
+
+/// The user's message.
+pub const USER: &str = "user";
+/// The assistant's answer.
+pub const ASSISTANT: &str = "assistant";
+/// Today's date.
+pub const DATE: &str = "date";
+/// The memories recalled for a prompt, one per line.
+pub const MEMORIES: &str = "memories";
+/// Messages of a conversation, one `Role: text` line each.
+pub const CONVERSATION: &str = "conversation";
+/// The running summary of a conversation so far.
+pub const SUMMARY: &str = "summary";
+/// The tools offered to the model for a prompt, one `- name: description` line each.
+pub const TOOLS: &str = "tools";
+
+/// Variables available to the summarization template.
+pub const SUMMARIZATION_VARIABLES: &[&str] = &[USER, ASSISTANT, DATE];
+/// Variables available to the template introducing recalled memories.
+pub const MEMORIES_VARIABLES: &[&str] = &[USER, MEMORIES, DATE];
+/// Variables available to the template folding old messages into a conversation's summary.
+pub const COMPACTION_VARIABLES: &[&str] = &[CONVERSATION, SUMMARY, DATE];
+/// Variables available to persona preambles.
+pub const PREAMBLE_VARIABLES: &[&str] = &[MEMORIES, DATE, TOOLS];
+
+pub(crate) const DEFAULT_PREAMBLE: &str = "You are Lyn, a personal assistant that runs on the \
+user's own machine. What the user shares is private: do not repeat personal details unless \
+asked to, and never suggest sending them to third parties. Say so when you are unsure.\n\n\
+Use these tools rather than guessing whenever an answer depends on something they can \
+compute or look up:\n{{tools}}";
+
+const DEFAULT_SUMMARIZATION: &str = "Summarize the following interaction concisely:\n\n\
+User: {{user}}\nAssistant: {{assistant}}\n\nSummary:";
+
+const DEFAULT_COMPACTION: &str = "Update the summary of a conversation with the messages \
+that follow it. Keep names, facts, decisions and open questions, and leave out small talk.\n\n\
+Summary so far:\n{{summary}}\n\nMessages:\n{{conversation}}\n\nUpdated summary:";
+
+const DEFAULT_MEMORIES: &str = "You remember the following from earlier conversations with \
+the user. Use it when it helps to answer, and ignore it otherwise:\n{{memories}}";
+
+/// A validated template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    text: String,
+    // Variables the template uses, in order
+    variables: Vec<String>,
+}
+
+impl PromptTemplate {
+    /// Parses a template, rejecting variables missing from `variables` and unclosed `{{`.
+    pub fn parse(name: &str, text: impl Into<String>, variables: &[&str]) -> Result<Self> {
+        let text = text.into();
+        let used = referenced_variables(&text).map_err(|e| invalid(name, &e))?;
+        if let Some(variable) = used.iter().find(|used| !variables.contains(used)) {
+            return Err(invalid(
+                name,
+                &f!(
+                    "unknown variable `{{{{{}}}}}`, expected one of: {}",
+                    variable,
+                    variables.join(", ")
+                ),
+            ));
+        }
+        let variables = used.into_iter().map(String::from).collect();
+        Ok(Self { text, variables })
+    }
+
+    /// A built-in template, valid by construction.
+    pub(crate) fn builtin(name: &str, text: &str, variables: &[&str]) -> Self {
+        Self::parse(name, text, variables).expect("built-in templates are valid")
+    }
+
+    /// Replaces each variable with its value; variables without a value become empty.
+    pub fn render(&self, values: &[(&str, &str)]) -> String {
+        let mut rendered = String::with_capacity(self.text.len());
+        let mut rest = self.text.as_str();
+        while let Some(start) = rest.find("{{") {
+            rendered.push_str(&rest[..start]);
+            // Validated templates close every variable
+            let end = rest[start..]
+                .find("}}")
+                .map_or(rest.len(), |end| start + end);
+            let name = rest[start + 2..end].trim();
+            if let Some((_, value)) = values.iter().find(|(variable, _)| *variable == name) {
+                rendered.push_str(value);
+            }
+            rest = rest.get(end + 2..).unwrap_or_default();
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+
+    /// Whether the template uses the given variable.
+    pub fn uses(&self, variable: &str) -> bool {
+        self.variables.iter().any(|used| used == variable)
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// The templates used to build prompts, built-in unless overridden in the config directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplates {
+    /// Asks the model to summarize an exchange into a memory (`summarization.txt`)
+    pub summarization: PromptTemplate,
+    /// Introduces the recalled memories to the model (`memories.txt`)
+    pub memories: PromptTemplate,
+    /// Folds the oldest messages of a long conversation into its summary (`compaction.txt`)
+    pub compaction: PromptTemplate,
+    /// Preamble of the built-in persona (`preamble.txt`)
+    pub preamble: PromptTemplate,
+}
+
+impl Default for PromptTemplates {
+    fn default() -> Self {
+        Self {
+            summarization: PromptTemplate::builtin(
+                "summarization",
+                DEFAULT_SUMMARIZATION,
+                SUMMARIZATION_VARIABLES,
+            ),
+            memories: PromptTemplate::builtin("memories", DEFAULT_MEMORIES, MEMORIES_VARIABLES),
+            compaction: PromptTemplate::builtin(
+                "compaction",
+                DEFAULT_COMPACTION,
+                COMPACTION_VARIABLES,
+            ),
+            preamble: PromptTemplate::builtin("preamble", DEFAULT_PREAMBLE, PREAMBLE_VARIABLES),
+        }
+    }
+}
+
+impl PromptTemplates {
+    /// Loads the templates found in `dir`, keeping the built-in ones for missing files.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let defaults = Self::default();
+        Ok(Self {
+            summarization: load_template(dir, "summarization", SUMMARIZATION_VARIABLES)?
+                .unwrap_or(defaults.summarization),
+            memories: load_template(dir, "memories", MEMORIES_VARIABLES)?
+                .unwrap_or(defaults.memories),
+            compaction: load_template(dir, "compaction", COMPACTION_VARIABLES)?
+                .unwrap_or(defaults.compaction),
+            preamble: load_template(dir, "preamble", PREAMBLE_VARIABLES)?
+                .unwrap_or(defaults.preamble),
+        })
+    }
+
+    /// Renders the memories recalled for a prompt, or an empty string when there are none.
+    pub fn render_memories(&self, user_prompt: &str, memories: &[ScoredMemory]) -> String {
+        if memories.is_empty() {
+            return String::new();
+        }
+        let list = memories
+            .iter()
+            .map(|memory| {
+                f!(
+                    "- {} (remembered {})",
+                    memory.record.content,
+                    memory.record.created_at.format("%Y-%m-%d")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.memories
+            .render(&[(USER, user_prompt), (MEMORIES, &list), (DATE, &today())])
+    }
+}
+
+/// The tools offered for a prompt as given to templates, one line each.
+pub fn tool_list(tools: &[ToolDefinition]) -> String {
+    if tools.is_empty() {
+        return String::from("(none)");
+    }
+    tools
+        .iter()
+        .map(|tool| {
+            let description = tool.description.lines().next().unwrap_or_default();
+            f!("- {}: {}", tool.name, description)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Today's date as given to templates.
+pub fn today() -> String {
+    Local::now().format("%A, %B %-d, %Y").to_string()
+}
+
+fn load_template(dir: &Path, name: &str, variables: &[&str]) -> Result<Option<PromptTemplate>> {
+    let path = dir.join(f!("{}.txt", name));
+    match fs::read_to_string(&path) {
+        Ok(text) => {
+            info!("Using the {} template from {}", name, path.display());
+            PromptTemplate::parse(name, text.trim_end(), variables).map(Some)
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::Config(ConfigError::ReadError(e))),
+    }
+}
+
+/// Names of the variables a template uses, in order.
+fn referenced_variables(text: &str) -> std::result::Result<Vec<&str>, String> {
+    let mut variables = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            return Err(String::from("`{{` is never closed"));
+        };
+        variables.push(rest[start + 2..start + end].trim());
+        rest = &rest[start + end + 2..];
+    }
+    Ok(variables)
+}
+
+fn invalid(name: &str, reason: &str) -> Error {
+    Error::Config(ConfigError::ValidationError(f!(
+        "Invalid {} template: {}",
+        name,
+        reason
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{memory::MemoryRecord, utils::temp_dir};
+
+    #[test]
+    fn test_parse_and_render() {
+        let template = PromptTemplate::parse(
+            "summarization",
+            "User: {{user}}\nAssistant: {{ assistant }}",
+            SUMMARIZATION_VARIABLES,
+        )
+        .unwrap();
+        assert_eq!(
+            template.render(&[(USER, "Hello!"), (ASSISTANT, "Hi there")]),
+            "User: Hello!\nAssistant: Hi there"
+        );
+        assert!(template.uses(ASSISTANT));
+        assert!(!template.uses(DATE));
+
+        assert!(matches!(
+            PromptTemplate::parse("preamble", "Reply to {{user}}", PREAMBLE_VARIABLES),
+            Err(Error::Config(ConfigError::ValidationError(_)))
+        ));
+        assert!(PromptTemplate::parse("preamble", "Today is {{date", PREAMBLE_VARIABLES).is_err());
+    }
+
+    #[test]
+    fn test_load_overrides_and_falls_back() {
+        let dir = temp_dir();
+        fs::write(
+            dir.path().join("memories.txt"),
+            "Earlier, when asked {{user}}:\n{{memories}}\n",
+        )
+        .unwrap();
+
+        let templates = PromptTemplates::load(dir.path()).unwrap();
+        let defaults = PromptTemplates::default();
+        assert_eq!(
+            templates.memories.text(),
+            "Earlier, when asked {{user}}:\n{{memories}}"
+        );
+        assert!(templates.memories.uses(USER) && !templates.memories.uses(DATE));
+        assert_eq!(templates.summarization, defaults.summarization);
+        assert_eq!(templates.preamble, defaults.preamble);
+
+        assert_eq!(templates.render_memories("What is my name?", &[]), "");
+        let memories = [ScoredMemory {
+            record: MemoryRecord::new("The user is called Ada".to_string(), Uuid::new_v4(), 0),
+            score: 0.9,
+        }];
+        let rendered = templates.render_memories("What is my name?", &memories);
+        assert!(
+            rendered.starts_with("Earlier, when asked What is my name?:\n- The user is called Ada")
+        );
+
+        // Templates are checked when loaded, not when first rendered
+        fs::write(
+            dir.path().join("compaction.txt"),
+            "{{conversation}} {{user}}",
+        )
+        .unwrap();
+        assert!(matches!(
+            PromptTemplates::load(dir.path()),
+            Err(Error::Config(ConfigError::ValidationError(_)))
+        ));
+        fs::write(dir.path().join("compaction.txt"), "{{conversation").unwrap();
+        assert!(PromptTemplates::load(dir.path()).is_err());
+    }
+}
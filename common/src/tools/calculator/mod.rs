@@ -15,7 +15,7 @@ use serde_json::json;
 use crate::prelude::*;
 pub(super) use error::CalculatorError;
 
-use super::ToolError;
+use super::{RiskLevel, ToolError, ToolRisk};
 
 /// Calculator tool for evaluating mathematical expressions
 #[derive(Debug, Clone)]
@@ -68,6 +68,12 @@ impl Tool for Calculator {
     }
 }
 
+impl ToolRisk for Calculator {
+    fn risk(&self) -> RiskLevel {
+        RiskLevel::Low
+    }
+}
+
 impl ToolEmbedding for Calculator {
     type InitError = CalculatorError;
     type Context = (); // No context needed for Calculator
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ApprovalConfig, FilesConfig, ShellConfig};
+
+/// Configuration of the tool system.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolsConfig {
+    /// Maximum number of model round-trips spent on tool calls for a single prompt.
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: usize,
+
+    /// Names of registered tools never offered to the model
+    #[serde(default)]
+    pub disabled: Vec<String>,
+
+    /// Whether every tool is offered, or only those most relevant to the prompt
+    #[serde(default)]
+    pub selection: ToolSelection,
+
+    /// Number of tools offered when they are retrieved by relevance
+    #[serde(default = "default_max_tools")]
+    pub max_tools: usize,
+
+    /// Similarity a retrieved tool's docs must reach with the prompt; prompts no tool is
+    /// relevant to are then offered none
+    #[serde(default)]
+    pub min_tool_score: Option<f32>,
+
+    /// Which tool calls need the user's approval before they run
+    #[serde(default)]
+    pub approval: ApprovalConfig,
+
+    /// Directories the file tools may access and the limits of their use
+    #[serde(default)]
+    pub files: FilesConfig,
+
+    /// Programs the shell tool may run and how
+    #[serde(default)]
+    pub shell: ShellConfig,
+}
+
+/// Which tools are offered to the model with a prompt.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolSelection {
+    /// Every enabled tool
+    #[default]
+    All,
+    /// The enabled tools whose embedding docs best match the prompt
+    Retrieved,
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            max_iterations: default_max_iterations(),
+            disabled: Vec::new(),
+            selection: ToolSelection::default(),
+            max_tools: default_max_tools(),
+            min_tool_score: None,
+            approval: ApprovalConfig::default(),
+            files: FilesConfig::default(),
+            shell: ShellConfig::default(),
+        }
+    }
+}
+
+fn default_max_iterations() -> usize {
+    5
+}
+
+fn default_max_tools() -> usize {
+    3
+}
@@ -11,6 +11,7 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
+use super::{RiskLevel, ToolRisk};
 use crate::prelude::*;
 pub(super) use error::DateTimeError;
 
@@ -86,6 +87,12 @@ impl Tool for DateTime {
     }
 }
 
+impl ToolRisk for DateTime {
+    fn risk(&self) -> RiskLevel {
+        RiskLevel::Low
+    }
+}
+
 impl ToolEmbedding for DateTime {
     type InitError = DateTimeError;
     type Context = ();
@@ -93,11 +100,11 @@ impl ToolEmbedding for DateTime {
 
     fn embedding_docs(&self) -> Vec<String> {
         vec![
-            "Calculator for mathematical expressions".to_string(),
-            "Math calculations and arithmetic operations".to_string(),
-            "Calculate numbers, evaluate expressions, perform math".to_string(),
-            "Add, subtract, multiply, divide numbers".to_string(),
-            "Trigonometric functions, logarithms, square roots".to_string(),
+            "Current date and time".to_string(),
+            "What day, month or year is it today".to_string(),
+            "What time is it now, the current hour and minute".to_string(),
+            "Today's date in a given format".to_string(),
+            "Current time in UTC or the local time zone".to_string(),
         ]
     }
 
@@ -1,4 +1,4 @@
-use super::calculator::CalculatorError;
+use super::{FileToolError, ShellError, calculator::CalculatorError};
 use rig::tool::ToolError as RigToolError;
 use thiserror::Error;
 
@@ -16,9 +16,21 @@ pub enum ToolError {
     #[error("Tool registration failed: {0}")]
     RegistrationFailed(String),
 
+    #[error("No tool call is waiting for approval with id {0}")]
+    UnknownApproval(String),
+
+    #[error("Model kept calling tools after {0} iterations without a final answer")]
+    IterationLimit(usize),
+
     #[error(transparent)]
     Tool(#[from] RigToolError),
 
     #[error(transparent)]
     Calculator(#[from] CalculatorError),
+
+    #[error(transparent)]
+    Files(#[from] FileToolError),
+
+    #[error(transparent)]
+    Shell(#[from] ShellError),
 }
@@ -0,0 +1,69 @@
+//! Trail of the changes file tools made.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{config, prelude::*, utils::JsonLog};
+
+const AUDIT_FILE_NAME: &str = "file_audit.jsonl";
+
+/// A change made by a file tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    Created,
+    Modified,
+    Trashed,
+}
+
+/// A change made by a file tool, recorded before it is made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    pub change: FileChange,
+    pub path: PathBuf,
+    /// Bytes written, for created and modified files
+    pub bytes: Option<u64>,
+    /// Where a trashed file was moved, so it can be restored
+    pub trashed_to: Option<PathBuf>,
+}
+
+/// Appends the changes of file tools to `<data dir>/file_audit.jsonl`.
+pub struct FileAudit {
+    log: JsonLog<AuditRecord>,
+}
+
+impl FileAudit {
+    /// Opens the trail kept in the data directory.
+    pub fn new() -> Result<Self> {
+        Ok(Self::with_file(
+            config::get_data_dir()?.join(AUDIT_FILE_NAME),
+        ))
+    }
+
+    pub fn with_file(path: PathBuf) -> Self {
+        Self {
+            log: JsonLog::with_file(path),
+        }
+    }
+
+    /// A trail that never touches the filesystem.
+    pub fn in_memory() -> Self {
+        Self {
+            log: JsonLog::in_memory(),
+        }
+    }
+
+    /// Stores a change. Unlike usage accounting, a change that cannot be recorded must not
+    /// be made, so failures are returned.
+    pub fn record(&self, record: AuditRecord) -> Result<()> {
+        self.log.append(record)
+    }
+
+    /// Every stored record, oldest first.
+    pub fn records(&self) -> Result<Vec<AuditRecord>> {
+        self.log.records()
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Configuration of the file tools.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FilesConfig {
+    /// Directories the file tools may access, along with everything below them. Without any,
+    /// the file tools are not offered.
+    #[serde(default)]
+    pub roots: Vec<PathBuf>,
+
+    /// Largest file, in bytes, that is read or searched
+    #[serde(default = "default_max_read_bytes")]
+    pub max_read_bytes: u64,
+
+    /// Largest content, in bytes, that is written at once
+    #[serde(default = "default_max_write_bytes")]
+    pub max_write_bytes: u64,
+
+    /// Most entries returned by a listing or search
+    #[serde(default = "default_max_results")]
+    pub max_results: usize,
+}
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        Self {
+            roots: Vec::new(),
+            max_read_bytes: default_max_read_bytes(),
+            max_write_bytes: default_max_write_bytes(),
+            max_results: default_max_results(),
+        }
+    }
+}
+
+fn default_max_read_bytes() -> u64 {
+    256 * 1024
+}
+
+fn default_max_write_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_max_results() -> usize {
+    200
+}
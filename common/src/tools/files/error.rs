@@ -0,0 +1,37 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FileToolError {
+    #[error("None of the directories configured for file tools exist")]
+    NoRoots,
+
+    #[error("Path '{0}' must not contain '..'")]
+    Traversal(String),
+
+    #[error("Path '{0}' is outside the directories file tools may access")]
+    OutsideRoots(String),
+
+    #[error("'{0}' does not exist")]
+    NotFound(String),
+
+    #[error("'{path}' is {size} bytes, more than the limit of {limit} bytes")]
+    TooLarge { path: String, size: u64, limit: u64 },
+
+    #[error("'{0}' is not a text file")]
+    NotText(String),
+
+    #[error("'{0}' is a directory")]
+    IsDirectory(String),
+
+    #[error("'{0}' is a directory file tools may access and cannot be trashed")]
+    Root(String),
+
+    #[error("Failed to record the change in the audit trail: {0}")]
+    Audit(String),
+
+    #[error(transparent)]
+    Pattern(#[from] glob::PatternError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
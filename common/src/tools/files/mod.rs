@@ -0,0 +1,168 @@
+//! File tools confined to the directories the user configured.
+
+mod audit;
+mod config;
+mod error;
+mod operations;
+mod sandbox;
+
+use std::sync::Arc;
+
+pub use audit::{AuditRecord, FileAudit, FileChange};
+pub use config::FilesConfig;
+pub use error::FileToolError;
+pub use operations::{ListFiles, ReadFile, SearchFiles, TrashFile, WriteFile};
+pub use sandbox::{FileEntry, FileKind, FileSandbox, Listing, SearchMatch, SearchResults};
+
+use super::{ToolCategory, ToolRegistry};
+
+/// Registers every file tool, all sharing the sandbox.
+pub fn register_file_tools(registry: &mut ToolRegistry, sandbox: Arc<FileSandbox>) {
+    let category = ToolCategory::FileOperations;
+    registry.register(ListFiles::new(sandbox.clone()), category.clone());
+    registry.register(ReadFile::new(sandbox.clone()), category.clone());
+    registry.register(SearchFiles::new(sandbox.clone()), category.clone());
+    registry.register(WriteFile::new(sandbox.clone()), category.clone());
+    registry.register(TrashFile::new(sandbox), category);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{tools::ToolRisk, utils::temp_dir};
+
+    fn sandbox(root: &Path, trash: &Path) -> FileSandbox {
+        let config = FilesConfig {
+            roots: vec![root.to_path_buf()],
+            max_read_bytes: 64,
+            ..Default::default()
+        };
+        FileSandbox::with_dirs(config, FileAudit::in_memory(), trash.to_path_buf()).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_paths_stay_within_roots() {
+        let (root, outside, trash) = (temp_dir(), temp_dir(), temp_dir());
+        let (root, outside) = (root.path(), outside.path());
+        fs::write(outside.join("secret.txt"), "secret").unwrap();
+        fs::write(root.join("notes.txt"), "notes").unwrap();
+        std::os::unix::fs::symlink(outside, root.join("escape")).unwrap();
+        let sandbox = sandbox(root, trash.path());
+
+        assert_eq!(sandbox.read("notes.txt").unwrap(), "notes");
+        assert!(matches!(
+            sandbox.read("../secret.txt"),
+            Err(FileToolError::Traversal(_))
+        ));
+        assert!(matches!(
+            sandbox.read(&outside.join("secret.txt").display().to_string()),
+            Err(FileToolError::OutsideRoots(_))
+        ));
+        assert!(matches!(
+            sandbox.read("escape/secret.txt"),
+            Err(FileToolError::OutsideRoots(_))
+        ));
+        assert!(matches!(
+            sandbox.write("escape/new.txt", "x", false),
+            Err(FileToolError::OutsideRoots(_))
+        ));
+
+        fs::write(root.join("large.txt"), "x".repeat(65)).unwrap();
+        assert!(matches!(
+            sandbox.read("large.txt"),
+            Err(FileToolError::TooLarge { size: 65, .. })
+        ));
+
+        // The symlink is listed but not followed
+        let listing = sandbox.list(".", true).unwrap();
+        assert_eq!(listing.entries.len(), 3);
+        assert!(
+            listing
+                .entries
+                .iter()
+                .any(|entry| entry.kind == FileKind::Symlink)
+        );
+        let found = sandbox.search(".", "**/*.txt", Some("NOTE")).unwrap();
+        assert_eq!(found.matches.len(), 1);
+        assert_eq!(found.matches[0].line, Some(1));
+
+        // The limit holds within a single file
+        fs::write(root.join("log.txt"), "note\n".repeat(10)).unwrap();
+        let config = FilesConfig {
+            roots: vec![root.to_path_buf()],
+            max_results: 3,
+            ..Default::default()
+        };
+        let sandbox =
+            FileSandbox::with_dirs(config, FileAudit::in_memory(), trash.path().to_path_buf())
+                .unwrap();
+        let found = sandbox.search(".", "log.txt", Some("note")).unwrap();
+        assert_eq!(found.matches.len(), 3);
+        assert!(found.truncated);
+    }
+
+    #[test]
+    fn test_changes_are_audited() {
+        let (root, trash) = (temp_dir(), temp_dir());
+        let (root, trash) = (root.path(), trash.path());
+        let sandbox = Arc::new(sandbox(root, trash));
+
+        let preview = sandbox.write("drafts/todo.md", "- milk\n", true).unwrap();
+        assert!(preview.contains("+- milk"));
+        let approval = WriteFile::new(sandbox.clone())
+            .preview(&json!({ "path": "drafts/todo.md", "content": "- milk\n" }))
+            .unwrap();
+        assert!(approval.contains("+- milk") && !approval.contains("nothing was written"));
+        assert!(!root.join("drafts").exists());
+
+        sandbox.write("drafts/todo.md", "- milk\n", false).unwrap();
+        let diff = sandbox
+            .write("drafts/todo.md", "- milk\n- eggs\n", false)
+            .unwrap();
+        assert!(diff.contains("+- eggs") && !diff.contains("-- milk"));
+
+        let trashed = sandbox.trash("drafts").unwrap();
+        assert!(!root.join("drafts").exists());
+        assert!(trashed.join("todo.md").exists() && trashed.starts_with(trash));
+        assert!(matches!(
+            sandbox.trash(&root.display().to_string()),
+            Err(FileToolError::Root(_))
+        ));
+
+        let changes = sandbox
+            .audit()
+            .records()
+            .unwrap()
+            .into_iter()
+            .map(|record| record.change)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            [
+                FileChange::Created,
+                FileChange::Modified,
+                FileChange::Trashed
+            ]
+        );
+    }
+
+    #[test]
+    fn test_copy_all_copies_directory_trees() {
+        let (from, to) = (temp_dir(), temp_dir());
+        let (from, to) = (from.path(), to.path().join("copy"));
+        fs::create_dir_all(from.join("nested/empty")).unwrap();
+        fs::write(from.join("nested/todo.md"), "- milk\n").unwrap();
+
+        sandbox::copy_all(from, &to).unwrap();
+        assert_eq!(
+            fs::read_to_string(to.join("nested/todo.md")).unwrap(),
+            "- milk\n"
+        );
+        assert!(to.join("nested/empty").is_dir());
+    }
+}
@@ -0,0 +1,364 @@
+//! The file tools offered to the model, each a thin layer over the [`FileSandbox`].
+
+use std::sync::Arc;
+
+use rig::{completion::ToolDefinition, tool::Tool};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::{FileSandbox, FileToolError, Listing, SearchResults};
+use crate::{
+    prelude::*,
+    tools::{RiskLevel, ToolRisk},
+};
+
+/// Describes a tool along with the directories it may access.
+fn describe(sandbox: &FileSandbox, what: &str) -> String {
+    let roots = sandbox
+        .roots()
+        .iter()
+        .map(|root| root.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    f!(
+        "{} Only paths within these directories can be accessed: {}",
+        what,
+        roots
+    )
+}
+
+/// Lists the contents of a directory.
+#[derive(Clone)]
+pub struct ListFiles {
+    sandbox: Arc<FileSandbox>,
+}
+
+impl ListFiles {
+    pub fn new(sandbox: Arc<FileSandbox>) -> Self {
+        Self { sandbox }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ListFilesParams {
+    /// Directory to list, absolute or relative to an accessible directory
+    pub path: String,
+
+    /// Whether to list everything below the directory
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+impl Tool for ListFiles {
+    const NAME: &'static str = "list_files";
+
+    type Error = FileToolError;
+    type Args = ListFilesParams;
+    type Output = Listing;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: describe(
+                &self.sandbox,
+                "Lists the files, directories and symlinks in a directory.",
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Directory to list, absolute or relative to an accessible directory",
+                    },
+                    "recursive": {
+                        "type": "boolean",
+                        "description": "Whether to list everything below the directory",
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> StdResult<Self::Output, Self::Error> {
+        self.sandbox.list(&args.path, args.recursive)
+    }
+}
+
+impl ToolRisk for ListFiles {
+    fn risk(&self) -> RiskLevel {
+        RiskLevel::Low
+    }
+}
+
+/// Reads a text file.
+#[derive(Clone)]
+pub struct ReadFile {
+    sandbox: Arc<FileSandbox>,
+}
+
+impl ReadFile {
+    pub fn new(sandbox: Arc<FileSandbox>) -> Self {
+        Self { sandbox }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ReadFileParams {
+    /// File to read, absolute or relative to an accessible directory
+    pub path: String,
+}
+
+impl Tool for ReadFile {
+    const NAME: &'static str = "read_file";
+
+    type Error = FileToolError;
+    type Args = ReadFileParams;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: describe(&self.sandbox, "Reads the contents of a text file."),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "File to read, absolute or relative to an accessible directory",
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> StdResult<Self::Output, Self::Error> {
+        self.sandbox.read(&args.path)
+    }
+}
+
+impl ToolRisk for ReadFile {
+    fn risk(&self) -> RiskLevel {
+        RiskLevel::Low
+    }
+}
+
+/// Finds files by name and, optionally, by content.
+#[derive(Clone)]
+pub struct SearchFiles {
+    sandbox: Arc<FileSandbox>,
+}
+
+impl SearchFiles {
+    pub fn new(sandbox: Arc<FileSandbox>) -> Self {
+        Self { sandbox }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SearchFilesParams {
+    /// Directory to search below
+    pub path: String,
+
+    /// Glob pattern the path relative to the directory must match
+    #[serde(default)]
+    pub pattern: Option<String>,
+
+    /// Text the matching lines must contain, regardless of case
+    #[serde(default)]
+    pub contains: Option<String>,
+}
+
+impl Tool for SearchFiles {
+    const NAME: &'static str = "search_files";
+
+    type Error = FileToolError;
+    type Args = SearchFilesParams;
+    type Output = SearchResults;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: describe(
+                &self.sandbox,
+                "Finds the files below a directory whose path matches a glob pattern, and the lines of those files containing some text.",
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Directory to search below",
+                    },
+                    "pattern": {
+                        "type": "string",
+                        "description": "Glob pattern the path relative to the directory must match (e.g., **/*.md); defaults to every file",
+                    },
+                    "contains": {
+                        "type": "string",
+                        "description": "Optional text the matching lines must contain, regardless of case",
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> StdResult<Self::Output, Self::Error> {
+        self.sandbox.search(
+            &args.path,
+            args.pattern.as_deref().unwrap_or("**/*"),
+            args.contains.as_deref(),
+        )
+    }
+}
+
+impl ToolRisk for SearchFiles {
+    fn risk(&self) -> RiskLevel {
+        RiskLevel::Low
+    }
+}
+
+/// Writes a text file, answering with the diff of the change.
+#[derive(Clone)]
+pub struct WriteFile {
+    sandbox: Arc<FileSandbox>,
+}
+
+impl WriteFile {
+    pub fn new(sandbox: Arc<FileSandbox>) -> Self {
+        Self { sandbox }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct WriteFileParams {
+    /// File to write, absolute or relative to an accessible directory
+    pub path: String,
+
+    /// The complete new contents of the file
+    pub content: String,
+
+    /// Whether to only show the diff without writing
+    #[serde(default)]
+    pub preview: bool,
+}
+
+impl Tool for WriteFile {
+    const NAME: &'static str = "write_file";
+
+    type Error = FileToolError;
+    type Args = WriteFileParams;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: describe(
+                &self.sandbox,
+                "Creates or replaces a text file and returns the diff of the change. Set preview to see the diff without writing.",
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "File to write, absolute or relative to an accessible directory",
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "The complete new contents of the file",
+                    },
+                    "preview": {
+                        "type": "boolean",
+                        "description": "Whether to only show the diff without writing",
+                    }
+                },
+                "required": ["path", "content"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> StdResult<Self::Output, Self::Error> {
+        self.sandbox.write(&args.path, &args.content, args.preview)
+    }
+}
+
+impl ToolRisk for WriteFile {
+    fn risk(&self) -> RiskLevel {
+        RiskLevel::Medium
+    }
+
+    /// The diff of the write, or why it would fail.
+    fn preview(&self, arguments: &Value) -> Option<String> {
+        let args = serde_json::from_value::<WriteFileParams>(arguments.clone()).ok()?;
+        Some(
+            self.sandbox
+                .diff(&args.path, &args.content)
+                .unwrap_or_else(|e| e.to_string()),
+        )
+    }
+}
+
+/// Moves a file or directory to the trash.
+#[derive(Clone)]
+pub struct TrashFile {
+    sandbox: Arc<FileSandbox>,
+}
+
+impl TrashFile {
+    pub fn new(sandbox: Arc<FileSandbox>) -> Self {
+        Self { sandbox }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TrashFileParams {
+    /// File or directory to move to the trash
+    pub path: String,
+}
+
+impl Tool for TrashFile {
+    const NAME: &'static str = "trash_file";
+
+    type Error = FileToolError;
+    type Args = TrashFileParams;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: describe(
+                &self.sandbox,
+                "Moves a file or directory to the trash, from where the user can restore it.",
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "File or directory to move to the trash",
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> StdResult<Self::Output, Self::Error> {
+        let destination = self.sandbox.trash(&args.path)?;
+        Ok(f!(
+            "Moved {} to the trash at {}",
+            args.path,
+            destination.display()
+        ))
+    }
+}
+
+impl ToolRisk for TrashFile {
+    fn risk(&self) -> RiskLevel {
+        RiskLevel::Medium
+    }
+}
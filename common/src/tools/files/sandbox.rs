@@ -0,0 +1,465 @@
+//! File access confined to the configured directories.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+};
+
+use chrono::Utc;
+use glob::Pattern;
+use serde::Serialize;
+use similar::TextDiff;
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+use super::{AuditRecord, FileAudit, FileChange, FileToolError, FilesConfig};
+use crate::{config, prelude::*, tools::ToolError};
+
+const TRASH_DIR_NAME: &str = "trash";
+/// Characters of a matching line returned by a content search.
+const MAX_LINE_CHARS: usize = 200;
+
+type FileResult<T> = StdResult<T, FileToolError>;
+
+/// What a directory entry is. Symlinks are reported, never followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileEntry {
+    pub path: String,
+    pub kind: FileKind,
+    /// Size in bytes, for files
+    pub size: Option<u64>,
+}
+
+/// Entries of a directory, cut short at the configured number of results.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Listing {
+    pub entries: Vec<FileEntry>,
+    pub truncated: bool,
+}
+
+/// A file matching a search, and the matching line when searching by content.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchMatch {
+    pub path: String,
+    pub line: Option<usize>,
+    pub text: Option<String>,
+}
+
+/// Matches of a search, cut short at the configured number of results.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchResults {
+    pub matches: Vec<SearchMatch>,
+    pub truncated: bool,
+}
+
+/// Gives the file tools access to the configured root directories and nothing else.
+///
+/// Paths are resolved to their canonical form before they are checked, so neither `..` nor a
+/// symlink can lead outside the roots. Every change is recorded in the audit trail before it
+/// is made, and trashed files are moved aside rather than deleted.
+pub struct FileSandbox {
+    // Canonical forms of the configured roots that exist
+    roots: Vec<PathBuf>,
+    config: FilesConfig,
+    audit: FileAudit,
+    trash_dir: PathBuf,
+}
+
+impl FileSandbox {
+    /// Opens the sandbox of the configured roots, keeping the audit trail and the trash in
+    /// the data directory.
+    pub fn new(config: FilesConfig) -> Result<Self> {
+        let trash_dir = config::get_data_dir()?.join(TRASH_DIR_NAME);
+        Self::with_dirs(config, FileAudit::new()?, trash_dir)
+    }
+
+    pub fn with_dirs(config: FilesConfig, audit: FileAudit, trash_dir: PathBuf) -> Result<Self> {
+        let roots = config
+            .roots
+            .iter()
+            .filter_map(|root| match root.canonicalize() {
+                Ok(root) => Some(root),
+                Err(e) => {
+                    warn!("Skipping file tool directory {}: {}", root.display(), e);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if roots.is_empty() {
+            return Err(Error::Tool(ToolError::Files(FileToolError::NoRoots)));
+        }
+
+        Ok(Self {
+            roots,
+            config,
+            audit,
+            trash_dir,
+        })
+    }
+
+    /// Canonical forms of the directories the file tools may access.
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    pub fn audit(&self) -> &FileAudit {
+        &self.audit
+    }
+
+    /// Resolves an existing path within the roots. Relative paths are taken from the first
+    /// root containing them.
+    pub fn resolve(&self, path: &str) -> FileResult<PathBuf> {
+        let resolved = self
+            .absolute(path)?
+            .canonicalize()
+            .map_err(|e| not_found(e, path))?;
+        self.contain(path, resolved)
+    }
+
+    /// Resolves a path that may not exist yet. Its deepest existing ancestor is resolved, so
+    /// a symlink anywhere along the way cannot lead outside the roots either.
+    pub fn resolve_new(&self, path: &str) -> FileResult<PathBuf> {
+        let candidate = self.absolute(path)?;
+        let mut existing = candidate.as_path();
+        let mut missing = Vec::new();
+        // Dangling symlinks exist too; resolving them fails below
+        while fs::symlink_metadata(existing).is_err() {
+            let (Some(name), Some(parent)) = (existing.file_name(), existing.parent()) else {
+                break;
+            };
+            missing.push(name);
+            existing = parent;
+        }
+
+        let mut resolved = existing.canonicalize().map_err(|e| not_found(e, path))?;
+        resolved.extend(missing.into_iter().rev());
+        self.contain(path, resolved)
+    }
+
+    /// Resolves an existing entry within the roots without following it when it is a
+    /// symlink, so the link itself is acted on rather than its target.
+    fn resolve_entry(&self, path: &str) -> FileResult<PathBuf> {
+        let candidate = self.absolute(path)?;
+        fs::symlink_metadata(&candidate).map_err(|e| not_found(e, path))?;
+        let (Some(name), Some(parent)) = (candidate.file_name(), candidate.parent()) else {
+            return Err(FileToolError::Root(path.to_string()));
+        };
+        let resolved = parent
+            .canonicalize()
+            .map_err(|e| not_found(e, path))?
+            .join(name);
+        self.contain(path, resolved)
+    }
+
+    /// Lists a directory, or everything below it when `recursive`.
+    pub fn list(&self, path: &str, recursive: bool) -> FileResult<Listing> {
+        let dir = self.resolve(path)?;
+        let depth = if recursive { usize::MAX } else { 1 };
+        let mut entries = WalkDir::new(&dir)
+            .min_depth(1)
+            .max_depth(depth)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|entry| {
+                entry
+                    .inspect_err(|e| debug!("Skipping unreadable entry: {}", e))
+                    .ok()
+            })
+            .map(|entry| {
+                let file_type = entry.file_type();
+                let (kind, size) = if file_type.is_symlink() {
+                    (FileKind::Symlink, None)
+                } else if file_type.is_dir() {
+                    (FileKind::Directory, None)
+                } else {
+                    let size = entry.metadata().ok().map(|metadata| metadata.len());
+                    (FileKind::File, size)
+                };
+                FileEntry {
+                    path: entry.path().display().to_string(),
+                    kind,
+                    size,
+                }
+            })
+            .take(self.config.max_results + 1)
+            .collect::<Vec<_>>();
+
+        let truncated = entries.len() > self.config.max_results;
+        entries.truncate(self.config.max_results);
+        Ok(Listing { entries, truncated })
+    }
+
+    /// Reads a text file no larger than the read limit.
+    pub fn read(&self, path: &str) -> FileResult<String> {
+        let file = self.resolve(path)?;
+        self.read_text(path, &file)
+    }
+
+    /// Finds the files below a directory whose path relative to it matches a glob pattern,
+    /// and, when `contains` is given, the lines of those files containing it regardless of
+    /// case.
+    pub fn search(
+        &self,
+        path: &str,
+        pattern: &str,
+        contains: Option<&str>,
+    ) -> FileResult<SearchResults> {
+        let dir = self.resolve(path)?;
+        let pattern = Pattern::new(pattern)?;
+        let needle = contains.map(str::to_lowercase);
+        let limit = self.config.max_results;
+        let mut matches = Vec::new();
+
+        let files = WalkDir::new(&dir)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(StdResult::ok)
+            // Symlinks are not followed, so only regular files are searched
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(&dir)
+                    .is_ok_and(|relative| pattern.matches_path(relative))
+            });
+        // One more match than the limit is kept, to tell whether the results were truncated
+        'files: for entry in files {
+            let shown = entry.path().display().to_string();
+            let Some(needle) = &needle else {
+                matches.push(SearchMatch {
+                    path: shown,
+                    line: None,
+                    text: None,
+                });
+                if matches.len() > limit {
+                    break;
+                }
+                continue;
+            };
+
+            // Files that are too large or not text are skipped rather than failing the search
+            let Ok(content) = self.read_text(&shown, entry.path()) else {
+                continue;
+            };
+            let lines = content
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| line.to_lowercase().contains(needle));
+            for (number, line) in lines {
+                matches.push(SearchMatch {
+                    path: shown.clone(),
+                    line: Some(number + 1),
+                    text: Some(line.trim().chars().take(MAX_LINE_CHARS).collect()),
+                });
+                if matches.len() > limit {
+                    break 'files;
+                }
+            }
+        }
+
+        let truncated = matches.len() > limit;
+        matches.truncate(limit);
+        Ok(SearchResults { matches, truncated })
+    }
+
+    /// Writes a text file, creating it and its directories if needed, and returns the
+    /// unified diff of the change. With `preview`, only the diff is returned.
+    pub fn write(&self, path: &str, content: &str, preview: bool) -> FileResult<String> {
+        let (file, existing, diff) = self.plan_write(path, content)?;
+        if preview {
+            return Ok(f!("{}\n(Preview only, nothing was written)", diff));
+        }
+
+        self.record(AuditRecord {
+            timestamp: Utc::now(),
+            change: if existing {
+                FileChange::Modified
+            } else {
+                FileChange::Created
+            },
+            path: file.clone(),
+            bytes: Some(content.len() as u64),
+            trashed_to: None,
+        })?;
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&file, content)?;
+        info!("Wrote {} bytes to {}", content.len(), file.display());
+        Ok(diff)
+    }
+
+    /// The unified diff writing `content` to `path` would make, without writing it.
+    pub fn diff(&self, path: &str, content: &str) -> FileResult<String> {
+        self.plan_write(path, content).map(|(_, _, diff)| diff)
+    }
+
+    /// Checks a write is allowed, returning the file it goes to, whether that file exists and
+    /// the diff of the change.
+    fn plan_write(&self, path: &str, content: &str) -> FileResult<(PathBuf, bool, String)> {
+        let limit = self.config.max_write_bytes;
+        if content.len() as u64 > limit {
+            return Err(FileToolError::TooLarge {
+                path: path.to_string(),
+                size: content.len() as u64,
+                limit,
+            });
+        }
+
+        let file = self.resolve_new(path)?;
+        if file.is_dir() {
+            return Err(FileToolError::IsDirectory(path.to_string()));
+        }
+        let existing = file.exists();
+        let old = if existing {
+            self.read_text(path, &file)?
+        } else {
+            String::new()
+        };
+
+        let shown = file.display().to_string();
+        let diff = TextDiff::from_lines(old.as_str(), content)
+            .unified_diff()
+            .header(&shown, &shown)
+            .to_string();
+        let diff = if diff.is_empty() {
+            String::from("No changes")
+        } else {
+            diff
+        };
+        Ok((file, existing, diff))
+    }
+
+    /// Moves a file or directory to the trash, returning where it went so it can be restored.
+    pub fn trash(&self, path: &str) -> FileResult<PathBuf> {
+        let entry = self.resolve_entry(path)?;
+        if self.roots.contains(&entry) {
+            return Err(FileToolError::Root(path.to_string()));
+        }
+        let Some(name) = entry.file_name() else {
+            return Err(FileToolError::Root(path.to_string()));
+        };
+
+        let batch = f!(
+            "{}-{}",
+            Utc::now().format("%Y%m%d%H%M%S"),
+            Uuid::new_v4().simple()
+        );
+        let destination = self.trash_dir.join(batch).join(name);
+        self.record(AuditRecord {
+            timestamp: Utc::now(),
+            change: FileChange::Trashed,
+            path: entry.clone(),
+            bytes: None,
+            trashed_to: Some(destination.clone()),
+        })?;
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Renaming fails across filesystems; the entry is then copied and removed instead
+        if fs::rename(&entry, &destination).is_err() {
+            copy_all(&entry, &destination)?;
+            if fs::symlink_metadata(&entry)?.is_dir() {
+                fs::remove_dir_all(&entry)?;
+            } else {
+                fs::remove_file(&entry)?;
+            }
+        }
+        info!(
+            "Moved {} to the trash at {}",
+            entry.display(),
+            destination.display()
+        );
+        Ok(destination)
+    }
+
+    fn read_text(&self, path: &str, file: &Path) -> FileResult<String> {
+        let metadata = fs::metadata(file)?;
+        if metadata.is_dir() {
+            return Err(FileToolError::IsDirectory(path.to_string()));
+        }
+        let limit = self.config.max_read_bytes;
+        if metadata.len() > limit {
+            return Err(FileToolError::TooLarge {
+                path: path.to_string(),
+                size: metadata.len(),
+                limit,
+            });
+        }
+        String::from_utf8(fs::read(file)?).map_err(|_| FileToolError::NotText(path.to_string()))
+    }
+
+    fn record(&self, record: AuditRecord) -> FileResult<()> {
+        self.audit
+            .record(record)
+            .map_err(|e| FileToolError::Audit(e.to_string()))
+    }
+
+    fn absolute(&self, path: &str) -> FileResult<PathBuf> {
+        let relative = Path::new(path);
+        if relative
+            .components()
+            .any(|component| component == Component::ParentDir)
+        {
+            return Err(FileToolError::Traversal(path.to_string()));
+        }
+        if relative.is_absolute() {
+            return Ok(relative.to_path_buf());
+        }
+        Ok(self
+            .roots
+            .iter()
+            .map(|root| root.join(relative))
+            .find(|candidate| fs::symlink_metadata(candidate).is_ok())
+            .unwrap_or_else(|| self.roots[0].join(relative)))
+    }
+
+    fn contain(&self, path: &str, resolved: PathBuf) -> FileResult<PathBuf> {
+        if self.roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            Err(FileToolError::OutsideRoots(path.to_string()))
+        }
+    }
+}
+
+fn not_found(error: std::io::Error, path: &str) -> FileToolError {
+    match error.kind() {
+        ErrorKind::NotFound => FileToolError::NotFound(path.to_string()),
+        _ => error.into(),
+    }
+}
+
+/// Copies a file or directory tree to `to`, recreating symlinks rather than following them.
+pub(super) fn copy_all(from: &Path, to: &Path) -> std::io::Result<()> {
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let relative = entry
+            .path()
+            .strip_prefix(from)
+            .expect("walked entries lie below the root of the walk");
+        let target = to.join(relative);
+        let kind = entry.file_type();
+        if kind.is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if kind.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+            #[cfg(not(unix))]
+            fs::copy(entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
@@ -1,12 +1,26 @@
 //! Tool system for Lyn AI assistant based on rig's Tool capabilities.
 
 mod calculator;
+mod config;
 mod datetime;
 mod error;
+mod files;
+mod policy;
 mod registry;
+mod selection;
+mod shell;
 
 // Re-exports
 pub use calculator::Calculator;
+pub use config::{ToolSelection, ToolsConfig};
 pub use datetime::DateTime;
 pub use error::ToolError;
+pub use files::{
+    AuditRecord, FileAudit, FileChange, FileEntry, FileKind, FileSandbox, FileToolError,
+    FilesConfig, ListFiles, Listing, ReadFile, SearchFiles, SearchMatch, SearchResults, TrashFile,
+    WriteFile, register_file_tools,
+};
+pub use policy::{ApprovalConfig, RiskLevel, ToolPolicy, ToolRisk};
 pub use registry::{ToolCategory, ToolRegistry};
+pub use selection::ToolIndex;
+pub use shell::{CommandOutput, Shell, ShellConfig, ShellError, ShellParams};
@@ -0,0 +1,117 @@
+//! Which tool calls run unattended, need the user's approval, or are refused.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::ToolCategory;
+
+/// How much harm a tool can do when called with the wrong arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    /// Only reads state or computes an answer
+    Low,
+    /// Changes state that can be restored
+    Medium,
+    /// Changes state that cannot be restored, or reaches outside the assistant
+    High,
+}
+
+impl RiskLevel {
+    /// Policy of tools the config says nothing about: only low risk tools run unattended.
+    pub fn default_policy(self) -> ToolPolicy {
+        match self {
+            RiskLevel::Low => ToolPolicy::Allow,
+            RiskLevel::Medium | RiskLevel::High => ToolPolicy::Ask,
+        }
+    }
+}
+
+/// Declares the risk of calling a tool; every registered tool must.
+pub trait ToolRisk {
+    fn risk(&self) -> RiskLevel;
+
+    /// Risk of a call with the given arguments, for tools some calls of which are harmless.
+    fn call_risk(&self, _arguments: &Value) -> RiskLevel {
+        self.risk()
+    }
+
+    /// What a call with the given arguments would change, shown when asking to approve it.
+    fn preview(&self, _arguments: &Value) -> Option<String> {
+        None
+    }
+}
+
+/// What happens when the model calls a tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolPolicy {
+    /// The tool runs without asking
+    Allow,
+    /// The user approves or denies each call
+    Ask,
+    /// The tool never runs; the model is told it was refused
+    Deny,
+}
+
+/// Policies overriding the default of a tool's risk level.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ApprovalConfig {
+    /// Policies by tool name; these take precedence over category policies
+    #[serde(default)]
+    pub tools: HashMap<String, ToolPolicy>,
+
+    /// Policies by category name, e.g. `FileOperations` or the name of a custom category
+    #[serde(default)]
+    pub categories: HashMap<String, ToolPolicy>,
+}
+
+impl ApprovalConfig {
+    /// The policy of a tool: its own, else its category's, else the default of its risk.
+    pub fn policy(&self, tool: &str, category: &ToolCategory, risk: RiskLevel) -> ToolPolicy {
+        self.configured(tool, category)
+            .unwrap_or_else(|| risk.default_policy())
+    }
+
+    /// The policy the config sets for a tool or its category, if any.
+    pub fn configured(&self, tool: &str, category: &ToolCategory) -> Option<ToolPolicy> {
+        self.tools
+            .get(tool)
+            .or_else(|| self.categories.get(category.name()))
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_precedence() {
+        let config = ApprovalConfig {
+            tools: HashMap::from([(String::from("datetime"), ToolPolicy::Deny)]),
+            categories: HashMap::from([(String::from("Utilities"), ToolPolicy::Ask)]),
+        };
+
+        let utilities = ToolCategory::Utilities;
+        assert_eq!(
+            config.policy("datetime", &utilities, RiskLevel::Low),
+            ToolPolicy::Deny
+        );
+        assert_eq!(
+            config.policy("calculator", &utilities, RiskLevel::Low),
+            ToolPolicy::Ask
+        );
+        let custom = ToolCategory::Custom(String::from("Notes"));
+        assert_eq!(
+            config.policy("notes", &custom, RiskLevel::Low),
+            ToolPolicy::Allow
+        );
+        assert_eq!(
+            config.policy("notes", &custom, RiskLevel::High),
+            ToolPolicy::Ask
+        );
+    }
+}
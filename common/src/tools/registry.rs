@@ -1,9 +1,19 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+};
 
-use rig::tool::{Tool, ToolDyn, ToolSet as RigToolSet};
+use rig::{
+    completion::ToolDefinition,
+    tool::{Tool, ToolDyn, ToolEmbedding, ToolError as RigToolError, ToolSet as RigToolSet},
+};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use super::{Calculator, DateTime};
+use super::{RiskLevel, ToolError, ToolPolicy, ToolRisk, ToolsConfig};
+use crate::prelude::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
@@ -15,99 +25,237 @@ pub enum ToolCategory {
     Custom(String),
 }
 
+impl ToolCategory {
+    /// Name of the category as written in the config
+    pub fn name(&self) -> &str {
+        match self {
+            ToolCategory::Utilities => "Utilities",
+            ToolCategory::System => "System",
+            ToolCategory::FileOperations => "FileOperations",
+            ToolCategory::WebAccess => "WebAccess",
+            ToolCategory::Custom(name) => name,
+        }
+    }
+}
+
+/// A registered tool, the category it belongs to, its risk and the texts describing it for
+/// retrieval.
+struct Entry {
+    tool: Arc<dyn ToolDyn>,
+    category: ToolCategory,
+    risk: RiskLevel,
+    // The same tool, rating the risk of each call and previewing what it would change
+    rated: Arc<dyn ToolRisk + Send + Sync>,
+    docs: Vec<String>,
+}
+
 /// Registry for managing and organizing tools
+///
+/// The registered tools, less the disabled ones, are the tools offered to the model and the
+/// only ones it can call.
 pub struct ToolRegistry {
-    // Map from category to tools in that category
-    categories: HashMap<ToolCategory, Vec<Box<dyn ToolDyn>>>,
-    // Map from tool name to category for quick lookups
-    tool_categories: HashMap<String, ToolCategory>,
+    // Tools in registration order, so the model sees them in a stable order
+    tools: Vec<Entry>,
+    // Map from tool name to its position in `tools` for quick lookups
+    index: HashMap<String, usize>,
+    // Names of the tools turned off in the config
+    disabled: HashSet<String>,
 }
 
 impl ToolRegistry {
     /// Create a new empty tool registry
     pub fn new() -> Self {
         Self {
-            categories: HashMap::new(),
-            tool_categories: HashMap::new(),
+            tools: Vec::new(),
+            index: HashMap::new(),
+            disabled: HashSet::new(),
         }
     }
 
-    /// Register a tool in a specific category
+    /// Register a tool in a specific category, replacing any tool of the same name
     pub fn register<T>(&mut self, tool: T, category: ToolCategory)
     where
-        T: Tool + 'static,
+        T: Tool + ToolRisk + 'static,
     {
-        let tool_name = T::NAME.to_string();
-        let boxed_tool = Box::new(tool);
+        self.insert::<T>(tool, category, Vec::new());
+    }
+
+    /// Register a tool along with its embedding docs, which describe it when tools are
+    /// retrieved by relevance to the prompt
+    pub fn register_embedding<T>(&mut self, tool: T, category: ToolCategory)
+    where
+        T: ToolEmbedding + ToolRisk + 'static,
+    {
+        let docs = tool.embedding_docs();
+        self.insert::<T>(tool, category, docs);
+    }
+
+    fn insert<T>(&mut self, tool: T, category: ToolCategory, docs: Vec<String>)
+    where
+        T: Tool + ToolRisk + 'static,
+    {
+        let tool = Arc::new(tool);
+        let entry = Entry {
+            risk: tool.risk(),
+            rated: tool.clone(),
+            tool,
+            category,
+            docs,
+        };
+        match self.index.get(T::NAME) {
+            Some(&position) => {
+                warn!("Tool '{}' is registered twice, keeping the last", T::NAME);
+                self.tools[position] = entry;
+            }
+            None => {
+                self.index.insert(T::NAME.to_string(), self.tools.len());
+                self.tools.push(entry);
+            }
+        }
+    }
+
+    /// Turns off the tools the config disables. Names of tools that are not registered are
+    /// reported, as they are most likely typos.
+    pub fn configure(&mut self, config: &ToolsConfig) {
+        for name in &config.disabled {
+            if !self.index.contains_key(name) {
+                warn!("Cannot disable tool '{}', no such tool is registered", name);
+            }
+        }
+        self.disabled = config.disabled.iter().cloned().collect();
+    }
+
+    /// Turn a registered tool on or off
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        if !self.index.contains_key(name) {
+            return Err(Error::Tool(ToolError::NotFound(name.to_string())));
+        }
+        if enabled {
+            self.disabled.remove(name);
+        } else {
+            self.disabled.insert(name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether a tool is registered and enabled
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.index.contains_key(name) && !self.disabled.contains(name)
+    }
+
+    /// Category of an enabled tool
+    pub fn category(&self, name: &str) -> Option<&ToolCategory> {
+        self.entry(name).map(|entry| &entry.category)
+    }
+
+    /// Risk of calling an enabled tool
+    pub fn risk(&self, name: &str) -> Option<RiskLevel> {
+        self.entry(name).map(|entry| entry.risk)
+    }
+
+    /// Risk of calling an enabled tool with the given arguments
+    pub fn call_risk(&self, name: &str, arguments: &Value) -> Option<RiskLevel> {
+        self.entry(name)
+            .map(|entry| entry.rated.call_risk(arguments))
+    }
 
-        // Add to category map
-        self.categories
-            .entry(category.clone())
-            .or_default()
-            .push(boxed_tool);
+    /// What calling an enabled tool with the given arguments would change, when it can tell
+    pub fn preview(&self, name: &str, arguments: &Value) -> Option<String> {
+        self.entry(name)
+            .and_then(|entry| entry.rated.preview(arguments))
+    }
 
-        // Add to tool category map for lookups
-        self.tool_categories.insert(tool_name, category);
+    /// What the approval config says happens when the model calls a tool with the given
+    /// arguments. Tools that are not registered or disabled are allowed, as calling them
+    /// fails anyway.
+    pub fn policy(&self, name: &str, arguments: &Value, config: &ToolsConfig) -> ToolPolicy {
+        self.entry(name)
+            .map(|entry| {
+                let risk = entry.rated.call_risk(arguments);
+                config.approval.policy(name, &entry.category, risk)
+            })
+            .unwrap_or(ToolPolicy::Allow)
     }
 
-    /// Get all tools in a specific category
-    pub fn get_by_category(&self, category: &ToolCategory) -> Vec<&Box<dyn ToolDyn>> {
-        self.categories
-            .get(category)
-            .map(|tools| tools.iter().collect())
-            .unwrap_or_default()
+    /// Names of the enabled tools, in registration order
+    pub fn names(&self) -> Vec<String> {
+        self.enabled().map(|entry| entry.tool.name()).collect()
     }
 
-    /// Get all available tools
-    pub fn get_all_tools(&self) -> Vec<&Box<dyn ToolDyn>> {
-        self.categories
-            .values()
-            .flat_map(|tools| tools.iter())
+    /// Get all enabled tools in a specific category
+    pub fn get_by_category(&self, category: &ToolCategory) -> Vec<&dyn ToolDyn> {
+        self.enabled()
+            .filter(|entry| &entry.category == category)
+            .map(|entry| entry.tool.as_ref())
             .collect()
     }
 
-    /// Get tool definitions for all tools
-    pub async fn get_tool_definitions(&self, prompt: &str) -> Vec<rig::completion::ToolDefinition> {
+    /// Get all enabled tools
+    pub fn get_all_tools(&self) -> Vec<&dyn ToolDyn> {
+        self.enabled().map(|entry| entry.tool.as_ref()).collect()
+    }
+
+    /// Look up an enabled tool by name
+    pub fn get(&self, name: &str) -> Option<&dyn ToolDyn> {
+        self.entry(name).map(|entry| entry.tool.as_ref())
+    }
+
+    /// Call an enabled tool by name with JSON encoded arguments
+    pub async fn call(&self, name: &str, args: String) -> Result<String> {
+        let tool = self
+            .get(name)
+            .ok_or_else(|| Error::ToolNotFound(name.to_string()))?;
+
+        debug!("Calling tool '{}' with args: {}", name, args);
+        tool.call(args)
+            .await
+            .map_err(|e| Error::Tool(ToolError::from(e)))
+    }
+
+    /// Get tool definitions for all enabled tools
+    pub async fn get_tool_definitions(&self, prompt: &str) -> Vec<ToolDefinition> {
         let mut definitions = Vec::new();
         for tool in self.get_all_tools() {
-            // Note: This requires downcasting which might not be ideal
-            // You might need to adapt this based on rig's actual API
-            let definition = tool.definition(prompt.to_string()).await;
-            definitions.push(definition);
+            definitions.push(tool.definition(prompt.to_string()).await);
         }
         definitions
     }
 
-    /// Convert to a rig ToolSet for LLM integration
+    /// Texts describing each enabled tool for retrieval: its embedding docs, or the
+    /// description of its definition when it has none
+    pub async fn embedding_docs(&self) -> Vec<(String, Vec<String>)> {
+        let mut docs = Vec::new();
+        for entry in self.enabled() {
+            let texts = if entry.docs.is_empty() {
+                vec![entry.tool.definition(String::new()).await.description]
+            } else {
+                entry.docs.clone()
+            };
+            docs.push((entry.tool.name(), texts));
+        }
+        docs
+    }
+
+    /// Convert to a rig ToolSet for LLM integration, holding every enabled tool
     pub fn as_rig_toolset(&self) -> RigToolSet {
         let mut toolset = RigToolSet::default();
+        for entry in self.enabled() {
+            toolset.add_tool(SharedTool(entry.tool.clone()));
+        }
+        toolset
+    }
 
-        for category in self.categories.keys() {
-            match category {
-                ToolCategory::Utilities => {
-                    // Add utility tools
-                    toolset.add_tool(Calculator);
-                    toolset.add_tool(DateTime);
-                }
-                ToolCategory::System => {
-                    // Add system tools
-                    // toolset.add_tool(SystemInfo);
-                }
-                ToolCategory::FileOperations => {
-                    // Add file operation tools
-                    // toolset.add_tool(FileSearcher);
-                }
-                ToolCategory::WebAccess => {
-                    // Add web access tools
-                    // toolset.add_tool(WebSearch);
-                }
-                ToolCategory::Custom(_) => {
-                    // Custom tools would need special handling
-                }
-            }
+    fn entry(&self, name: &str) -> Option<&Entry> {
+        if self.disabled.contains(name) {
+            return None;
         }
+        self.index.get(name).map(|&position| &self.tools[position])
+    }
 
-        toolset
+    fn enabled(&self) -> impl Iterator<Item = &Entry> {
+        self.tools
+            .iter()
+            .filter(|entry| !self.disabled.contains(&entry.tool.name()))
     }
 }
 
@@ -116,3 +264,66 @@ impl Default for ToolRegistry {
         Self::new()
     }
 }
+
+/// A registered tool handed to a rig toolset while the registry keeps it too.
+struct SharedTool(Arc<dyn ToolDyn>);
+
+impl ToolDyn for SharedTool {
+    fn name(&self) -> String {
+        self.0.name()
+    }
+
+    fn definition(
+        &self,
+        prompt: String,
+    ) -> Pin<Box<dyn Future<Output = ToolDefinition> + Send + Sync + '_>> {
+        self.0.definition(prompt)
+    }
+
+    fn call(
+        &self,
+        args: String,
+    ) -> Pin<Box<dyn Future<Output = StdResult<String, RigToolError>> + Send + Sync + '_>> {
+        self.0.call(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{Calculator, DateTime};
+
+    #[async_std::test]
+    async fn test_registry_offers_enabled_tools() {
+        let mut registry = ToolRegistry::new();
+        registry.register(Calculator, ToolCategory::Utilities);
+        registry.register(DateTime, ToolCategory::Custom(String::from("Clock")));
+        registry.configure(&ToolsConfig {
+            disabled: vec![String::from("calculator")],
+            ..Default::default()
+        });
+
+        assert_eq!(registry.names(), ["datetime"]);
+        assert!(registry.get("calculator").is_none());
+        assert!(
+            registry
+                .call("calculator", String::from("{}"))
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            registry.category("datetime"),
+            Some(&ToolCategory::Custom(String::from("Clock")))
+        );
+
+        let toolset = registry.as_rig_toolset();
+        assert!(toolset.contains("datetime") && !toolset.contains("calculator"));
+
+        registry.set_enabled("calculator", true).unwrap();
+        let result = registry
+            .call("calculator", String::from(r#"{"expression": "1 + 2"}"#))
+            .await
+            .unwrap();
+        assert_eq!(result, "3.0");
+    }
+}
@@ -0,0 +1,161 @@
+//! Retrieval of the tools relevant to a prompt.
+
+use async_std::sync::RwLock;
+use rig::completion::ToolDefinition;
+
+use crate::{
+    llm::{EmbeddingType, LLMProvider},
+    prelude::*,
+    tools::ToolRegistry,
+    utils::cosine_similarity,
+};
+
+/// Embeddings of the tools' embedding docs, used to offer only the tools relevant to a
+/// prompt so small models are not handed long tool lists.
+///
+/// The index is built on first use, as embedding needs the provider to be reachable.
+#[derive(Default)]
+pub struct ToolIndex {
+    docs: RwLock<Option<Vec<IndexedDoc>>>,
+}
+
+/// An embedded doc and the tool it describes.
+struct IndexedDoc {
+    tool: String,
+    vector: Vec<f32>,
+}
+
+impl ToolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the `limit` tools whose docs best match the prompt, best match first, leaving
+    /// out those scoring below `min_score`.
+    pub async fn retrieve(
+        &self,
+        provider: &dyn LLMProvider,
+        registry: &ToolRegistry,
+        prompt: &str,
+        tools: Vec<ToolDefinition>,
+        limit: usize,
+        min_score: Option<f32>,
+    ) -> Result<Vec<ToolDefinition>> {
+        if tools.len() <= limit && min_score.is_none() {
+            return Ok(tools);
+        }
+        self.build(provider, registry).await?;
+        let query = provider
+            .generate_embedding(EmbeddingType::Text(prompt.to_string()))
+            .await?
+            .pop()
+            .unwrap_or_default();
+
+        let docs = self.docs.read().await;
+        let docs = docs.as_deref().unwrap_or_default();
+        let mut scored = tools
+            .into_iter()
+            .map(|tool| {
+                let score = docs
+                    .iter()
+                    .filter(|doc| doc.tool == tool.name)
+                    .filter_map(|doc| cosine_similarity(&query, &doc.vector).ok())
+                    .fold(f32::MIN, f32::max);
+                (score, tool)
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        debug!(
+            "Tool relevance: {:?}",
+            scored
+                .iter()
+                .map(|(score, tool)| (&tool.name, score))
+                .collect::<Vec<_>>()
+        );
+
+        Ok(scored
+            .into_iter()
+            .take(limit)
+            .filter(|(score, _)| min_score.is_none_or(|min| *score >= min))
+            .map(|(_, tool)| tool)
+            .collect())
+    }
+
+    async fn build(&self, provider: &dyn LLMProvider, registry: &ToolRegistry) -> Result<()> {
+        if self.docs.read().await.is_some() {
+            return Ok(());
+        }
+        let mut docs = self.docs.write().await;
+        if docs.is_some() {
+            return Ok(());
+        }
+
+        let (names, texts): (Vec<_>, Vec<_>) = registry
+            .embedding_docs()
+            .await
+            .into_iter()
+            .flat_map(|(name, texts)| texts.into_iter().map(move |text| (name.clone(), text)))
+            .unzip();
+        let vectors = provider
+            .generate_embeddings(texts.into_iter().map(EmbeddingType::Text).collect())
+            .await?;
+        info!("Indexed {} tool doc(s) for retrieval", vectors.len());
+        *docs = Some(
+            names
+                .into_iter()
+                .zip(vectors)
+                .map(|(tool, vector)| IndexedDoc { tool, vector })
+                .collect(),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        llm::MockProvider,
+        tools::{Calculator, DateTime, ToolCategory},
+    };
+
+    #[async_std::test]
+    async fn test_retrieve_ranks_tools_by_docs() {
+        let mut registry = ToolRegistry::new();
+        registry.register_embedding(Calculator, ToolCategory::Utilities);
+        registry.register_embedding(DateTime, ToolCategory::Utilities);
+        let provider = MockProvider::scripted([]);
+        let index = ToolIndex::new();
+        let prompt = "What time is it now?";
+
+        let tools = registry.get_tool_definitions(prompt).await;
+        let retrieved = index
+            .retrieve(&provider, &registry, prompt, tools, 1, None)
+            .await
+            .unwrap();
+        assert_eq!(retrieved.len(), 1);
+        assert_eq!(retrieved[0].name, "datetime");
+
+        let tools = registry.get_tool_definitions(prompt).await;
+        let retrieved = index
+            .retrieve(
+                &provider,
+                &registry,
+                "Multiply these numbers",
+                tools,
+                1,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(retrieved[0].name, "calculator");
+
+        // Nothing is relevant to small talk
+        let tools = registry.get_tool_definitions(prompt).await;
+        let retrieved = index
+            .retrieve(&provider, &registry, "Hello there", tools, 2, Some(0.5))
+            .await
+            .unwrap();
+        assert!(retrieved.is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Configuration of the shell tool.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShellConfig {
+    /// Whether the shell tool is offered to the model
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Programs that may run, by the name or path the model gives
+    #[serde(default)]
+    pub allowlist: Vec<String>,
+
+    /// Let programs outside the allowlist run too. Setting the `ask` approval policy for the
+    /// shell tool, or its `System` category, does the same, as the user then sees each command
+    /// first.
+    #[serde(default)]
+    pub allow_any: bool,
+
+    /// Seconds a command may run before it is killed
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Bytes of stdout, and of stderr, returned to the model
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+
+    /// Environment variables passed on to commands; all others are removed
+    #[serde(default = "default_env")]
+    pub env: Vec<String>,
+
+    /// Directory commands run in; the home directory when unset
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+
+    /// Only show what would run, for every command
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allowlist: Vec::new(),
+            allow_any: false,
+            timeout_secs: default_timeout_secs(),
+            max_output_bytes: default_max_output_bytes(),
+            env: default_env(),
+            working_dir: None,
+            dry_run: false,
+        }
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_max_output_bytes() -> usize {
+    16 * 1024
+}
+
+fn default_env() -> Vec<String> {
+    ["PATH", "HOME", "USER", "LANG", "LC_ALL", "TERM", "TMPDIR"]
+        .map(String::from)
+        .to_vec()
+}
@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ShellError {
+    #[error("Program '{0}' is not in the allowlist of the shell tool")]
+    NotAllowed(String),
+
+    #[error("No program to run was given")]
+    MissingProgram,
+
+    #[error("Failed to run '{program}': {source}")]
+    Spawn {
+        program: String,
+        source: std::io::Error,
+    },
+}
@@ -0,0 +1,421 @@
+//! Shell tool running programs for task automation.
+
+mod config;
+mod error;
+
+use std::{
+    io::Read,
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use async_std::task;
+use rig::{completion::ToolDefinition, tool::Tool};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::{ApprovalConfig, RiskLevel, ToolCategory, ToolPolicy, ToolRisk};
+use crate::prelude::*;
+pub use config::ShellConfig;
+pub use error::ShellError;
+
+/// How often a running command is checked for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(25);
+/// How long output is still collected after a command exits, in case a process it left
+/// behind holds its output open.
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// Runs a program directly, without a shell, in a scrubbed environment.
+///
+/// Only allowlisted programs run, unless the config opts into any program with `allow_any` or
+/// an explicit `ask` approval policy. Commands run in a process group of their own, which is
+/// killed once they time out, and their output is cut to the configured budget.
+#[derive(Debug, Clone)]
+pub struct Shell {
+    config: ShellConfig,
+    // Programs outside the allowlist may run
+    any_program: bool,
+}
+
+impl Shell {
+    /// Creates the tool for the `System` category, running under the policy `approval` gives
+    /// it. The risk's default policy does not open the tool to any program; only the config
+    /// asking for approval explicitly does.
+    pub fn new(config: ShellConfig, approval: &ApprovalConfig) -> Self {
+        let asks = approval.configured(Self::NAME, &ToolCategory::System) == Some(ToolPolicy::Ask);
+        Self {
+            any_program: config.allow_any || asks,
+            config,
+        }
+    }
+
+    fn working_dir(&self) -> PathBuf {
+        self.config
+            .working_dir
+            .clone()
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// The configured variables that are set, with their values.
+    fn environment(&self) -> Vec<(String, String)> {
+        self.config
+            .env
+            .iter()
+            .filter_map(|name| std::env::var(name).ok().map(|value| (name.clone(), value)))
+            .collect()
+    }
+}
+
+/// Parameters for the shell tool
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ShellParams {
+    /// Program to run, by name or path
+    pub program: String,
+
+    /// Arguments passed to the program as they are
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Whether to only show what would run
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// What ran and what it printed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandOutput {
+    /// The command line as it would be typed
+    pub command: String,
+    pub working_dir: String,
+    /// Names of the environment variables passed on
+    pub environment: Vec<String>,
+    /// The command was only shown, not run
+    pub dry_run: bool,
+    /// Unset when the command did not run, timed out or was killed by a signal
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Tool for Shell {
+    const NAME: &'static str = "shell";
+
+    type Error = ShellError;
+    type Args = ShellParams;
+    type Output = CommandOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let allowed = if self.any_program {
+            String::from("Any program can run once the user approves it.")
+        } else {
+            f!(
+                "Only these programs can run: {}.",
+                self.config.allowlist.join(", ")
+            )
+        };
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: f!(
+                "Runs a program with arguments and returns its exit code and output. It is run directly, without a shell, so pipes, redirections and globs are not available. {}",
+                allowed
+            ),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "program": {
+                        "type": "string",
+                        "description": "Program to run, by name or path",
+                    },
+                    "args": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Arguments passed to the program as they are",
+                    },
+                    "dry_run": {
+                        "type": "boolean",
+                        "description": "Whether to only show what would run",
+                    }
+                },
+                "required": ["program"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> StdResult<Self::Output, Self::Error> {
+        let ShellParams {
+            program,
+            args,
+            dry_run,
+        } = args;
+        if program.trim().is_empty() {
+            return Err(ShellError::MissingProgram);
+        }
+        if !self.any_program && !self.config.allowlist.contains(&program) {
+            return Err(ShellError::NotAllowed(program));
+        }
+
+        let working_dir = self.working_dir();
+        let environment = self.environment();
+        let mut output = CommandOutput {
+            command: command_line(&program, &args),
+            working_dir: working_dir.display().to_string(),
+            environment: environment.iter().map(|(name, _)| name.clone()).collect(),
+            dry_run: dry_run || self.config.dry_run,
+            exit_code: None,
+            timed_out: false,
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+        if output.dry_run {
+            info!("Dry run of `{}`", output.command);
+            return Ok(output);
+        }
+
+        info!("Running `{}` in {}", output.command, output.working_dir);
+        let mut command = Command::new(&program);
+        command
+            .args(&args)
+            .current_dir(&working_dir)
+            .env_clear()
+            .envs(environment)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // The command leads a group of its own, so the processes it starts can be killed
+        // along with it
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command.spawn().map_err(|source| ShellError::Spawn {
+            program: program.clone(),
+            source,
+        })?;
+        let budget = self.config.max_output_bytes;
+        let stdout = child.stdout.take().map(|pipe| capture(pipe, budget));
+        let stderr = child.stderr.take().map(|pipe| capture(pipe, budget));
+
+        let deadline = Instant::now() + Duration::from_secs(self.config.timeout_secs);
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) if Instant::now() < deadline => task::sleep(POLL_INTERVAL).await,
+                Ok(None) => {
+                    warn!(
+                        "`{}` timed out after {} s, killing it",
+                        output.command, self.config.timeout_secs
+                    );
+                    output.timed_out = true;
+                    break None;
+                }
+                Err(e) => {
+                    warn!("Failed to wait for `{}`: {}", output.command, e);
+                    break None;
+                }
+            }
+        };
+        if status.is_none() {
+            kill(&mut child);
+        }
+        output.exit_code = status.and_then(|status| status.code());
+
+        let grace = Instant::now() + OUTPUT_GRACE;
+        let pending = |capture: &Option<JoinHandle<String>>| {
+            capture.as_ref().is_some_and(|handle| !handle.is_finished())
+        };
+        while (pending(&stdout) || pending(&stderr)) && Instant::now() < grace {
+            task::sleep(POLL_INTERVAL).await;
+        }
+        output.stdout = collect(stdout);
+        output.stderr = collect(stderr);
+        Ok(output)
+    }
+}
+
+impl ToolRisk for Shell {
+    fn risk(&self) -> RiskLevel {
+        RiskLevel::High
+    }
+
+    /// Dry runs only show the command, so they need no approval.
+    fn call_risk(&self, arguments: &Value) -> RiskLevel {
+        let dry_run = arguments
+            .get("dry_run")
+            .and_then(Value::as_bool)
+            .unwrap_or_default();
+        if dry_run || self.config.dry_run {
+            RiskLevel::Low
+        } else {
+            self.risk()
+        }
+    }
+}
+
+/// Kills a command along with every process of its group, so none is left behind holding its
+/// output open.
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        // The group id is the pid of the command leading it
+        // SAFETY: killpg only sends a signal and takes no pointers
+        let _ = unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) };
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Reads a pipe to its end on a thread of its own, so a command never blocks on a full pipe,
+/// keeping only the first `budget` bytes.
+fn capture(mut pipe: impl Read + Send + 'static, budget: usize) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut kept = Vec::new();
+        let mut left_out = 0;
+        let mut buffer = [0; 8192];
+        loop {
+            let read = match pipe.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            let keep = read.min(budget - kept.len());
+            kept.extend_from_slice(&buffer[..keep]);
+            left_out += read - keep;
+        }
+
+        let text = String::from_utf8_lossy(&kept).into_owned();
+        if left_out > 0 {
+            f!("{}\n[{} more bytes left out]", text, left_out)
+        } else {
+            text
+        }
+    })
+}
+
+/// The captured output, or a note when it could not be collected in time.
+fn collect(capture: Option<JoinHandle<String>>) -> String {
+    match capture {
+        Some(handle) if handle.is_finished() => handle.join().unwrap_or_default(),
+        Some(_) => String::from("[output was still open when the command exited]"),
+        None => String::new(),
+    }
+}
+
+/// The command as it would be typed into a shell, quoting arguments where needed.
+fn command_line(program: &str, args: &[String]) -> String {
+    let quote = |arg: &str| {
+        let plain = !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_alphanumeric() || "-_./=:,+@%".contains(c));
+        if plain {
+            arg.to_string()
+        } else {
+            f!("'{}'", arg.replace('\'', r"'\''"))
+        }
+    };
+    [program.to_string()]
+        .into_iter()
+        .chain(args.iter().map(|arg| quote(arg)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn shell(config: ShellConfig) -> Shell {
+        let approval = ApprovalConfig {
+            tools: HashMap::from([(String::from("shell"), ToolPolicy::Allow)]),
+            ..Default::default()
+        };
+        Shell::new(config, &approval)
+    }
+
+    fn params(program: &str, args: &[&str]) -> ShellParams {
+        ShellParams {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            dry_run: false,
+        }
+    }
+
+    #[async_std::test]
+    async fn test_shell_runs_allowlisted_commands() {
+        let shell = shell(ShellConfig {
+            allowlist: ["env", "seq", "sh"].map(String::from).to_vec(),
+            timeout_secs: 1,
+            max_output_bytes: 4096,
+            ..Default::default()
+        });
+
+        assert!(matches!(
+            shell.call(params("rm", &["-rf", "/tmp/x"])).await,
+            Err(ShellError::NotAllowed(_))
+        ));
+
+        let output = shell.call(params("env", &[])).await.unwrap();
+        assert_eq!(output.exit_code, Some(0));
+        let mut passed = output
+            .stdout
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(name, _)| name.to_string())
+            .collect::<Vec<_>>();
+        passed.sort();
+        let mut expected = output.environment.clone();
+        expected.sort();
+        assert!(expected.contains(&String::from("PATH")));
+        assert_eq!(passed, expected);
+
+        let output = shell.call(params("seq", &["1", "10000"])).await.unwrap();
+        assert!(output.stdout.ends_with("more bytes left out]"));
+
+        // The process left running in the background is killed along with the command
+        let output = shell
+            .call(params("sh", &["-c", "sleep 5 & echo started; sleep 5"]))
+            .await
+            .unwrap();
+        assert!(output.timed_out && output.exit_code.is_none());
+        assert_eq!(output.stdout, "started\n");
+
+        let output = shell
+            .call(ShellParams {
+                dry_run: true,
+                ..params("seq", &["it's", "3"])
+            })
+            .await
+            .unwrap();
+        assert_eq!(output.command, r"seq 'it'\''s' 3");
+        assert!(output.dry_run && output.exit_code.is_none());
+    }
+
+    #[test]
+    fn test_only_explicit_config_allows_any_program() {
+        let shell = Shell::new(ShellConfig::default(), &ApprovalConfig::default());
+        assert!(!shell.any_program);
+        assert_eq!(
+            shell.call_risk(&json!({ "program": "ls" })),
+            RiskLevel::High
+        );
+        assert_eq!(
+            shell.call_risk(&json!({ "program": "ls", "dry_run": true })),
+            RiskLevel::Low
+        );
+
+        let approval = ApprovalConfig {
+            categories: HashMap::from([(String::from("System"), ToolPolicy::Ask)]),
+            ..Default::default()
+        };
+        assert!(Shell::new(ShellConfig::default(), &approval).any_program);
+        let config = ShellConfig {
+            allow_any: true,
+            ..Default::default()
+        };
+        assert!(Shell::new(config, &ApprovalConfig::default()).any_program);
+    }
+}
@@ -0,0 +1,77 @@
+//! Append-only logs of JSON records, one record per line.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::prelude::*;
+
+/// Records appended to a JSON lines file, or kept in memory when the log has no file.
+pub struct JsonLog<T> {
+    path: Option<PathBuf>,
+    // Records of a log without a file; file writes are serialized through it too
+    records: Mutex<Vec<T>>,
+}
+
+impl<T> JsonLog<T>
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    pub fn with_file(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            records: Mutex::default(),
+        }
+    }
+
+    /// A log that never touches the filesystem.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            records: Mutex::default(),
+        }
+    }
+
+    /// Appends a record to the end of the log.
+    pub fn append(&self, record: T) -> Result<()> {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(path) = &self.path else {
+            records.push(record);
+            return Ok(());
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        Ok(())
+    }
+
+    /// Every record, oldest first. Lines that cannot be read, such as one cut short by a
+    /// crash, are skipped.
+    pub fn records(&self) -> Result<Vec<T>> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(path) = &self.path else {
+            return Ok(records.clone());
+        };
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut parsed = Vec::new();
+        for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(record) => parsed.push(record),
+                Err(e) => warn!(
+                    "Skipping unreadable record on line {} of {}: {}",
+                    number + 1,
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        Ok(parsed)
+    }
+}
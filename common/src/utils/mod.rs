@@ -1,8 +1,20 @@
 //! General utility functions for lyn-core.
 
+mod jsonl;
+
 use crate::prelude::*;
+pub use jsonl::JsonLog;
 use ndarray::ArrayView1; // Combined use statement
 
+/// A directory for a test, removed along with its contents once dropped.
+#[cfg(test)]
+pub(crate) fn temp_dir() -> tempfile::TempDir {
+    tempfile::Builder::new()
+        .prefix("lyn-")
+        .tempdir()
+        .expect("the temporary directory can be created")
+}
+
 /// Calculates the cosine similarity between two vectors.
 ///
 /// Returns `Ok(similarity)` where similarity is a value between -1.0 and 1.0,
@@ -41,6 +53,13 @@ pub fn cosine_similarity(v1: &[f32], v2: &[f32]) -> Result<f32> {
     Ok(similarity.clamp(-1.0, 1.0))
 }
 
+/// Roughly estimates the number of tokens a text occupies in a model's context.
+///
+/// Uses the common approximation of four characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,4 +127,11 @@ mod tests {
         let v3 = [0.0, 0.0];
         assert_relative_eq!(cosine_similarity(&v1, &v3).unwrap(), 0.0, epsilon = 1e-6);
     }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+    }
 }
@@ -1,4 +1,4 @@
-use crate::commands::{chat, settings};
+use crate::commands::{chat, sessions, settings};
 use crate::error::AppError;
 use common::core::Engine;
 use tracing::{error, info};
@@ -17,17 +17,29 @@ pub async fn run_app() -> Result<(), AppError> {
             return Err(AppError::EngineInitFailed(e.to_string()));
         }
     };
+    if let Err(e) = engine.probe().await {
+        error!("Providers are not usable: {}", e);
+        return Err(AppError::EngineInitFailed(e.to_string()));
+    }
 
     let builder = tauri::Builder::default()
         .setup(move |app| {
             // Make engine available to command handlers
             app.manage(engine.clone());
+            app.manage(chat::ActiveMessages::default());
             // Remove manual creation of the main window; Tauri creates it by default
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             chat::send_message,
             chat::send_prompt,
+            chat::cancel_message,
+            chat::approve_tool_call,
+            chat::deny_tool_call,
+            sessions::create_session,
+            sessions::list_sessions,
+            sessions::resume_session,
+            sessions::drop_session,
             settings::get_settings,
             settings::update_settings,
             settings::reset_settings,
@@ -1,10 +1,13 @@
 use futures_util::StreamExt;
-use common::core::Engine;
+use common::core::{CancellationToken, Engine, EngineEvent, EngineStream, SessionId};
+use common::memory::ScoredMemory;
 use serde::Serialize;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tauri::{Emitter, Runtime, State, Window}; // Updated imports
 use uuid::Uuid;
 
+use super::events::{chat as events, tool as tool_events};
 use super::pii::{sanitize_text, scan_for_pii};
 
 #[derive(Debug, Serialize)]
@@ -12,18 +15,49 @@ pub struct ChatResponse {
     id: String,
     content: String,
     complete: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    memories: Vec<ScoredMemory>,
+}
+
+/// Cancellation tokens of the responses being streamed, by message id.
+#[derive(Clone, Default)]
+pub struct ActiveMessages(Arc<Mutex<HashMap<String, CancellationToken>>>);
+
+impl ActiveMessages {
+    /// Registers a response and returns the token that stops it.
+    fn start(&self, message_id: &str) -> CancellationToken {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(message_id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    fn finish(&self, message_id: &str) -> Option<CancellationToken> {
+        self.0.lock().unwrap().remove(message_id)
+    }
+}
+
+/// A non-text event of a streamed response, tagged with the message it belongs to.
+#[derive(Debug, Serialize)]
+struct ChatEvent<'a> {
+    id: &'a str,
+    #[serde(flatten)]
+    event: &'a EngineEvent,
 }
 
 // Changed from #[tauri::command] to #[command]
 #[tauri::command]
 pub async fn send_message<R: Runtime>(
     engine: State<'_, Arc<Engine>>,
+    active: State<'_, ActiveMessages>,
     window: Window<R>,
+    session_id: SessionId,
     message: String,
     auto_redact: bool,
 ) -> Result<String, String> {
     let message_id = Uuid::new_v4().to_string();
-    let engine_clone = engine.inner().clone();
 
     let pii_detections = scan_for_pii(message.clone()).await?;
     if !pii_detections.is_empty() {
@@ -35,121 +69,132 @@ pub async fn send_message<R: Runtime>(
         // If auto-redact is enabled, sanitize the message
         if auto_redact {
             let sanitized = sanitize_text(message.clone(), pii_detections).await?;
-            process_message(engine, window.clone(), sanitized, message_id.clone()).await;
+            process_message(
+                engine.inner(),
+                active.inner(),
+                window,
+                session_id,
+                sanitized,
+                message_id.clone(),
+            );
         } else {
             return Ok(message_id);
         }
     } else {
         // No PII detected, process normally
         process_message(
-            engine,
-            window.clone(),
-            message.clone(),
+            engine.inner(),
+            active.inner(),
+            window,
+            session_id,
+            message,
             message_id.clone(),
-        )
-        .await;
+        );
     }
 
-    let message_id_clone = message_id.clone();
-    // Updated spawn syntax
-    let message_clone = message.clone();
-    tauri::async_runtime::spawn(async move {
-        match engine_clone.process_prompt_stream(message_clone).await {
-            Ok(mut stream) => {
-                let mut accumulated_content = String::new();
-
-                while let Some(result) = stream.next().await {
-                    match result {
-                        Ok(chunk) => {
-                            accumulated_content.push_str(&chunk);
-
-                            // Updated event emission
-                            window
-                                .emit(
-                                    "chat:chunk",
-                                    &ChatResponse {
-                                        id: message_id_clone.clone(),
-                                        content: accumulated_content.clone(),
-                                        complete: false,
-                                    },
-                                )
-                                .unwrap_or_default();
-                        }
-                        Err(e) => {
-                            window
-                                .emit("chat:error", format!("Error: {}", e))
-                                .unwrap_or_default();
-                            return;
-                        }
-                    }
-                }
-
-                // Updated event emission
-                window
-                    .emit(
-                        "chat:complete",
-                        &ChatResponse {
-                            id: message_id_clone,
-                            content: accumulated_content,
-                            complete: true,
-                        },
-                    )
-                    .unwrap_or_default();
-            }
-            Err(e) => {
-                window
-                    .emit("chat:error", format!("Failed to start stream: {}", e))
-                    .unwrap_or_default();
-            }
-        }
-    });
-
     Ok(message_id)
 }
 
 #[tauri::command]
 pub async fn confirm_send_with_pii<R: Runtime>(
     engine: State<'_, Arc<Engine>>,
+    active: State<'_, ActiveMessages>,
     window: Window<R>,
+    session_id: SessionId,
     message: String,
     message_id: String,
 ) -> Result<(), String> {
-    process_message(engine, window, message, message_id).await;
+    process_message(
+        engine.inner(),
+        active.inner(),
+        window,
+        session_id,
+        message,
+        message_id,
+    );
     Ok(())
 }
 
 #[tauri::command]
 pub async fn confirm_send_redacted<R: Runtime>(
     engine: State<'_, Arc<Engine>>,
+    active: State<'_, ActiveMessages>,
     window: Window<R>,
+    session_id: SessionId,
     message: String,
     message_id: String,
 ) -> Result<(), String> {
     let pii_detections = scan_for_pii(message.clone()).await?;
     let sanitized = sanitize_text(message, pii_detections).await?;
 
-    process_message(engine, window, sanitized, message_id).await;
+    process_message(
+        engine.inner(),
+        active.inner(),
+        window,
+        session_id,
+        sanitized,
+        message_id,
+    );
     Ok(())
 }
 
 #[tauri::command]
 pub async fn send_prompt(
     engine: State<'_, Arc<Engine>>,
+    session_id: SessionId,
     prompt: String,
 ) -> Result<ChatResponse, String> {
-    match engine.process_prompt(&prompt).await {
+    match engine.process_prompt(session_id, &prompt).await {
         Ok(response) => Ok(ChatResponse {
             id: Uuid::new_v4().to_string(),
-            content: response,
+            content: response.content,
             complete: true,
+            memories: response.memories,
         }),
         Err(e) => Err(format!("Failed to process prompt: {}", e)),
     }
 }
 
-async fn process_message<R: Runtime>(
-    engine: State<'_, Arc<Engine>>,
+/// Stops the response to a message. The partial answer is kept in the session and sent as
+/// `chat:interrupted`.
+#[tauri::command]
+pub fn cancel_message(
+    active: State<'_, ActiveMessages>,
+    message_id: String,
+) -> Result<(), String> {
+    match active.finish(&message_id) {
+        Some(cancel) => {
+            cancel.cancel();
+            Ok(())
+        }
+        None => Err(format!("No response in progress for message {}", message_id)),
+    }
+}
+
+/// Lets a tool call announced by `tool:approval_required` run.
+#[tauri::command]
+pub fn approve_tool_call(engine: State<'_, Arc<Engine>>, request_id: Uuid) -> Result<(), String> {
+    engine
+        .answer_approval(request_id, true)
+        .map_err(|e| format!("Failed to approve tool call: {}", e))
+}
+
+/// Refuses a tool call announced by `tool:approval_required`; the model is told it was
+/// denied.
+#[tauri::command]
+pub fn deny_tool_call(engine: State<'_, Arc<Engine>>, request_id: Uuid) -> Result<(), String> {
+    engine
+        .answer_approval(request_id, false)
+        .map_err(|e| format!("Failed to deny tool call: {}", e))
+}
+
+/// Answers a message in the background, so the caller can return its id and
+/// `cancel_message` can stop it while it streams.
+fn process_message<R: Runtime>(
+    engine: &Arc<Engine>,
+    active: &ActiveMessages,
     window: Window<R>,
+    session_id: SessionId,
     message: String,
     message_id: String,
 ) {
@@ -164,52 +209,110 @@ async fn process_message<R: Runtime>(
         )
         .unwrap_or_default();
 
-    match engine.process_prompt_stream(message).await {
-        Ok(mut stream) => {
-            let mut accumulated_content = String::new();
-
-            while let Some(result) = stream.next().await {
-                match result {
-                    Ok(chunk) => {
-                        accumulated_content.push_str(&chunk);
-
-                        // Updated event emission
-                        window
-                            .emit(
-                                "chat:chunk",
-                                &ChatResponse {
-                                    id: message_id.clone(),
-                                    content: accumulated_content.clone(),
-                                    complete: false,
-                                },
-                            )
-                            .unwrap_or_default();
-                    }
-                    Err(e) => {
-                        window
-                            .emit("chat:error", format!("Error: {}", e))
-                            .unwrap_or_default();
-                        return;
-                    }
-                }
+    // Registered before returning so the id can be cancelled straight away
+    let cancel = active.start(&message_id);
+    let (engine, active) = (engine.clone(), active.clone());
+    tauri::async_runtime::spawn(async move {
+        match engine.process_prompt_stream(session_id, message, cancel).await {
+            Ok(stream) => emit_events(&window, &message_id, stream).await,
+            Err(e) => {
+                window
+                    .emit("chat:error", format!("Failed to start stream: {}", e))
+                    .unwrap_or_default();
             }
-
-            // Updated event emission
-            window
-                .emit(
-                    "chat:complete",
-                    &ChatResponse {
-                        id: message_id,
-                        content: accumulated_content,
-                        complete: true,
-                    },
-                )
-                .unwrap_or_default();
         }
-        Err(e) => {
-            window
-                .emit("chat:error", format!("Failed to start stream: {}", e))
-                .unwrap_or_default();
+        active.finish(&message_id);
+    });
+}
+
+/// Forwards the events of a streamed response to the frontend as `chat:*` events.
+async fn emit_events<R: Runtime>(
+    window: &Window<R>,
+    message_id: &str,
+    mut stream: EngineStream,
+) {
+    let mut accumulated_content = String::new();
+
+    while let Some(event) = stream.next().await {
+        match event {
+            EngineEvent::MemoriesRecalled(_) => {
+                emit_chat_event(window, events::MEMORIES, message_id, &event)
+            }
+            EngineEvent::Compacted { .. } => {
+                emit_chat_event(window, events::COMPACTED, message_id, &event)
+            }
+            EngineEvent::TextDelta(chunk) => {
+                accumulated_content.push_str(&chunk);
+                window
+                    .emit(
+                        events::CHUNK,
+                        &ChatResponse {
+                            id: message_id.to_string(),
+                            content: accumulated_content.clone(),
+                            complete: false,
+                            memories: Vec::new(),
+                        },
+                    )
+                    .unwrap_or_default();
+            }
+            EngineEvent::Reasoning(_) => {
+                emit_chat_event(window, events::REASONING, message_id, &event)
+            }
+            EngineEvent::ToolCallStarted { .. } => {
+                emit_chat_event(window, events::TOOL_CALL, message_id, &event)
+            }
+            EngineEvent::ApprovalRequired(_) => {
+                emit_chat_event(window, tool_events::APPROVAL_REQUIRED, message_id, &event)
+            }
+            EngineEvent::ToolResult { .. } => {
+                emit_chat_event(window, events::TOOL_RESULT, message_id, &event)
+            }
+            EngineEvent::Usage(_) => {
+                emit_chat_event(window, events::USAGE, message_id, &event)
+            }
+            EngineEvent::Done { content } => {
+                window
+                    .emit(
+                        events::COMPLETE,
+                        &ChatResponse {
+                            id: message_id.to_string(),
+                            content,
+                            complete: true,
+                            memories: Vec::new(),
+                        },
+                    )
+                    .unwrap_or_default();
+            }
+            EngineEvent::Interrupted { content } => {
+                window
+                    .emit(
+                        events::INTERRUPTED,
+                        &ChatResponse {
+                            id: message_id.to_string(),
+                            content,
+                            complete: false,
+                            memories: Vec::new(),
+                        },
+                    )
+                    .unwrap_or_default();
+            }
+            EngineEvent::Error(e) => {
+                window
+                    .emit(events::ERROR, format!("Error: {}", e))
+                    .unwrap_or_default();
+                return;
+            }
         }
     }
 }
+
+fn emit_chat_event<R: Runtime>(
+    window: &Window<R>,
+    name: &str,
+    message_id: &str,
+    event: &EngineEvent,
+) {
+    window
+        .emit(name, &ChatEvent { id: message_id, event })
+        .unwrap_or_default();
+}
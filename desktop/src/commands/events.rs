@@ -2,12 +2,33 @@ pub mod chat {
     /// Emitted when a chat session starts.
     pub const START: &str = "chat:start";
 
+    /// Emitted with the stored memories a response is based on.
+    pub const MEMORIES: &str = "chat:memories";
+
+    /// Emitted with the running summary when older messages were compacted into it.
+    pub const COMPACTED: &str = "chat:compacted";
+
     /// Emitted when a chunk of the response is available.
     pub const CHUNK: &str = "chat:chunk";
 
+    /// Emitted when a piece of the model's thought process is available.
+    pub const REASONING: &str = "chat:reasoning";
+
+    /// Emitted when the model starts a tool call.
+    pub const TOOL_CALL: &str = "chat:tool_call";
+
+    /// Emitted when a tool call finishes.
+    pub const TOOL_RESULT: &str = "chat:tool_result";
+
+    /// Emitted with the resources consumed by a response.
+    pub const USAGE: &str = "chat:usage";
+
     /// Emitted when the response is complete.
     pub const COMPLETE: &str = "chat:complete";
 
+    /// Emitted with the partial response when it was cancelled.
+    pub const INTERRUPTED: &str = "chat:interrupted";
+
     /// Emitted when an error occurs.
     pub const ERROR: &str = "chat:error";
 
@@ -15,6 +36,11 @@ pub mod chat {
     pub const PII_DETECTED: &str = "chat:pii_detected";
 }
 
+pub mod tool {
+    /// Emitted when a tool call waits for the user to approve or deny it.
+    pub const APPROVAL_REQUIRED: &str = "tool:approval_required";
+}
+
 pub mod settings {
     /// Emitted when settings are updated.
     pub const UPDATED: &str = "settings:updated";
@@ -1,4 +1,5 @@
 pub mod chat;
 pub mod events;
 pub mod pii;
+pub mod sessions;
 pub mod settings;
@@ -0,0 +1,58 @@
+use common::core::{Engine, SessionId, SessionInfo, TranscriptEntry};
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::State;
+
+#[derive(Debug, Serialize)]
+pub struct ResumedSession {
+    id: SessionId,
+    title: String,
+    /// Running summary of the compacted start of the conversation
+    summary: Option<String>,
+    messages: Vec<TranscriptEntry>,
+}
+
+#[tauri::command]
+pub async fn create_session(engine: State<'_, Arc<Engine>>) -> Result<SessionId, String> {
+    engine
+        .create_session()
+        .await
+        .map_err(|e| format!("Failed to create session: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_sessions(engine: State<'_, Arc<Engine>>) -> Result<Vec<SessionInfo>, String> {
+    engine
+        .list_sessions()
+        .await
+        .map_err(|e| format!("Failed to list sessions: {}", e))
+}
+
+#[tauri::command]
+pub async fn resume_session(
+    engine: State<'_, Arc<Engine>>,
+    session_id: SessionId,
+) -> Result<ResumedSession, String> {
+    let session = engine
+        .resume_session(session_id)
+        .await
+        .map_err(|e| format!("Failed to resume session: {}", e))?;
+
+    Ok(ResumedSession {
+        id: session.id,
+        title: session.title(),
+        summary: session.summary.clone(),
+        messages: session.transcript(),
+    })
+}
+
+#[tauri::command]
+pub async fn drop_session(
+    engine: State<'_, Arc<Engine>>,
+    session_id: SessionId,
+) -> Result<(), String> {
+    engine
+        .drop_session(session_id)
+        .await
+        .map_err(|e| format!("Failed to drop session: {}", e))
+}